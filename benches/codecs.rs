@@ -0,0 +1,103 @@
+//! Benchmarks for encoding and decoding large values.
+//!
+//! To compare against another revision, run
+//! `cargo bench --bench codecs -- --save-baseline before` there, then
+//! `cargo bench --bench codecs -- --baseline before` here.
+
+use criterion::{
+    black_box,
+    criterion_group,
+    criterion_main,
+    Criterion,
+    Throughput,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use serialization::{
+    from_bytes,
+    to_bytes,
+    Packed,
+};
+use std::collections::HashMap;
+
+// This is the number of elements in each benchmarked collection.
+const ELEMENTS: usize = 100_000;
+
+#[derive(Deserialize, Serialize)]
+struct Record {
+    id: u32,
+    delta: i64,
+    reading: f64,
+    name: String,
+    flags: Vec<u16>,
+}
+
+// Produce integers spread evenly over every encoded length, from one byte
+// up to ten.
+fn integers() -> Vec<u64> {
+    (0..ELEMENTS as u64)
+        .map(|i| i.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> (i % 64))
+        .collect()
+}
+
+fn records() -> Vec<Record> {
+    (0..ELEMENTS as u32)
+        .map(|i| Record {
+            id: i,
+            delta: -i64::from(i) * 1000,
+            reading: f64::from(i) / 7.0,
+            name: format!("sensor-{i}"),
+            flags: vec![i as u16; (i % 4) as usize],
+        })
+        .collect()
+}
+
+fn rows() -> Vec<Vec<u32>> {
+    (0..ELEMENTS as u32 / 16).map(|i| (i..i + 16).collect()).collect()
+}
+
+fn samples() -> Vec<f32> {
+    (0..ELEMENTS * 10).map(|i| (i as f32).sin()).collect()
+}
+
+fn names() -> HashMap<u32, String> {
+    (0..ELEMENTS as u32).map(|i| (i, format!("name-{i}"))).collect()
+}
+
+fn bench<T>(
+    c: &mut Criterion,
+    name: &str,
+    value: &T,
+) where
+    T: Serialize + for<'de> Deserialize<'de>,
+{
+    let bytes = to_bytes(value).unwrap();
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Bytes(bytes.len() as u64));
+    group.bench_function("encode", |b| {
+        b.iter(|| to_bytes(black_box(value)).unwrap());
+    });
+    group.bench_function("decode", |b| {
+        b.iter(|| from_bytes::<T>(black_box(&bytes)).unwrap());
+    });
+    group.finish();
+}
+
+fn codecs(c: &mut Criterion) {
+    bench(c, "vec_u64", &integers());
+    bench(
+        c,
+        "vec_i64",
+        &integers().into_iter().map(|i| i as i64).collect::<Vec<_>>(),
+    );
+    bench(c, "records", &records());
+    bench(c, "rows", &rows());
+    bench(c, "map", &names());
+    bench(c, "vec_f32", &samples());
+    bench(c, "packed_f32", &Packed(samples()));
+}
+
+criterion_group!(benches, codecs);
+criterion_main!(benches);
@@ -0,0 +1,22 @@
+// This generates the C header declaring the functions exported by the
+// `capi` module into the output directory.  The copy in `include` is
+// checked against it by `tests/capi.rs`, so that it always matches them.
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "capi")]
+    {
+        println!("cargo:rerun-if-changed=cbindgen.toml");
+        println!("cargo:rerun-if-changed=src/capi.rs");
+        let config = cbindgen::Config::from_file("cbindgen.toml")
+            .expect("unable to read cbindgen.toml");
+        cbindgen::Builder::new()
+            .with_config(config)
+            .with_src("src/capi.rs")
+            .generate()
+            .expect("unable to generate C header")
+            .write_to_file(
+                std::path::Path::new(&std::env::var_os("OUT_DIR").unwrap())
+                    .join("serialization.h"),
+            );
+    }
+}
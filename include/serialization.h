@@ -0,0 +1,463 @@
+#ifndef SERIALIZATION_H
+#define SERIALIZATION_H
+
+/* This file is generated from src/capi.rs when the crate is built with the
+ * `capi` feature.  Do not edit it by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * This identifies the type of the contents of a value.
+ */
+typedef enum SerializationKind {
+  /**
+   * The value is empty.
+   */
+  SERIALIZATION_KIND_EMPTY,
+  /**
+   * The value holds a boolean.
+   */
+  SERIALIZATION_KIND_BOOLEAN,
+  /**
+   * The value holds a signed 32-bit integer.
+   */
+  SERIALIZATION_KIND_INTEGER,
+  /**
+   * The value holds an unsigned 64-bit integer.
+   */
+  SERIALIZATION_KIND_UNSIGNED_INTEGER,
+  /**
+   * The value holds a double-precision floating-point number.
+   */
+  SERIALIZATION_KIND_DECIMAL,
+  /**
+   * The value holds a string.
+   */
+  SERIALIZATION_KIND_STRING,
+  /**
+   * The value holds a vector of other values.
+   */
+  SERIALIZATION_KIND_VECTOR,
+  /**
+   * The value holds a set of other values with distinct names.
+   */
+  SERIALIZATION_KIND_COLLECTION,
+  /**
+   * The value holds an IPv4 address.
+   */
+  SERIALIZATION_KIND_IP_ADDRESS,
+  /**
+   * The value holds a vector of signed 32-bit integers.
+   */
+  SERIALIZATION_KIND_INTEGER_VECTOR,
+  /**
+   * The value holds a vector of unsigned 32-bit integers.
+   */
+  SERIALIZATION_KIND_UNSIGNED_INTEGER_VECTOR,
+} SerializationKind;
+
+/**
+ * This is the outcome of a function which can fail.
+ */
+typedef enum SerializationStatus {
+  /**
+   * The function succeeded.
+   */
+  SERIALIZATION_STATUS_OK,
+  /**
+   * A pointer which is required was null.
+   */
+  SERIALIZATION_STATUS_NULL_ARGUMENT,
+  /**
+   * A value did not have the kind which the function requires.
+   */
+  SERIALIZATION_STATUS_WRONG_KIND,
+  /**
+   * A string was not valid UTF-8.
+   */
+  SERIALIZATION_STATUS_INVALID_UTF8,
+  /**
+   * An index was not less than the number of elements.
+   */
+  SERIALIZATION_STATUS_OUT_OF_RANGE,
+  /**
+   * A collection had no value with the given name.
+   */
+  SERIALIZATION_STATUS_NOT_FOUND,
+  /**
+   * A value could not be encoded.
+   */
+  SERIALIZATION_STATUS_ENCODE_FAILED,
+  /**
+   * The bytes given did not hold the encoding of a value.
+   */
+  SERIALIZATION_STATUS_DECODE_FAILED,
+} SerializationStatus;
+
+/**
+ * This is a dynamic value, which holds an `Object` and is opaque to C.
+ */
+typedef struct SerializationValue SerializationValue;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Return a new empty value.
+ */
+struct SerializationValue *serialization_value_new_empty(void);
+
+/**
+ * Return a new value holding the given boolean.
+ */
+struct SerializationValue *serialization_value_new_boolean(bool value);
+
+/**
+ * Return a new value holding the given signed integer.
+ */
+struct SerializationValue *serialization_value_new_integer(int32_t value);
+
+/**
+ * Return a new value holding the given unsigned integer.
+ */
+struct SerializationValue *serialization_value_new_unsigned_integer(uint64_t value);
+
+/**
+ * Return a new value holding the given floating-point number.
+ */
+struct SerializationValue *serialization_value_new_decimal(double value);
+
+/**
+ * Return a new value holding a copy of the given string, or null if the
+ * string is not valid UTF-8.
+ *
+ * # Safety
+ *
+ * `data` must point to `len` readable bytes, or be null if `len` is zero.
+ */
+struct SerializationValue *serialization_value_new_string(const char *data, size_t len);
+
+/**
+ * Return a new value holding an empty vector of values.
+ */
+struct SerializationValue *serialization_value_new_vector(void);
+
+/**
+ * Return a new value holding an empty collection of values.
+ */
+struct SerializationValue *serialization_value_new_collection(void);
+
+/**
+ * Return a new value holding the given IPv4 address, whose most
+ * significant byte is the first octet of its dotted rendering.
+ */
+struct SerializationValue *serialization_value_new_ip_address(uint32_t address);
+
+/**
+ * Return a new value holding a copy of the given signed integers.
+ *
+ * # Safety
+ *
+ * `data` must point to `len` readable integers, or be null if `len` is
+ * zero.
+ */
+struct SerializationValue *serialization_value_new_integer_vector(const int32_t *data, size_t len);
+
+/**
+ * Return a new value holding a copy of the given unsigned integers.
+ *
+ * # Safety
+ *
+ * `data` must point to `len` readable integers, or be null if `len` is
+ * zero.
+ */
+struct SerializationValue *serialization_value_new_unsigned_integer_vector(const uint32_t *data,
+                                                                           size_t len);
+
+/**
+ * Free the given value, along with any values it holds.  Nothing is done
+ * if the pointer is null.
+ *
+ * # Safety
+ *
+ * `value` must be null or have been returned by this library, and must
+ * not be owned by another value, nor used again afterwards.
+ */
+void serialization_value_free(struct SerializationValue *value);
+
+/**
+ * Append the given element to the given vector value.  The vector takes
+ * ownership of the element, which is freed if it cannot be appended.
+ *
+ * # Safety
+ *
+ * `vector` must be null or point to a value, and `element` must be null
+ * or own a value which is not the vector or held by it.
+ */
+enum SerializationStatus serialization_vector_push(struct SerializationValue *vector,
+                                                   struct SerializationValue *element);
+
+/**
+ * Put the given element in the given collection value under the given
+ * name, replacing any value it had under that name.  The collection takes
+ * ownership of the element, which is freed if it cannot be put in the
+ * collection.
+ *
+ * # Safety
+ *
+ * `collection` must be null or point to a value, `name` must point to
+ * `name_len` readable bytes, and `element` must be null or own a value
+ * which is not the collection or held by it.
+ */
+enum SerializationStatus serialization_collection_set(struct SerializationValue *collection,
+                                                      const char *name,
+                                                      size_t name_len,
+                                                      struct SerializationValue *element);
+
+/**
+ * Encode the given value.  On success, the encoding is stored through
+ * `data` and its length through `len`, and must be given back with
+ * `serialization_bytes_free`.
+ *
+ * # Safety
+ *
+ * `value` must be null or point to a value, and `data` and `len` must be
+ * null or writable.
+ */
+enum SerializationStatus serialization_encode(const struct SerializationValue *value,
+                                              uint8_t **data,
+                                              size_t *len);
+
+/**
+ * Free an encoding returned by `serialization_encode`.  Nothing is done if
+ * the pointer is null.
+ *
+ * # Safety
+ *
+ * `data` must be null or have been returned by `serialization_encode`
+ * along with `len`, and must not be used again afterwards.
+ */
+void serialization_bytes_free(uint8_t *data, size_t len);
+
+/**
+ * Decode a value from the given bytes.  On success, a new value is stored
+ * through `value`.
+ *
+ * # Safety
+ *
+ * `data` must point to `len` readable bytes, or be null if `len` is zero,
+ * and `value` must be null or writable.
+ */
+enum SerializationStatus serialization_decode(const uint8_t *data,
+                                              size_t len,
+                                              struct SerializationValue **value);
+
+/**
+ * Return the kind of the given value, treating a null pointer as an empty
+ * value.
+ *
+ * # Safety
+ *
+ * `value` must be null or point to a value.
+ */
+enum SerializationKind serialization_value_kind(const struct SerializationValue *value);
+
+/**
+ * Store the boolean held by the given value through `out`.
+ *
+ * # Safety
+ *
+ * `value` must be null or point to a value, and `out` must be null or
+ * writable.
+ */
+enum SerializationStatus serialization_value_boolean(const struct SerializationValue *value,
+                                                     bool *out);
+
+/**
+ * Store the signed integer held by the given value through `out`.
+ *
+ * # Safety
+ *
+ * `value` must be null or point to a value, and `out` must be null or
+ * writable.
+ */
+enum SerializationStatus serialization_value_integer(const struct SerializationValue *value,
+                                                     int32_t *out);
+
+/**
+ * Store the unsigned integer held by the given value through `out`.
+ *
+ * # Safety
+ *
+ * `value` must be null or point to a value, and `out` must be null or
+ * writable.
+ */
+enum SerializationStatus serialization_value_unsigned_integer(const struct SerializationValue *value,
+                                                              uint64_t *out);
+
+/**
+ * Store the floating-point number held by the given value through `out`.
+ *
+ * # Safety
+ *
+ * `value` must be null or point to a value, and `out` must be null or
+ * writable.
+ */
+enum SerializationStatus serialization_value_decimal(const struct SerializationValue *value,
+                                                     double *out);
+
+/**
+ * Store the start and length in bytes of the string held by the given
+ * value through `data` and `len`.  The string is borrowed from the value.
+ *
+ * # Safety
+ *
+ * `value` must be null or point to a value, and `data` and `len` must be
+ * null or writable.
+ */
+enum SerializationStatus serialization_value_string(const struct SerializationValue *value,
+                                                    const char **data,
+                                                    size_t *len);
+
+/**
+ * Store the IPv4 address held by the given value through `out`, with the
+ * first octet of its dotted rendering in the most significant byte.
+ *
+ * # Safety
+ *
+ * `value` must be null or point to a value, and `out` must be null or
+ * writable.
+ */
+enum SerializationStatus serialization_value_ip_address(const struct SerializationValue *value,
+                                                        uint32_t *out);
+
+/**
+ * Store the number of elements held by the given value through `out`.
+ * This is the number of values in a vector or collection, or of integers
+ * in a vector of integers.
+ *
+ * # Safety
+ *
+ * `value` must be null or point to a value, and `out` must be null or
+ * writable.
+ */
+enum SerializationStatus serialization_value_len(const struct SerializationValue *value,
+                                                 size_t *out);
+
+/**
+ * Store the element at the given index of the given vector value through
+ * `out`.  The element is borrowed from the vector.
+ *
+ * # Safety
+ *
+ * `vector` must be null or point to a value, and `out` must be null or
+ * writable.
+ */
+enum SerializationStatus serialization_vector_get(const struct SerializationValue *vector,
+                                                  size_t index,
+                                                  const struct SerializationValue **out);
+
+/**
+ * Store the integer at the given index of the given vector of signed
+ * integers through `out`.
+ *
+ * # Safety
+ *
+ * `vector` must be null or point to a value, and `out` must be null or
+ * writable.
+ */
+enum SerializationStatus serialization_integer_vector_get(const struct SerializationValue *vector,
+                                                          size_t index,
+                                                          int32_t *out);
+
+/**
+ * Store the integer at the given index of the given vector of unsigned
+ * integers through `out`.
+ *
+ * # Safety
+ *
+ * `vector` must be null or point to a value, and `out` must be null or
+ * writable.
+ */
+enum SerializationStatus serialization_unsigned_integer_vector_get(const struct SerializationValue *vector,
+                                                                   size_t index,
+                                                                   uint32_t *out);
+
+/**
+ * Store the value which the given collection value has under the given
+ * name through `out`.  The value is borrowed from the collection.
+ *
+ * # Safety
+ *
+ * `collection` must be null or point to a value, `name` must point to
+ * `name_len` readable bytes, and `out` must be null or writable.
+ */
+enum SerializationStatus serialization_collection_get(const struct SerializationValue *collection,
+                                                      const char *name,
+                                                      size_t name_len,
+                                                      const struct SerializationValue **out);
+
+/**
+ * Store the name and value of the entry at the given index of the given
+ * collection value, in order of their names, through `name`, `name_len`
+ * and `out`.  Both are borrowed from the collection.  This takes time in
+ * proportion to the index, so `serialization_collection_first` and
+ * `serialization_collection_next` are better suited to visiting every
+ * entry.
+ *
+ * # Safety
+ *
+ * `collection` must be null or point to a value, and `name`, `name_len`
+ * and `out` must be null or writable.
+ */
+enum SerializationStatus serialization_collection_entry(const struct SerializationValue *collection,
+                                                        size_t index,
+                                                        const char **name,
+                                                        size_t *name_len,
+                                                        const struct SerializationValue **out);
+
+/**
+ * Store the name and value of the entry with the first name in the given
+ * collection value through `name`, `name_len` and `out`.  Both are
+ * borrowed from the collection.  `OutOfRange` is returned if the
+ * collection is empty.
+ *
+ * # Safety
+ *
+ * `collection` must be null or point to a value, and `name`, `name_len`
+ * and `out` must be null or writable.
+ */
+enum SerializationStatus serialization_collection_first(const struct SerializationValue *collection,
+                                                        const char **name,
+                                                        size_t *name_len,
+                                                        const struct SerializationValue **out);
+
+/**
+ * Store the name and value of the entry whose name follows `after` in the
+ * given collection value through `name`, `name_len` and `out`.  Both are
+ * borrowed from the collection.  `OutOfRange` is returned if `after` is
+ * the last name, and `NotFound` if the collection has no such name.
+ *
+ * # Safety
+ *
+ * `collection` must be null or point to a value, `after` must point to
+ * `after_len` readable bytes, and `name`, `name_len` and `out` must be
+ * null or writable.
+ */
+enum SerializationStatus serialization_collection_next(const struct SerializationValue *collection,
+                                                       const char *after,
+                                                       size_t after_len,
+                                                       const char **name,
+                                                       size_t *name_len,
+                                                       const struct SerializationValue **out);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* SERIALIZATION_H */
@@ -0,0 +1,673 @@
+//! This crate provides the `Encode` and `Decode` derive macros of the
+//! [`serialization`] crate, which should be used through that crate's
+//! `derive` feature rather than directly.
+//!
+//! [`serialization`]: https://docs.rs/serialization
+
+#![warn(clippy::pedantic)]
+#![warn(missing_docs)]
+
+use proc_macro::TokenStream;
+use proc_macro2::{
+    TokenStream as TokenStream2,
+    TokenTree,
+};
+use quote::{
+    format_ident,
+    quote,
+    ToTokens as _,
+};
+use std::collections::HashMap;
+use syn::{
+    parse_macro_input,
+    parse_quote,
+    Attribute,
+    Data,
+    DataEnum,
+    DataStruct,
+    DeriveInput,
+    Fields,
+    Generics,
+    Ident,
+    LitInt,
+    Type,
+};
+
+// This is the largest wire id which an enum variant may be given.  The
+// derived impls pass serde a table of variant names indexed by wire id, so
+// the limit keeps that table small.
+const MAX_VARIANT_ID: u32 = 255;
+
+struct Field {
+    name: Ident,
+    tag: u32,
+    ty: Type,
+}
+
+struct Variant<'a> {
+    name: &'a Ident,
+    id: u32,
+    fields: &'a Fields,
+}
+
+// Find the value of the given key in the `#[ser(key = N)]` attributes of an
+// item, rejecting any other keys.
+fn ser_attribute(
+    attrs: &[Attribute],
+    key: &str,
+) -> syn::Result<Option<u32>> {
+    let mut value = None;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("ser")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident(key) {
+                let literal: LitInt = meta.value()?.parse()?;
+                value = Some(literal.base10_parse::<u32>()?);
+                Ok(())
+            } else {
+                Err(meta.error(format!("expected `{key} = N`")))
+            }
+        })?;
+    }
+    Ok(value)
+}
+
+fn reject_lifetimes(input: &DeriveInput) -> syn::Result<()> {
+    match input.generics.lifetimes().next() {
+        Some(lifetime) => Err(syn::Error::new_spanned(
+            lifetime,
+            "types with tagged fields or pinned variants cannot borrow data",
+        )),
+        None => Ok(()),
+    }
+}
+
+// Collect the named fields of the given struct along with their tags,
+// checking that every field has a tag and that no two share one.
+fn tagged_fields(
+    input: &DeriveInput,
+    data: &DataStruct,
+) -> syn::Result<Vec<Field>> {
+    let fields = match &data.fields {
+        Fields::Named(fields) => &fields.named,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "only structs with named fields can have tagged fields",
+            ))
+        },
+    };
+    reject_lifetimes(input)?;
+    let mut tagged = Vec::new();
+    let mut names_by_tag = HashMap::new();
+    for field in fields {
+        let name = field.ident.clone().expect("named field has a name");
+        let tag = ser_attribute(&field.attrs, "tag")?.ok_or_else(|| {
+            syn::Error::new_spanned(
+                &name,
+                "field needs a tag, such as `#[ser(tag = 1)]`",
+            )
+        })?;
+        if let Some(other) = names_by_tag.insert(tag, name.clone()) {
+            return Err(syn::Error::new_spanned(
+                &name,
+                format!("tag {tag} is already used by field `{other}`"),
+            ));
+        }
+        tagged.push(Field {
+            name,
+            tag,
+            ty: field.ty.clone(),
+        });
+    }
+    Ok(tagged)
+}
+
+// Collect the variants of the given enum along with their wire ids,
+// checking that every variant has an id and that no two share one.
+fn pinned_variants<'a>(
+    input: &DeriveInput,
+    data: &'a DataEnum,
+) -> syn::Result<Vec<Variant<'a>>> {
+    reject_lifetimes(input)?;
+    let mut pinned = Vec::new();
+    let mut names_by_id = HashMap::new();
+    for variant in &data.variants {
+        let name = &variant.ident;
+        let id = ser_attribute(&variant.attrs, "id")?.ok_or_else(|| {
+            syn::Error::new_spanned(
+                name,
+                "variant needs a wire id, such as `#[ser(id = 1)]`",
+            )
+        })?;
+        if id > MAX_VARIANT_ID {
+            return Err(syn::Error::new_spanned(
+                name,
+                format!(
+                    "variant ids must not be greater than {MAX_VARIANT_ID}"
+                ),
+            ));
+        }
+        if let Some(other) = names_by_id.insert(id, name) {
+            return Err(syn::Error::new_spanned(
+                name,
+                format!("id {id} is already used by variant `{other}`"),
+            ));
+        }
+        let empty = match &variant.fields {
+            Fields::Named(fields) => fields.named.is_empty(),
+            Fields::Unnamed(fields) => fields.unnamed.is_empty(),
+            Fields::Unit => false,
+        };
+        if empty {
+            return Err(syn::Error::new_spanned(
+                name,
+                "variants without fields must be unit variants",
+            ));
+        }
+        pinned.push(Variant {
+            name,
+            id,
+            fields: &variant.fields,
+        });
+    }
+    Ok(pinned)
+}
+
+// Return whether the given tokens mention any of the given identifiers.
+fn mentions(
+    tokens: TokenStream2,
+    idents: &[Ident],
+) -> bool {
+    tokens.into_iter().any(|token| match token {
+        TokenTree::Ident(ident) => idents.contains(&ident),
+        TokenTree::Group(group) => mentions(group.stream(), idents),
+        _ => false,
+    })
+}
+
+// Require `Default` of the type of each of the given fields which mentions
+// a type parameter of the given generics, as a missing field is given its
+// default value.  Bounding the parameters themselves instead would wrongly
+// rule out a field such as `Option<T>` whose type has a default when `T`
+// does not.
+fn add_default_bounds(
+    generics: &Generics,
+    fields: &[Field],
+) -> Generics {
+    let mut generics = generics.clone();
+    let params: Vec<_> =
+        generics.type_params().map(|param| param.ident.clone()).collect();
+    let where_clause = generics.make_where_clause();
+    for field in fields {
+        if mentions(field.ty.to_token_stream(), &params) {
+            let ty = &field.ty;
+            where_clause
+                .predicates
+                .push(parse_quote!(#ty: ::std::default::Default));
+        }
+    }
+    generics
+}
+
+// Add the given bound to every type parameter of the given generics.
+fn add_bound(
+    generics: &Generics,
+    bound: &syn::TypeParamBound,
+) -> Generics {
+    let mut generics = generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(bound.clone());
+    }
+    generics
+}
+
+fn encode_struct(
+    input: &DeriveInput,
+    data: &DataStruct,
+) -> syn::Result<TokenStream2> {
+    let fields = tagged_fields(input, data)?;
+    let ident = &input.ident;
+    let generics = add_bound(
+        &input.generics,
+        &parse_quote!(::serialization::__private::serde::Serialize),
+    );
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let count = fields.len();
+    let elements = fields.iter().map(|field| {
+        let name = &field.name;
+        let tag = field.tag;
+        quote! {
+            seq.serialize_element(
+                &::serialization::__private::TaggedField(#tag, &self.#name),
+            )?;
+        }
+    });
+    Ok(quote! {
+        impl #impl_generics ::serialization::__private::serde::Serialize
+            for #ident #ty_generics #where_clause
+        {
+            fn serialize<__S>(
+                &self,
+                serializer: __S,
+            ) -> ::std::result::Result<__S::Ok, __S::Error>
+            where
+                __S: ::serialization::__private::serde::Serializer,
+            {
+                use ::serialization::__private::serde::ser::SerializeSeq as _;
+                let mut seq = serializer.serialize_seq(Some(#count))?;
+                #(#elements)*
+                seq.end()
+            }
+        }
+    })
+}
+
+fn decode_struct(
+    input: &DeriveInput,
+    data: &DataStruct,
+) -> syn::Result<TokenStream2> {
+    let fields = tagged_fields(input, data)?;
+    let ident = &input.ident;
+    let generics = add_bound(
+        &input.generics,
+        &parse_quote!(::serialization::__private::serde::de::DeserializeOwned),
+    );
+    let generics = add_default_bounds(&generics, &fields);
+    let (_, ty_generics, _) = generics.split_for_impl();
+    let mut visitor_generics = generics.clone();
+    visitor_generics.params.insert(0, parse_quote!('de));
+    let (impl_generics, visitor_ty_generics, where_clause) =
+        visitor_generics.split_for_impl();
+    let params = generics.type_params().map(|param| &param.ident);
+    let names: Vec<_> = fields.iter().map(|field| &field.name).collect();
+    let locals: Vec<_> =
+        names.iter().map(|name| format_ident!("__field_{}", name)).collect();
+    let tags = fields.iter().map(|field| field.tag);
+    let expecting = format!("tagged fields of struct {ident}");
+    Ok(quote! {
+        impl #impl_generics ::serialization::__private::serde::Deserialize<'de>
+            for #ident #ty_generics #where_clause
+        {
+            fn deserialize<__D>(
+                deserializer: __D,
+            ) -> ::std::result::Result<Self, __D::Error>
+            where
+                __D: ::serialization::__private::serde::Deserializer<'de>,
+            {
+                struct __Visitor #impl_generics #where_clause {
+                    marker: ::std::marker::PhantomData<(&'de (), #(#params,)*)>,
+                }
+
+                impl #impl_generics
+                    ::serialization::__private::serde::de::Visitor<'de>
+                    for __Visitor #visitor_ty_generics #where_clause
+                {
+                    type Value = #ident #ty_generics;
+
+                    fn expecting(
+                        &self,
+                        f: &mut ::std::fmt::Formatter,
+                    ) -> ::std::fmt::Result {
+                        f.write_str(#expecting)
+                    }
+
+                    fn visit_seq<__A>(
+                        self,
+                        mut __seq: __A,
+                    ) -> ::std::result::Result<Self::Value, __A::Error>
+                    where
+                        __A: ::serialization::__private::serde::de::SeqAccess<'de>,
+                    {
+                        #(let mut #locals = ::std::option::Option::None;)*
+                        while let ::std::option::Option::Some(__field) =
+                            __seq.next_element::<
+                                ::serialization::__private::RawField<'de>,
+                            >()?
+                        {
+                            match __field.tag {
+                                #(#tags => {
+                                    #locals = ::std::option::Option::Some(
+                                        __field.decode()?,
+                                    );
+                                },)*
+                                _ => {},
+                            }
+                        }
+                        ::std::result::Result::Ok(#ident {
+                            #(#names: #locals.unwrap_or_default(),)*
+                        })
+                    }
+                }
+
+                deserializer.deserialize_seq(__Visitor {
+                    marker: ::std::marker::PhantomData,
+                })
+            }
+        }
+    })
+}
+
+fn encode_enum(
+    input: &DeriveInput,
+    data: &DataEnum,
+) -> syn::Result<TokenStream2> {
+    let variants = pinned_variants(input, data)?;
+    let ident = &input.ident;
+    let enum_name = ident.to_string();
+    let generics = add_bound(
+        &input.generics,
+        &parse_quote!(::serialization::__private::serde::Serialize),
+    );
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let arms = variants.iter().map(|variant| {
+        let name = variant.name;
+        let id = variant.id;
+        let variant_name = name.to_string();
+        match variant.fields {
+            Fields::Unit => quote! {
+                #ident::#name => serializer.serialize_unit_variant(
+                    #enum_name,
+                    #id,
+                    #variant_name,
+                ),
+            },
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => quote! {
+                #ident::#name(ref __field_0) => serializer
+                    .serialize_newtype_variant(
+                        #enum_name,
+                        #id,
+                        #variant_name,
+                        __field_0,
+                    ),
+            },
+            Fields::Unnamed(fields) => {
+                let count = fields.unnamed.len();
+                let bindings: Vec<_> = (0..count)
+                    .map(|i| format_ident!("__field_{}", i))
+                    .collect();
+                quote! {
+                    #ident::#name(#(ref #bindings),*) => {
+                        let mut __variant = serializer.serialize_tuple_variant(
+                            #enum_name,
+                            #id,
+                            #variant_name,
+                            #count,
+                        )?;
+                        #(__variant.serialize_field(#bindings)?;)*
+                        __variant.end()
+                    },
+                }
+            },
+            Fields::Named(fields) => {
+                let count = fields.named.len();
+                let names: Vec<_> = fields
+                    .named
+                    .iter()
+                    .map(|field| field.ident.as_ref().expect("named field"))
+                    .collect();
+                let keys = names.iter().map(ToString::to_string);
+                let bindings: Vec<_> = names
+                    .iter()
+                    .map(|name| format_ident!("__field_{}", name))
+                    .collect();
+                quote! {
+                    #ident::#name { #(#names: ref #bindings),* } => {
+                        let mut __variant = serializer.serialize_struct_variant(
+                            #enum_name,
+                            #id,
+                            #variant_name,
+                            #count,
+                        )?;
+                        #(__variant.serialize_field(#keys, #bindings)?;)*
+                        __variant.end()
+                    },
+                }
+            },
+        }
+    });
+    Ok(quote! {
+        impl #impl_generics ::serialization::__private::serde::Serialize
+            for #ident #ty_generics #where_clause
+        {
+            fn serialize<__S>(
+                &self,
+                serializer: __S,
+            ) -> ::std::result::Result<__S::Ok, __S::Error>
+            where
+                __S: ::serialization::__private::serde::Serializer,
+            {
+                use ::serialization::__private::serde::ser::{
+                    SerializeStructVariant as _,
+                    SerializeTupleVariant as _,
+                };
+                match *self {
+                    #(#arms)*
+                }
+            }
+        }
+    })
+}
+
+// Produce the match arm which decodes the contents of the given variant,
+// once its id has been decoded.
+fn decode_variant(
+    ident: &Ident,
+    variant: &Variant,
+) -> TokenStream2 {
+    let name = variant.name;
+    let id = variant.id;
+    match variant.fields {
+        Fields::Unit => quote! {
+            #id => {
+                __variant.unit_variant()?;
+                ::std::result::Result::Ok(#ident::#name)
+            },
+        },
+        Fields::Unnamed(fields) if fields.unnamed.len() == 1 => quote! {
+            #id => __variant.newtype_variant().map(#ident::#name),
+        },
+        Fields::Unnamed(fields) => {
+            let count = fields.unnamed.len();
+            let types = fields.unnamed.iter().map(|field| &field.ty);
+            let bindings: Vec<_> =
+                (0..count).map(|i| format_ident!("__field_{}", i)).collect();
+            quote! {
+                #id => {
+                    let (#(#bindings,)*) = __variant.tuple_variant(
+                        #count,
+                        ::serialization::__private::PositionalVisitor::<
+                            (#(#types,)*),
+                        >::new(),
+                    )?;
+                    ::std::result::Result::Ok(#ident::#name(#(#bindings),*))
+                },
+            }
+        },
+        Fields::Named(fields) => {
+            let names: Vec<_> = fields
+                .named
+                .iter()
+                .map(|field| field.ident.as_ref().expect("named field"))
+                .collect();
+            let keys = names.iter().map(ToString::to_string);
+            let types = fields.named.iter().map(|field| &field.ty);
+            let bindings: Vec<_> = names
+                .iter()
+                .map(|name| format_ident!("__field_{}", name))
+                .collect();
+            quote! {
+                #id => {
+                    let (#(#bindings,)*) = __variant.struct_variant(
+                        &[#(#keys),*],
+                        ::serialization::__private::PositionalVisitor::<
+                            (#(#types,)*),
+                        >::new(),
+                    )?;
+                    ::std::result::Result::Ok(#ident::#name {
+                        #(#names: #bindings),*
+                    })
+                },
+            }
+        },
+    }
+}
+
+fn decode_enum(
+    input: &DeriveInput,
+    data: &DataEnum,
+) -> syn::Result<TokenStream2> {
+    let variants = pinned_variants(input, data)?;
+    let ident = &input.ident;
+    let enum_name = ident.to_string();
+    let generics = add_bound(
+        &input.generics,
+        &parse_quote!(::serialization::__private::serde::Deserialize<'de>),
+    );
+    let (_, ty_generics, _) = generics.split_for_impl();
+    let mut visitor_generics = generics.clone();
+    visitor_generics.params.insert(0, parse_quote!('de));
+    let (impl_generics, visitor_ty_generics, where_clause) =
+        visitor_generics.split_for_impl();
+    let params = generics.type_params().map(|param| &param.ident);
+    let mut names_by_id = vec![String::new(); variants.len()];
+    for variant in &variants {
+        let index = variant.id as usize;
+        if names_by_id.len() <= index {
+            names_by_id.resize(index + 1, String::new());
+        }
+        names_by_id[index] = variant.name.to_string();
+    }
+    let arms = variants.iter().map(|variant| decode_variant(ident, variant));
+    let expecting = format!("enum {ident}");
+    Ok(quote! {
+        impl #impl_generics ::serialization::__private::serde::Deserialize<'de>
+            for #ident #ty_generics #where_clause
+        {
+            fn deserialize<__D>(
+                deserializer: __D,
+            ) -> ::std::result::Result<Self, __D::Error>
+            where
+                __D: ::serialization::__private::serde::Deserializer<'de>,
+            {
+                // The names of the variants, indexed by wire id, with empty
+                // names for ids which no variant uses.
+                const VARIANTS: &[&str] = &[#(#names_by_id),*];
+
+                struct __Visitor #impl_generics #where_clause {
+                    marker: ::std::marker::PhantomData<(&'de (), #(#params,)*)>,
+                }
+
+                impl #impl_generics
+                    ::serialization::__private::serde::de::Visitor<'de>
+                    for __Visitor #visitor_ty_generics #where_clause
+                {
+                    type Value = #ident #ty_generics;
+
+                    fn expecting(
+                        &self,
+                        f: &mut ::std::fmt::Formatter,
+                    ) -> ::std::fmt::Result {
+                        f.write_str(#expecting)
+                    }
+
+                    fn visit_enum<__A>(
+                        self,
+                        data: __A,
+                    ) -> ::std::result::Result<Self::Value, __A::Error>
+                    where
+                        __A: ::serialization::__private::serde::de::EnumAccess<'de>,
+                    {
+                        use ::serialization::__private::serde::de::VariantAccess as _;
+                        let (__id, __variant) = data.variant_seed(
+                            ::serialization::__private::VariantId(VARIANTS),
+                        )?;
+                        match __id {
+                            #(#arms)*
+                            _ => ::std::result::Result::Err(
+                                ::serialization::__private::serde::de::Error::invalid_value(
+                                    ::serialization::__private::serde::de::Unexpected::Unsigned(
+                                        u64::from(__id),
+                                    ),
+                                    &self,
+                                ),
+                            ),
+                        }
+                    }
+                }
+
+                deserializer.deserialize_enum(
+                    #enum_name,
+                    VARIANTS,
+                    __Visitor {
+                        marker: ::std::marker::PhantomData,
+                    },
+                )
+            }
+        }
+    })
+}
+
+fn encode(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    match &input.data {
+        Data::Struct(data) => encode_struct(input, data),
+        Data::Enum(data) => encode_enum(input, data),
+        Data::Union(_) => Err(syn::Error::new_spanned(
+            &input.ident,
+            "unions cannot be encoded",
+        )),
+    }
+}
+
+fn decode(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    match &input.data {
+        Data::Struct(data) => decode_struct(input, data),
+        Data::Enum(data) => decode_enum(input, data),
+        Data::Union(_) => Err(syn::Error::new_spanned(
+            &input.ident,
+            "unions cannot be decoded",
+        )),
+    }
+}
+
+/// Implement `serde::Serialize` for a struct whose fields each carry a
+/// `#[ser(tag = N)]` attribute, so that it is encoded as a sequence of
+/// tagged fields.
+///
+/// Each field is encoded as its tag, as a variable-length integer, followed
+/// by the encoding of its value as a block of bytes.  Fields may therefore
+/// be added, removed or reordered without breaking readers of the struct,
+/// as long as the tag of a field is never reused for a different field.
+///
+/// For an enum, each variant must instead carry a `#[ser(id = N)]`
+/// attribute, with `N` no greater than 255.  The variant is encoded with
+/// that id in place of its position in the enum, so variants may be
+/// reordered, and new variants added, without changing the encoding of
+/// existing ones.  The contents of each variant are encoded as usual.
+///
+/// Use this in place of `serde::Serialize`, not alongside it.
+#[proc_macro_derive(Encode, attributes(ser))]
+pub fn derive_encode(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    encode(&input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+/// Implement `serde::Deserialize` for a struct whose fields each carry a
+/// `#[ser(tag = N)]` attribute, decoding it from a sequence of tagged
+/// fields.
+///
+/// Fields with tags which the struct does not have are skipped, and fields
+/// of the struct whose tags are missing are given their default values, so
+/// every field type must implement `Default`.  For a generic struct, this
+/// is required of the field types rather than the type parameters, so that
+/// a field such as `Option<T>` does not need `T` to implement `Default`.
+///
+/// For an enum whose variants carry `#[ser(id = N)]` attributes, each
+/// variant is decoded from its id.  The deserializer of this crate rejects
+/// an id which no variant has with `Error::UnknownVariant`.
+///
+/// Use this in place of `serde::Deserialize`, not alongside it.
+#[proc_macro_derive(Decode, attributes(ser))]
+pub fn derive_decode(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    decode(&input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
@@ -0,0 +1,309 @@
+use std::{
+    fmt,
+    marker::PhantomData,
+    ops::{
+        Deref,
+        DerefMut,
+    },
+};
+
+// This is the name of the newtype struct through which an appendable struct
+// passes to a serializer or deserializer.  The serializer and deserializer
+// of this crate recognize it and use the appendable layout for the struct
+// inside.
+pub(crate) const NAME: &str = "$serialization::Appendable";
+
+/// This wraps a struct so that newer versions of it may add fields at the
+/// end, and older and newer versions can still decode each other's data.
+///
+/// The struct is encoded as the number of bytes following, the number of
+/// fields, and then the fields as usual.  When fewer fields are found than
+/// the struct has, the missing trailing fields are left to their
+/// `#[serde(default)]` values, and decoding fails if they have none.  When
+/// more fields are found, the extra ones are skipped.  Fields must only
+/// ever be added at the end, never removed or reordered.
+///
+/// Wrapping a type which is not a struct has no effect on its encoding.
+/// A [`BorrowedSlice`] within an appendable struct is not aligned.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate serialization;
+/// # extern crate serde;
+/// use serialization::Appendable;
+///
+/// #[derive(serde::Serialize)]
+/// struct Version1 {
+///     id: u32,
+/// }
+///
+/// #[derive(serde::Deserialize, PartialEq, Debug)]
+/// struct Version2 {
+///     id: u32,
+///     #[serde(default)]
+///     name: String,
+/// }
+///
+/// let encoding = serialization::to_bytes(&Appendable(Version1 {
+///     id: 7,
+/// }))
+/// .unwrap();
+/// assert_eq!(&[2, 1, 7][..], encoding);
+/// let decoded: Appendable<Version2> =
+///     serialization::from_bytes(&encoding).unwrap();
+/// assert_eq!(
+///     Version2 {
+///         id: 7,
+///         name: String::new(),
+///     },
+///     decoded.into_inner()
+/// );
+/// ```
+///
+/// [`BorrowedSlice`]: struct.BorrowedSlice.html
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Appendable<T>(pub T);
+
+impl<T> Appendable<T> {
+    /// Return the wrapped value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Appendable<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Appendable<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T> From<T> for Appendable<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl<T> serde::Serialize for Appendable<T>
+where
+    T: serde::Serialize,
+{
+    fn serialize<S>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_newtype_struct(NAME, &self.0)
+    }
+}
+
+struct Visitor<T> {
+    value: PhantomData<T>,
+}
+
+impl<'de, T> serde::de::Visitor<'de> for Visitor<T>
+where
+    T: serde::Deserialize<'de>,
+{
+    type Value = Appendable<T>;
+
+    fn expecting(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        write!(f, "an appendable struct")
+    }
+
+    fn visit_newtype_struct<D>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        T::deserialize(deserializer).map(Appendable)
+    }
+}
+
+impl<'de, T> serde::Deserialize<'de> for Appendable<T>
+where
+    T: serde::Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(NAME, Visitor {
+            value: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        from_bytes,
+        from_bytes_interned,
+        to_bytes,
+        to_bytes_interned,
+        Error,
+    };
+
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    struct Version1 {
+        id: u32,
+        name: String,
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    struct Version2 {
+        id: u32,
+        name: String,
+        #[serde(default)]
+        tags: Vec<String>,
+        #[serde(default)]
+        score: i64,
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    struct Envelope<T> {
+        message: Appendable<T>,
+        after: u8,
+    }
+
+    fn version2() -> Version2 {
+        Version2 {
+            id: 300,
+            name: String::from("a"),
+            tags: vec![String::from("b")],
+            score: -1,
+        }
+    }
+
+    #[test]
+    fn serialize_layout() {
+        let serialization = to_bytes(&Appendable(Version1 {
+            id: 300,
+            name: String::from("a"),
+        }));
+        assert!(serialization.is_ok());
+        let serialization = serialization.unwrap();
+        assert_eq!(&[5, 2, 0x82, 0x2C, 1, b'a'][..], serialization);
+        let serialization = to_bytes(&Appendable((1_u8, 2_u8)));
+        assert!(serialization.is_ok());
+        let serialization = serialization.unwrap();
+        assert_eq!(&[1, 2][..], serialization);
+    }
+
+    #[test]
+    fn new_reader_defaults_missing_fields() {
+        let encoding = to_bytes(&Envelope {
+            message: Appendable(Version1 {
+                id: 300,
+                name: String::from("a"),
+            }),
+            after: 9,
+        })
+        .unwrap();
+        let deserialization = from_bytes::<Envelope<Version2>>(&encoding);
+        assert!(deserialization.is_ok());
+        let deserialization = deserialization.unwrap();
+        assert_eq!(
+            Envelope {
+                message: Appendable(Version2 {
+                    id: 300,
+                    name: String::from("a"),
+                    tags: Vec::new(),
+                    score: 0,
+                }),
+                after: 9,
+            },
+            deserialization
+        );
+    }
+
+    #[test]
+    fn old_reader_skips_extra_fields() {
+        let encoding = to_bytes(&Envelope {
+            message: Appendable(version2()),
+            after: 9,
+        })
+        .unwrap();
+        let deserialization = from_bytes::<Envelope<Version1>>(&encoding);
+        assert!(deserialization.is_ok());
+        let deserialization = deserialization.unwrap();
+        assert_eq!(
+            Envelope {
+                message: Appendable(Version1 {
+                    id: 300,
+                    name: String::from("a"),
+                }),
+                after: 9,
+            },
+            deserialization
+        );
+    }
+
+    #[test]
+    fn nested_round_trip() {
+        let value = Appendable(Envelope {
+            message: Appendable(version2()),
+            after: 9,
+        });
+        let encoding = to_bytes(&value).unwrap();
+        assert_eq!(value, from_bytes(&encoding).unwrap());
+    }
+
+    #[test]
+    fn deserialize_missing_required_field() {
+        #[derive(serde::Serialize)]
+        struct Version0 {
+            id: u32,
+        }
+        let encoding = to_bytes(&Appendable(Version0 {
+            id: 300,
+        }))
+        .unwrap();
+        assert!(matches!(
+            from_bytes::<Appendable<Version1>>(&encoding),
+            Err(Error::Message(_))
+        ));
+    }
+
+    #[test]
+    fn deserialize_truncated() {
+        let encoding = to_bytes(&Appendable(version2())).unwrap();
+        assert!(matches!(
+            from_bytes::<Appendable<Version1>>(&encoding[..encoding.len() - 1]),
+            Err(Error::ValueTruncated)
+        ));
+    }
+
+    #[test]
+    fn interned_strings_scoped_to_struct() {
+        let value = (
+            String::from("a"),
+            vec![Appendable(version2()), Appendable(version2())],
+            String::from("b"),
+        );
+        let encoding = to_bytes_interned(&value).unwrap();
+        let deserialization: (String, Vec<Appendable<Version1>>, String) =
+            from_bytes_interned(&encoding).unwrap();
+        assert_eq!(value.0, deserialization.0);
+        for message in &deserialization.1 {
+            assert_eq!("a", message.name);
+        }
+        assert_eq!(value.2, deserialization.2);
+    }
+}
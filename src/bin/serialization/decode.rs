@@ -0,0 +1,700 @@
+use serde::Deserialize;
+use serialization::{
+    Deserializer,
+    Field,
+    Schema,
+    Variant,
+    VariantShape,
+};
+use std::{
+    convert::TryFrom,
+    fmt,
+};
+
+// These are the names of the newtype structs through which the wrappers
+// of the library pass.  An appendable struct is preceded by its length and
+// number of fields, while shared values and aligned slices have layouts
+// which depend on more than their schemas, and so cannot be decoded here.
+const APPENDABLE: &str = "$serialization::Appendable";
+const SHARED: &str = "$serialization::Shared";
+const ALIGNED: &str = "$serialization::Aligned";
+
+// Sequences of values which may occupy no bytes cannot be bounded by the
+// length of the input, so they are bounded by this instead.
+const MAX_EMPTY_ELEMENTS: u64 = 1 << 20;
+
+/// This is a problem found at a particular offset of the input.
+#[derive(Debug)]
+pub struct Failure {
+    pub offset: usize,
+    pub message: String,
+}
+
+impl Failure {
+    pub fn new<M>(
+        offset: usize,
+        message: M,
+    ) -> Self
+    where
+        M: Into<String>,
+    {
+        Self {
+            offset,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        write!(f, "offset {}: {}", self.offset, self.message)
+    }
+}
+
+/// This is a value decoded according to a schema, along with the range of
+/// bytes it occupies.  Bytes from `start` to `body` hold any length, flag,
+/// or variant index which precedes the contents of the value.
+#[derive(Debug)]
+pub struct Node {
+    pub start: usize,
+    pub body: usize,
+    pub end: usize,
+    pub value: Value,
+}
+
+#[derive(Debug)]
+pub enum Value {
+    Unit,
+    Bool(bool),
+    Signed(i64),
+    Unsigned(u64),
+    F32(f32),
+    F64(f64),
+    Char(char),
+    Str(String),
+    Bytes(Vec<u8>),
+    Option(Option<Box<Node>>),
+    Seq(Vec<Node>),
+    Map(Vec<(Node, Node)>),
+    Tuple(Vec<Node>),
+    UnitStruct(String),
+    NewtypeStruct(String, Box<Node>),
+    Appendable {
+        name: String,
+        count: u64,
+        fields: Vec<(String, Node)>,
+    },
+    TupleStruct(String, Vec<Node>),
+    Struct(String, Vec<(String, Node)>),
+    Variant {
+        name: String,
+        index: u64,
+        contents: Contents,
+    },
+}
+
+#[derive(Debug)]
+pub enum Contents {
+    Unit,
+    Newtype(Box<Node>),
+    Tuple(Vec<Node>),
+    Struct(Vec<(String, Node)>),
+}
+
+/// This walks a schema, keeping track of the named types which enclose the
+/// current position, so that references to them can be followed.
+pub struct Scopes<'s> {
+    enclosing: Vec<&'s Schema>,
+}
+
+impl<'s> Scopes<'s> {
+    pub fn new() -> Self {
+        Self {
+            enclosing: Vec::new(),
+        }
+    }
+
+    pub fn enter(
+        &mut self,
+        schema: &'s Schema,
+    ) -> bool {
+        match schema {
+            Schema::NewtypeStruct {
+                ..
+            }
+            | Schema::TupleStruct {
+                ..
+            }
+            | Schema::Struct {
+                ..
+            }
+            | Schema::Enum {
+                ..
+            } => {
+                self.enclosing.push(schema);
+                true
+            },
+            _ => false,
+        }
+    }
+
+    pub fn leave(
+        &mut self,
+        entered: bool,
+    ) {
+        if entered {
+            self.enclosing.pop();
+        }
+    }
+
+    pub fn resolve(
+        &self,
+        name: &str,
+    ) -> Option<&'s Schema> {
+        self.enclosing.iter().rev().copied().find(|schema| match schema {
+            Schema::NewtypeStruct {
+                name: other,
+                ..
+            }
+            | Schema::TupleStruct {
+                name: other,
+                ..
+            }
+            | Schema::Struct {
+                name: other,
+                ..
+            }
+            | Schema::Enum {
+                name: other,
+                ..
+            } => other == name,
+            _ => false,
+        })
+    }
+
+    /// Return the struct which the given schema is, or refers to, if any.
+    pub fn resolve_struct(
+        &self,
+        schema: &'s Schema,
+    ) -> Option<&'s Schema> {
+        let schema = match schema {
+            Schema::Ref {
+                name,
+            } => self.resolve(name)?,
+            _ => schema,
+        };
+        matches!(schema, Schema::Struct { .. }).then_some(schema)
+    }
+}
+
+/// Return a failure if the newtype struct with the given name is one whose
+/// layout cannot be handled without the type of its contents.
+pub fn check_supported(
+    offset: usize,
+    name: &str,
+) -> Result<(), Failure> {
+    if name == SHARED || name.starts_with(ALIGNED) {
+        Err(Failure::new(
+            offset,
+            format!("values wrapped in `{name}` are not supported"),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Return `true` if the given name is that of the newtype struct through
+/// which an appendable struct passes.
+pub fn is_appendable(name: &str) -> bool {
+    name == APPENDABLE
+}
+
+fn can_be_empty(schema: &Schema) -> bool {
+    match schema {
+        Schema::Unit
+        | Schema::UnitStruct {
+            ..
+        } => true,
+        Schema::NewtypeStruct {
+            name,
+            value,
+        } => !is_appendable(name) && can_be_empty(value),
+        Schema::Tuple(schemas)
+        | Schema::TupleStruct {
+            fields: schemas,
+            ..
+        } => schemas.iter().all(can_be_empty),
+        Schema::Struct {
+            fields,
+            ..
+        } => fields.iter().all(|field| can_be_empty(&field.schema)),
+        _ => false,
+    }
+}
+
+struct Decoder<'a, 's> {
+    bytes: &'a [u8],
+    offset: usize,
+    scopes: Scopes<'s>,
+}
+
+impl<'a, 's> Decoder<'a, 's> {
+    fn count(
+        &mut self,
+        element: &Schema,
+    ) -> Result<u64, Failure> {
+        let start = self.offset;
+        let count: u64 = self.read()?;
+        let remaining = (self.bytes.len() - self.offset) as u64;
+        let limit = if can_be_empty(element) {
+            MAX_EMPTY_ELEMENTS
+        } else {
+            remaining
+        };
+        if count > limit {
+            return Err(Failure::new(
+                start,
+                format!("length {count} exceeds the remaining input"),
+            ));
+        }
+        Ok(count)
+    }
+
+    fn decode(
+        &mut self,
+        schema: &'s Schema,
+    ) -> Result<Node, Failure> {
+        let entered = self.scopes.enter(schema);
+        let start = self.offset;
+        let result = self.decode_value(schema);
+        self.scopes.leave(entered);
+        let (body, value) = result?;
+        Ok(Node {
+            start,
+            body,
+            end: self.offset,
+            value,
+        })
+    }
+
+    // Decode a struct in the appendable layout, leaving out any trailing
+    // fields it lacks and skipping any extra ones.
+    fn decode_appendable(
+        &mut self,
+        schema: &'s Schema,
+    ) -> Result<(usize, Value), Failure> {
+        let Schema::Struct {
+            name,
+            fields,
+        } = schema
+        else {
+            unreachable!("only structs are appendable");
+        };
+        let (_, end) = self.length()?;
+        let bytes = self.bytes;
+        self.bytes = &bytes[..end];
+        let entered = self.scopes.enter(schema);
+        let result = self.read::<u64>().and_then(|count| {
+            let body = self.offset;
+            let fields = fields
+                .iter()
+                .take(usize::try_from(count).unwrap_or(usize::MAX))
+                .map(|field| {
+                    Ok((field.name.clone(), self.decode(&field.schema)?))
+                })
+                .collect::<Result<_, _>>()?;
+            Ok((body, count, fields))
+        });
+        self.scopes.leave(entered);
+        self.bytes = bytes;
+        let (body, count, fields) = result?;
+        self.offset = end;
+        Ok((body, Value::Appendable {
+            name: name.clone(),
+            count,
+            fields,
+        }))
+    }
+
+    fn decode_newtype(
+        &mut self,
+        name: &str,
+        value: &'s Schema,
+    ) -> Result<(usize, Value), Failure> {
+        let start = self.offset;
+        if is_appendable(name) {
+            if let Some(schema) = self.scopes.resolve_struct(value) {
+                return self.decode_appendable(schema);
+            }
+            // Wrapping anything other than a struct has no effect.
+            let node = self.decode(value)?;
+            return Ok((node.body, node.value));
+        }
+        check_supported(start, name)?;
+        let node = self.decode(value)?;
+        Ok((start, Value::NewtypeStruct(name.to_string(), Box::new(node))))
+    }
+
+    fn decode_all(
+        &mut self,
+        schemas: &'s [Schema],
+    ) -> Result<Vec<Node>, Failure> {
+        schemas.iter().map(|schema| self.decode(schema)).collect()
+    }
+
+    fn decode_fields(
+        &mut self,
+        fields: &'s [Field],
+    ) -> Result<Vec<(String, Node)>, Failure> {
+        fields
+            .iter()
+            .map(|field| Ok((field.name.clone(), self.decode(&field.schema)?)))
+            .collect()
+    }
+
+    fn decode_text(&mut self) -> Result<(usize, Vec<u8>), Failure> {
+        let (body, end) = self.length()?;
+        self.offset = end;
+        Ok((body, self.bytes[body..end].to_vec()))
+    }
+
+    fn decode_value(
+        &mut self,
+        schema: &'s Schema,
+    ) -> Result<(usize, Value), Failure> {
+        let start = self.offset;
+        let value = match schema {
+            Schema::Unit => Value::Unit,
+            Schema::Bool => Value::Bool(self.read()?),
+            Schema::I8 => Value::Signed(i64::from(self.read::<i8>()?)),
+            Schema::I16 => Value::Signed(i64::from(self.read::<i16>()?)),
+            Schema::I32 => Value::Signed(i64::from(self.read::<i32>()?)),
+            Schema::I64 => Value::Signed(self.read()?),
+            Schema::U8 => Value::Unsigned(u64::from(self.read::<u8>()?)),
+            Schema::U16 => Value::Unsigned(u64::from(self.read::<u16>()?)),
+            Schema::U32 => Value::Unsigned(u64::from(self.read::<u32>()?)),
+            Schema::U64 => Value::Unsigned(self.read()?),
+            Schema::F32 => Value::F32(self.read()?),
+            Schema::F64 => Value::F64(self.read()?),
+            Schema::Char => Value::Char(self.read()?),
+            Schema::Str => {
+                let (body, text) = self.decode_text()?;
+                let text = String::from_utf8(text).map_err(|error| {
+                    Failure::new(body, format!("invalid UTF-8 text: {error}"))
+                })?;
+                return Ok((body, Value::Str(text)));
+            },
+            Schema::Bytes => {
+                let (body, bytes) = self.decode_text()?;
+                return Ok((body, Value::Bytes(bytes)));
+            },
+            Schema::Option(schema) => {
+                let present: bool = self.read()?;
+                let body = self.offset;
+                let value = if present {
+                    Some(Box::new(self.decode(schema)?))
+                } else {
+                    None
+                };
+                return Ok((body, Value::Option(value)));
+            },
+            Schema::Seq(schema) => {
+                let count = self.count(schema)?;
+                let body = self.offset;
+                let elements = (0..count)
+                    .map(|_| self.decode(schema))
+                    .collect::<Result<_, _>>()?;
+                return Ok((body, Value::Seq(elements)));
+            },
+            Schema::Map {
+                key,
+                value,
+            } => {
+                let count = self.count(key)?;
+                let body = self.offset;
+                let entries = (0..count)
+                    .map(|_| Ok((self.decode(key)?, self.decode(value)?)))
+                    .collect::<Result<_, _>>()?;
+                return Ok((body, Value::Map(entries)));
+            },
+            Schema::Tuple(schemas) => Value::Tuple(self.decode_all(schemas)?),
+            Schema::UnitStruct {
+                name,
+            } => Value::UnitStruct(name.clone()),
+            Schema::NewtypeStruct {
+                name,
+                value,
+            } => return self.decode_newtype(name, value),
+            Schema::TupleStruct {
+                name,
+                fields,
+            } => Value::TupleStruct(name.clone(), self.decode_all(fields)?),
+            Schema::Struct {
+                name,
+                fields,
+            } => Value::Struct(name.clone(), self.decode_fields(fields)?),
+            Schema::Enum {
+                variants,
+                ..
+            } => return self.decode_variant(variants),
+            Schema::Ref {
+                name,
+            } => {
+                let schema = self.scopes.resolve(name).ok_or_else(|| {
+                    Failure::new(start, format!("unknown type `{name}`"))
+                })?;
+                return self.decode_value(schema);
+            },
+        };
+        Ok((start, value))
+    }
+
+    fn decode_variant(
+        &mut self,
+        variants: &'s [Variant],
+    ) -> Result<(usize, Value), Failure> {
+        let start = self.offset;
+        let index: u64 = self.read()?;
+        let body = self.offset;
+        let variant = variants
+            .iter()
+            .find(|variant| u64::from(variant.index) == index)
+            .ok_or_else(|| {
+                Failure::new(start, format!("unknown variant {index}"))
+            })?;
+        let contents = match &variant.shape {
+            VariantShape::Unit => Contents::Unit,
+            VariantShape::Newtype(schema) => {
+                Contents::Newtype(Box::new(self.decode(schema)?))
+            },
+            VariantShape::Tuple(schemas) => {
+                Contents::Tuple(self.decode_all(schemas)?)
+            },
+            VariantShape::Struct(fields) => {
+                Contents::Struct(self.decode_fields(fields)?)
+            },
+        };
+        Ok((body, Value::Variant {
+            name: variant.name.clone(),
+            index,
+            contents,
+        }))
+    }
+
+    // Read a length, and return the offsets of the start and end of the
+    // bytes it covers, which must all be present.
+    fn length(&mut self) -> Result<(usize, usize), Failure> {
+        let start = self.offset;
+        let length: u64 = self.read()?;
+        let body = self.offset;
+        let end = usize::try_from(length)
+            .ok()
+            .and_then(|length| body.checked_add(length))
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| {
+                Failure::new(
+                    start,
+                    format!("length {length} exceeds the remaining input"),
+                )
+            })?;
+        Ok((body, end))
+    }
+
+    fn read<T>(&mut self) -> Result<T, Failure>
+    where
+        T: Deserialize<'a>,
+    {
+        let mut deserializer = Deserializer::new(&self.bytes[self.offset..]);
+        let value = T::deserialize(&mut deserializer)
+            .map_err(|error| Failure::new(self.offset, error.to_string()))?;
+        self.offset += deserializer.offset();
+        Ok(value)
+    }
+}
+
+/// Decode the given bytes according to the given schema, requiring that
+/// every byte is used.
+pub fn decode(
+    bytes: &[u8],
+    schema: &Schema,
+) -> Result<Node, Failure> {
+    let mut decoder = Decoder {
+        bytes,
+        offset: 0,
+        scopes: Scopes::new(),
+    };
+    let node = decoder.decode(schema)?;
+    if decoder.offset < bytes.len() {
+        return Err(Failure::new(
+            decoder.offset,
+            format!("{} unused bytes", bytes.len() - decoder.offset),
+        ));
+    }
+    Ok(node)
+}
+
+/// Read a single unsigned and signed variable-length integer from the start
+/// of the given bytes, returning both interpretations and the number of
+/// bytes used.
+pub fn read_varint(bytes: &[u8]) -> Option<(u64, Option<i64>, usize)> {
+    let mut deserializer = Deserializer::new(bytes);
+    let unsigned = u64::deserialize(&mut deserializer).ok()?;
+    let length = deserializer.offset();
+    let mut deserializer = Deserializer::new(bytes);
+    let signed = i64::deserialize(&mut deserializer)
+        .ok()
+        .filter(|_| deserializer.offset() == length);
+    Some((unsigned, signed, length))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serialization::{
+        schema_of,
+        to_bytes,
+        Appendable,
+        Shared,
+    };
+    use std::rc::Rc;
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    enum List {
+        Cons(u8, Box<List>),
+        Nil,
+    }
+
+    #[test]
+    fn decode_recursive() {
+        let schema = schema_of::<List>().unwrap();
+        let bytes = to_bytes(&List::Cons(
+            1,
+            Box::new(List::Cons(2, Box::new(List::Nil))),
+        ))
+        .unwrap();
+        let node = decode(&bytes, &schema).unwrap();
+        assert_eq!(bytes.len(), node.end);
+        assert!(matches!(node.value, Value::Variant {
+            index: 0,
+            ..
+        }));
+    }
+
+    #[test]
+    fn decode_unused_bytes() {
+        let failure = decode(&[1, 2], &Schema::U8).unwrap_err();
+        assert_eq!(1, failure.offset);
+    }
+
+    #[test]
+    fn decode_length_too_long() {
+        let failure =
+            decode(&[0x83, 0x00, 1], &Schema::Seq(Box::new(Schema::U8)))
+                .unwrap_err();
+        assert_eq!(0, failure.offset);
+    }
+
+    #[derive(Default, serde::Serialize, serde::Deserialize)]
+    struct Old {
+        a: u8,
+    }
+
+    #[derive(Default, serde::Serialize, serde::Deserialize)]
+    struct New {
+        a: u8,
+        b: u16,
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct Wrapped<T> {
+        s: Appendable<T>,
+        t: u8,
+    }
+
+    fn wrapped<T>(s: T) -> Vec<u8>
+    where
+        T: serde::Serialize,
+    {
+        to_bytes(&Wrapped {
+            s: Appendable(s),
+            t: 7,
+        })
+        .unwrap()
+    }
+
+    fn fields(node: &Node) -> (u64, Vec<&str>, &Node) {
+        match &node.value {
+            Value::Struct(_, outer) => match &outer[0].1.value {
+                Value::Appendable {
+                    count,
+                    fields,
+                    ..
+                } => (
+                    *count,
+                    fields.iter().map(|(name, _)| name.as_str()).collect(),
+                    &outer[1].1,
+                ),
+                other => panic!("not appendable: {:?}", other),
+            },
+            other => panic!("not a struct: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn decode_appendable() {
+        let schema = schema_of::<Wrapped<New>>().unwrap();
+        let bytes = wrapped(New {
+            a: 1,
+            b: 2,
+        });
+        let node = decode(&bytes, &schema).unwrap();
+        let (count, names, t) = fields(&node);
+        assert_eq!((2, vec!["a", "b"]), (count, names));
+        assert!(matches!(t.value, Value::Unsigned(7)));
+    }
+
+    #[test]
+    fn decode_appendable_older() {
+        let schema = schema_of::<Wrapped<New>>().unwrap();
+        let bytes = wrapped(Old {
+            a: 1,
+        });
+        let node = decode(&bytes, &schema).unwrap();
+        let (count, names, t) = fields(&node);
+        assert_eq!((1, vec!["a"]), (count, names));
+        assert!(matches!(t.value, Value::Unsigned(7)));
+    }
+
+    #[test]
+    fn decode_appendable_newer() {
+        let schema = schema_of::<Wrapped<Old>>().unwrap();
+        let bytes = wrapped(New {
+            a: 1,
+            b: 2,
+        });
+        let node = decode(&bytes, &schema).unwrap();
+        let (count, names, t) = fields(&node);
+        assert_eq!((2, vec!["a"]), (count, names));
+        assert_eq!(bytes.len() - 1, t.start);
+        assert!(matches!(t.value, Value::Unsigned(7)));
+    }
+
+    #[test]
+    fn decode_appendable_length_too_long() {
+        let schema = schema_of::<Wrapped<Old>>().unwrap();
+        let failure = decode(&[9, 1, 1, 7], &schema).unwrap_err();
+        assert_eq!(0, failure.offset);
+    }
+
+    #[test]
+    fn decode_shared_unsupported() {
+        let schema = schema_of::<Shared<Rc<u8>>>().unwrap();
+        let bytes = to_bytes(&Shared(Rc::new(1_u8))).unwrap();
+        let failure = decode(&bytes, &schema).unwrap_err();
+        assert_eq!(0, failure.offset);
+        assert!(failure.message.contains("not supported"));
+    }
+}
@@ -0,0 +1,405 @@
+use crate::{
+    decode::{
+        read_varint,
+        Contents,
+        Node,
+        Value,
+    },
+    text::render,
+};
+use std::{
+    convert::TryFrom,
+    fmt::Write as _,
+};
+
+// This is the number of bytes shown on each line of a dump.
+const BYTES_PER_LINE: usize = 8;
+
+// Text shorter than this is more likely to be a coincidence than a string
+// when guessing at the layout of a blob.
+const MIN_GUESSED_TEXT: usize = 2;
+
+struct Dump<'a> {
+    bytes: &'a [u8],
+    output: String,
+}
+
+impl Dump<'_> {
+    fn line(
+        &mut self,
+        start: usize,
+        end: usize,
+        annotation: &str,
+    ) {
+        let mut offset = start;
+        loop {
+            let line_end = end.min(offset + BYTES_PER_LINE);
+            let hex = self.bytes[offset..line_end]
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let annotation = if offset == start {
+                annotation
+            } else {
+                ""
+            };
+            let _ = writeln!(
+                self.output,
+                "{:08x}  {:width$}  {}",
+                offset,
+                hex,
+                annotation,
+                width = BYTES_PER_LINE * 3 - 1
+            );
+            offset = line_end;
+            if offset >= end {
+                break;
+            }
+        }
+    }
+
+    fn node(
+        &mut self,
+        path: &str,
+        node: &Node,
+    ) {
+        let label = if path.is_empty() {
+            "value"
+        } else {
+            path
+        };
+        match &node.value {
+            Value::Str(_) | Value::Bytes(_) => {
+                self.line(
+                    node.start,
+                    node.body,
+                    &format!("{}: length {}", label, node.end - node.body),
+                );
+                self.line(
+                    node.body,
+                    node.end,
+                    &format!("{} = {}", label, render(node)),
+                );
+            },
+            Value::Option(value) => {
+                self.line(
+                    node.start,
+                    node.body,
+                    &format!(
+                        "{}: {}",
+                        label,
+                        if value.is_some() {
+                            "Some"
+                        } else {
+                            "None"
+                        }
+                    ),
+                );
+                if let Some(value) = value {
+                    self.node(path, value);
+                }
+            },
+            Value::Seq(elements) => {
+                self.line(
+                    node.start,
+                    node.body,
+                    &format!("{}: length {}", label, elements.len()),
+                );
+                for (index, element) in elements.iter().enumerate() {
+                    self.node(&format!("{label}[{index}]"), element);
+                }
+            },
+            Value::Map(entries) => {
+                self.line(
+                    node.start,
+                    node.body,
+                    &format!("{}: {} entries", label, entries.len()),
+                );
+                for (index, (key, value)) in entries.iter().enumerate() {
+                    self.node(&format!("{label}[{index}].key"), key);
+                    self.node(&format!("{label}[{index}].value"), value);
+                }
+            },
+            Value::Tuple(elements)
+            | Value::TupleStruct(_, elements)
+            | Value::Variant {
+                contents: Contents::Tuple(elements),
+                ..
+            } => {
+                self.variant(label, node);
+                for (index, element) in elements.iter().enumerate() {
+                    self.node(&join(path, &index.to_string()), element);
+                }
+            },
+            Value::NewtypeStruct(_, value)
+            | Value::Variant {
+                contents: Contents::Newtype(value),
+                ..
+            } => {
+                self.variant(label, node);
+                self.node(path, value);
+            },
+            Value::Struct(_, fields)
+            | Value::Variant {
+                contents: Contents::Struct(fields),
+                ..
+            } => {
+                self.variant(label, node);
+                for (name, field) in fields {
+                    self.node(&join(path, name), field);
+                }
+            },
+            Value::Appendable {
+                ..
+            } => self.appendable(path, label, node),
+            Value::Variant {
+                contents: Contents::Unit,
+                ..
+            } => self.variant(label, node),
+            _ => self.line(
+                node.start,
+                node.end,
+                &format!("{} = {}", label, render(node)),
+            ),
+        }
+    }
+
+    fn appendable(
+        &mut self,
+        path: &str,
+        label: &str,
+        node: &Node,
+    ) {
+        let Value::Appendable {
+            count,
+            fields,
+            ..
+        } = &node.value
+        else {
+            return;
+        };
+        self.line(
+            node.start,
+            node.body,
+            &format!("{label}: appendable, {count} fields"),
+        );
+        for (name, field) in fields {
+            self.node(&join(path, name), field);
+        }
+        // Fields added by a later version are not in the schema.
+        let known = fields.last().map_or(node.body, |(_, field)| field.end);
+        if known < node.end {
+            self.line(known, node.end, &format!("{label}: unknown fields"));
+        }
+    }
+
+    fn variant(
+        &mut self,
+        label: &str,
+        node: &Node,
+    ) {
+        if let Value::Variant {
+            name,
+            index,
+            ..
+        } = &node.value
+        {
+            self.line(
+                node.start,
+                node.body,
+                &format!("{label}: variant {name} (index {index})"),
+            );
+        }
+    }
+}
+
+fn join(
+    path: &str,
+    segment: &str,
+) -> String {
+    if path.is_empty() {
+        segment.to_string()
+    } else {
+        format!("{path}.{segment}")
+    }
+}
+
+/// Produce a hexdump of the given bytes, annotated with the values decoded
+/// from them according to a schema.
+pub fn dump_with_schema(
+    bytes: &[u8],
+    node: &Node,
+) -> String {
+    let mut dump = Dump {
+        bytes,
+        output: String::new(),
+    };
+    dump.node("", node);
+    dump.output
+}
+
+fn guess_text(bytes: &[u8]) -> Option<(usize, usize)> {
+    let (length, _, prefix) = read_varint(bytes)?;
+    let length = usize::try_from(length).ok()?;
+    if length < MIN_GUESSED_TEXT || bytes.len() - prefix < length {
+        return None;
+    }
+    let text = std::str::from_utf8(&bytes[prefix..prefix + length]).ok()?;
+    if text.chars().all(|c| !c.is_control() || c == '\n' || c == '\t') {
+        Some((prefix, length))
+    } else {
+        None
+    }
+}
+
+/// Produce a hexdump of the given bytes, annotated with guesses at the
+/// values they hold, for use when no schema is available.  Each position
+/// is taken to hold either a string, if a plausible length is followed by
+/// printable text, or else a variable-length integer.
+pub fn dump_heuristic(bytes: &[u8]) -> String {
+    let mut dump = Dump {
+        bytes,
+        output: String::new(),
+    };
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let rest = &bytes[offset..];
+        if let Some((prefix, length)) = guess_text(rest) {
+            let body = offset + prefix;
+            let end = body + length;
+            dump.line(offset, body, &format!("length {length}?"));
+            let text = String::from_utf8_lossy(&bytes[body..end]);
+            dump.line(body, end, &format!("string {text:?}?"));
+            offset = end;
+            continue;
+        }
+        let Some((unsigned, signed, length)) = read_varint(rest) else {
+            dump.line(offset, bytes.len(), "truncated or invalid varint");
+            break;
+        };
+        let mut annotation = format!("varint {unsigned}");
+        if let Some(signed) = signed {
+            if i128::from(signed) != i128::from(unsigned) {
+                let _ = write!(annotation, " or signed {signed}");
+            }
+        }
+        match (length, rest[0]) {
+            (1, 0) => annotation.push_str(" (false, None or empty?)"),
+            (1, 1) => annotation.push_str(" (true or Some?)"),
+            _ => {},
+        }
+        dump.line(offset, offset + length, &annotation);
+        offset += length;
+    }
+    dump.output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::decode;
+    use serialization::{
+        schema_of,
+        to_bytes,
+        Appendable,
+    };
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct Message {
+        id: u16,
+        tags: Vec<String>,
+        reply: Option<bool>,
+    }
+
+    #[test]
+    fn annotated_with_schema() {
+        let bytes = to_bytes(&Message {
+            id: 500,
+            tags: vec![String::from("hi")],
+            reply: None,
+        })
+        .unwrap();
+        let schema = schema_of::<Message>().unwrap();
+        let node = decode(&bytes, &schema).unwrap();
+        assert_eq!(
+            concat!(
+                "00000000  83 74                    id = 500\n",
+                "00000002  01                       tags: length 1\n",
+                "00000003  02                       tags[0]: length 2\n",
+                "00000004  68 69                    tags[0] = \"hi\"\n",
+                "00000006  00                       reply: None\n",
+            ),
+            dump_with_schema(&bytes, &node)
+        );
+    }
+
+    #[test]
+    fn long_values_wrap() {
+        let bytes = to_bytes("0123456789").unwrap();
+        let node = decode(&bytes, &serialization::Schema::Str).unwrap();
+        assert_eq!(
+            concat!(
+                "00000000  0a                       value: length 10\n",
+                "00000001  30 31 32 33 34 35 36 37  value = \"0123456789\"\n",
+                "00000009  38 39                    \n",
+            ),
+            dump_with_schema(&bytes, &node)
+        );
+    }
+
+    #[test]
+    fn heuristic() {
+        let bytes = to_bytes(&(300_u32, "abc", -2_i32, Some(()))).unwrap();
+        assert_eq!(
+            concat!(
+                "00000000  82 2c                    varint 300\n",
+                "00000002  03                       length 3?\n",
+                "00000003  61 62 63                 string \"abc\"?\n",
+                "00000006  42                       varint 66 or signed -2\n",
+                "00000007  01                       varint 1 (true or Some?)\n",
+            ),
+            dump_heuristic(&bytes)
+        );
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct Newer {
+        id: u16,
+        extra: u8,
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct Older {
+        id: u16,
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct Wrapped<T> {
+        s: Appendable<T>,
+        t: u8,
+    }
+
+    #[test]
+    fn annotated_appendable() {
+        let bytes = to_bytes(&Wrapped {
+            s: Appendable(Newer {
+                id: 500,
+                extra: 9,
+            }),
+            t: 7,
+        })
+        .unwrap();
+        let schema = schema_of::<Wrapped<Older>>().unwrap();
+        let node = decode(&bytes, &schema).unwrap();
+        assert_eq!(
+            concat!(
+                "00000000  04 02                    s: appendable, 2 fields\n",
+                "00000002  83 74                    s.id = 500\n",
+                "00000004  09                       s: unknown fields\n",
+                "00000005  07                       t = 7\n",
+            ),
+            dump_with_schema(&bytes, &node)
+        );
+    }
+}
@@ -0,0 +1,161 @@
+//! This is a command-line tool for inspecting data encoded by the
+//! `serialization` crate.  Run it with no arguments for usage information.
+
+#![warn(clippy::pedantic)]
+
+mod decode;
+mod dump;
+mod text;
+
+use serialization::{
+    from_bytes,
+    Schema,
+};
+use std::{
+    fs,
+    io::{
+        self,
+        Read as _,
+        Write as _,
+    },
+    process::ExitCode,
+};
+
+const USAGE: &str = "\
+Usage: serialization <COMMAND> [OPTIONS] [INPUT]
+
+Commands:
+  dump [--schema FILE] [INPUT]   Print an annotated hexdump of a blob.
+                                 Without a schema, strings and integers
+                                 are guessed at.
+  validate --schema FILE [INPUT] Check that a blob matches a schema.
+  to-text --schema FILE [INPUT]  Print a blob as text.
+  from-text --schema FILE [INPUT]
+                                 Encode text as a blob, written to
+                                 standard output.
+  schema FILE                    Print the contents of a schema file.
+
+INPUT is read from standard input if it is missing or `-`.
+
+A schema file holds a schema encoded by the library, such as with
+`serialization::to_bytes(&serialization::schema_of::<T>()?)`.
+";
+
+struct Arguments {
+    command: String,
+    schema: Option<String>,
+    input: Option<String>,
+}
+
+fn parse_arguments<I>(mut args: I) -> Result<Arguments, String>
+where
+    I: Iterator<Item = String>,
+{
+    let command = args.next().ok_or_else(|| String::from("missing command"))?;
+    let mut schema = None;
+    let mut input = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--schema" | "-s" => {
+                schema = Some(args.next().ok_or_else(|| {
+                    String::from("missing file name after --schema")
+                })?);
+            },
+            _ if input.is_none() => input = Some(arg),
+            _ => return Err(format!("unexpected argument `{arg}`")),
+        }
+    }
+    Ok(Arguments {
+        command,
+        schema,
+        input,
+    })
+}
+
+fn read_input(input: Option<&str>) -> Result<Vec<u8>, String> {
+    match input {
+        None | Some("-") => {
+            let mut bytes = Vec::new();
+            io::stdin().read_to_end(&mut bytes).map_err(|error| {
+                format!("unable to read standard input: {error}")
+            })?;
+            Ok(bytes)
+        },
+        Some(path) => fs::read(path)
+            .map_err(|error| format!("unable to read `{path}`: {error}")),
+    }
+}
+
+fn read_schema(path: Option<&str>) -> Result<Schema, String> {
+    let path = path.ok_or_else(|| String::from("a schema file is required"))?;
+    let bytes = fs::read(path)
+        .map_err(|error| format!("unable to read `{path}`: {error}"))?;
+    from_bytes(&bytes)
+        .map_err(|error| format!("`{path}` is not a schema file: {error}"))
+}
+
+fn run(arguments: &Arguments) -> Result<(), String> {
+    let input = arguments.input.as_deref();
+    let schema = arguments.schema.as_deref();
+    let output = match arguments.command.as_str() {
+        "dump" => {
+            let bytes = read_input(input)?;
+            match schema {
+                Some(schema) => {
+                    let schema = read_schema(Some(schema))?;
+                    let node = decode::decode(&bytes, &schema)
+                        .map_err(|failure| failure.to_string())?;
+                    dump::dump_with_schema(&bytes, &node).into_bytes()
+                },
+                None => dump::dump_heuristic(&bytes).into_bytes(),
+            }
+        },
+        "validate" => {
+            let schema = read_schema(schema)?;
+            let bytes = read_input(input)?;
+            decode::decode(&bytes, &schema)
+                .map_err(|failure| format!("invalid: {failure}"))?;
+            format!("valid: {} bytes\n", bytes.len()).into_bytes()
+        },
+        "to-text" => {
+            let schema = read_schema(schema)?;
+            let bytes = read_input(input)?;
+            let node = decode::decode(&bytes, &schema)
+                .map_err(|failure| failure.to_string())?;
+            format!("{}\n", text::render(&node)).into_bytes()
+        },
+        "from-text" => {
+            let schema = read_schema(schema)?;
+            let bytes = read_input(input)?;
+            let input = String::from_utf8(bytes)
+                .map_err(|_| String::from("input is not UTF-8 text"))?;
+            text::parse(&input, &schema)
+                .map_err(|failure| failure.to_string())?
+        },
+        "schema" => {
+            let schema = read_schema(input.or(schema))?;
+            format!("{schema:#?}\n").into_bytes()
+        },
+        command => return Err(format!("unknown command `{command}`")),
+    };
+    io::stdout()
+        .write_all(&output)
+        .map_err(|error| format!("unable to write output: {error}"))
+}
+
+fn main() -> ExitCode {
+    let arguments = match parse_arguments(std::env::args().skip(1)) {
+        Ok(arguments) => arguments,
+        Err(message) => {
+            eprintln!("error: {message}\n\n{USAGE}");
+            return ExitCode::from(2);
+        },
+    };
+    match run(&arguments) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("error: {message}");
+            ExitCode::FAILURE
+        },
+    }
+}
@@ -0,0 +1,861 @@
+use crate::decode::{
+    check_supported,
+    is_appendable,
+    Contents,
+    Failure,
+    Node,
+    Scopes,
+    Value,
+};
+use serialization::{
+    to_bytes,
+    Field,
+    Schema,
+    Variant,
+    VariantShape,
+};
+use std::{
+    convert::TryFrom,
+    fmt::Write as _,
+};
+
+fn render_bytes(bytes: &[u8]) -> String {
+    let mut text = String::from("b\"");
+    for byte in bytes {
+        match byte {
+            b'"' => text.push_str("\\\""),
+            b'\\' => text.push_str("\\\\"),
+            0x20..=0x7E => text.push(char::from(*byte)),
+            _ => {
+                let _ = write!(text, "\\x{byte:02x}");
+            },
+        }
+    }
+    text.push('"');
+    text
+}
+
+fn render_list<'n, I>(
+    open: &str,
+    nodes: I,
+    close: &str,
+) -> String
+where
+    I: IntoIterator<Item = &'n Node>,
+{
+    let items = nodes.into_iter().map(render).collect::<Vec<_>>();
+    if open == "(" && items.len() == 1 {
+        format!("({},)", items[0])
+    } else {
+        format!("{}{}{}", open, items.join(", "), close)
+    }
+}
+
+fn render_fields(fields: &[(String, Node)]) -> String {
+    if fields.is_empty() {
+        return String::from("{}");
+    }
+    let fields = fields
+        .iter()
+        .map(|(name, node)| format!("{}: {}", name, render(node)))
+        .collect::<Vec<_>>();
+    format!("{{ {} }}", fields.join(", "))
+}
+
+/// Render a decoded value as text, in a syntax which [`parse`] accepts.
+///
+/// [`parse`]: fn.parse.html
+pub fn render(node: &Node) -> String {
+    match &node.value {
+        Value::Unit => String::from("()"),
+        Value::Bool(value) => value.to_string(),
+        Value::Signed(value) => value.to_string(),
+        Value::Unsigned(value) => value.to_string(),
+        Value::F32(value) => format!("{value:?}"),
+        Value::F64(value) => format!("{value:?}"),
+        Value::Char(value) => format!("{value:?}"),
+        Value::Str(value) => format!("{value:?}"),
+        Value::Bytes(value) => render_bytes(value),
+        Value::Option(None) => String::from("None"),
+        Value::Option(Some(node)) => format!("Some({})", render(node)),
+        Value::Seq(nodes) => render_list("[", nodes, "]"),
+        Value::Map(entries) => {
+            let entries = entries
+                .iter()
+                .map(|(key, value)| {
+                    format!("{}: {}", render(key), render(value))
+                })
+                .collect::<Vec<_>>();
+            format!("{{{}}}", entries.join(", "))
+        },
+        Value::Tuple(nodes) => render_list("(", nodes, ")"),
+        Value::UnitStruct(name) => name.clone(),
+        Value::NewtypeStruct(name, node) => {
+            format!("{}({})", name, render(node))
+        },
+        Value::TupleStruct(name, nodes) => {
+            format!("{}{}", name, render_list("(", nodes, ")"))
+        },
+        Value::Struct(name, fields)
+        | Value::Appendable {
+            name,
+            fields,
+            ..
+        } => format!("{} {}", name, render_fields(fields)),
+        Value::Variant {
+            name,
+            contents,
+            ..
+        } => match contents {
+            Contents::Unit => name.clone(),
+            Contents::Newtype(node) => format!("{}({})", name, render(node)),
+            Contents::Tuple(nodes) => {
+                format!("{}{}", name, render_list("(", nodes, ")"))
+            },
+            Contents::Struct(fields) => {
+                format!("{} {}", name, render_fields(fields))
+            },
+        },
+    }
+}
+
+struct Parser<'t, 's> {
+    text: &'t str,
+    offset: usize,
+    scopes: Scopes<'s>,
+}
+
+impl<'t, 's> Parser<'t, 's> {
+    fn expect(
+        &mut self,
+        expected: char,
+    ) -> Result<(), Failure> {
+        if self.take(expected) {
+            Ok(())
+        } else {
+            Err(self.failure(format!("expected `{expected}`")))
+        }
+    }
+
+    fn failure<M>(
+        &self,
+        message: M,
+    ) -> Failure
+    where
+        M: Into<String>,
+    {
+        Failure::new(self.offset, message)
+    }
+
+    fn identifier(&mut self) -> Option<&'t str> {
+        self.skip_whitespace();
+        let rest = &self.text[self.offset..];
+        let length = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        let identifier = &rest[..length];
+        if identifier
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_alphabetic() || c == '_')
+        {
+            self.offset += length;
+            Some(identifier)
+        } else {
+            None
+        }
+    }
+
+    fn keyword(
+        &mut self,
+        keyword: &str,
+    ) -> bool {
+        let offset = self.offset;
+        if self.identifier() == Some(keyword) {
+            true
+        } else {
+            self.offset = offset;
+            false
+        }
+    }
+
+    fn literal(
+        &mut self,
+        quote: char,
+    ) -> Result<String, Failure> {
+        self.expect(quote)?;
+        let mut value = String::new();
+        let mut chars = self.text[self.offset..].char_indices();
+        while let Some((index, c)) = chars.next() {
+            match c {
+                '\\' => {
+                    let escaped = match chars.next().map(|(_, c)| c) {
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('0') => '\0',
+                        Some('\\') => '\\',
+                        Some('\'') => '\'',
+                        Some('"') => '"',
+                        Some('x') => {
+                            let digits: String = chars
+                                .by_ref()
+                                .take(2)
+                                .map(|(_, c)| c)
+                                .collect();
+                            u8::from_str_radix(&digits, 16)
+                                .map(char::from)
+                                .map_err(|_| self.failure("invalid escape"))?
+                        },
+                        Some('u') => {
+                            let digits: String = chars
+                                .by_ref()
+                                .map(|(_, c)| c)
+                                .take_while(|c| *c != '}')
+                                .collect();
+                            digits
+                                .strip_prefix('{')
+                                .and_then(|digits| {
+                                    u32::from_str_radix(digits, 16).ok()
+                                })
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| self.failure("invalid escape"))?
+                        },
+                        _ => return Err(self.failure("invalid escape")),
+                    };
+                    value.push(escaped);
+                },
+                _ if c == quote => {
+                    self.offset += index + 1;
+                    return Ok(value);
+                },
+                _ => value.push(c),
+            }
+        }
+        Err(self.failure("unterminated literal"))
+    }
+
+    fn name(
+        &mut self,
+        expected: &str,
+    ) -> Result<bool, Failure> {
+        let offset = self.offset;
+        match self.identifier() {
+            Some(name) if name == expected => Ok(true),
+            Some(name) => {
+                self.offset = offset;
+                Err(self
+                    .failure(format!("expected `{expected}`, found `{name}`")))
+            },
+            None => Ok(false),
+        }
+    }
+
+    fn number<T>(&mut self) -> Result<T, Failure>
+    where
+        T: std::str::FromStr,
+    {
+        self.skip_whitespace();
+        let rest = &self.text[self.offset..];
+        let length = rest
+            .find(|c: char| {
+                !(c.is_alphanumeric() || c == '.' || c == '-' || c == '+')
+            })
+            .unwrap_or(rest.len());
+        let value = rest[..length]
+            .parse()
+            .map_err(|_| self.failure("expected a number"))?;
+        self.offset += length;
+        Ok(value)
+    }
+
+    fn parse(
+        &mut self,
+        schema: &'s Schema,
+        output: &mut Vec<u8>,
+    ) -> Result<(), Failure> {
+        let entered = self.scopes.enter(schema);
+        let result = self.parse_value(schema, output);
+        self.scopes.leave(entered);
+        result
+    }
+
+    fn parse_all(
+        &mut self,
+        schemas: &'s [Schema],
+        output: &mut Vec<u8>,
+    ) -> Result<(), Failure> {
+        self.expect('(')?;
+        for (index, schema) in schemas.iter().enumerate() {
+            if index > 0 {
+                self.expect(',')?;
+            }
+            self.parse(schema, output)?;
+        }
+        self.take(',');
+        self.expect(')')
+    }
+
+    // Encode a struct in the appendable layout, as its length, then its
+    // number of fields, then those fields.
+    fn parse_appendable(
+        &mut self,
+        schema: &'s Schema,
+        output: &mut Vec<u8>,
+    ) -> Result<(), Failure> {
+        let Schema::Struct {
+            name,
+            fields,
+        } = schema
+        else {
+            unreachable!("only structs are appendable");
+        };
+        let entered = self.scopes.enter(schema);
+        let mut body = Vec::new();
+        let result = self
+            .name(name)
+            .and_then(|_| self.parse_fields(fields, true, &mut body));
+        self.scopes.leave(entered);
+        result?;
+        self.write(&body.len(), output)?;
+        output.extend(body);
+        Ok(())
+    }
+
+    // Fields may be given in any order, but are encoded in the order of the
+    // schema.  Trailing fields of an appendable struct may be left out, and
+    // its encoding is then preceded by the number of fields present.
+    fn parse_fields(
+        &mut self,
+        fields: &'s [Field],
+        appendable: bool,
+        output: &mut Vec<u8>,
+    ) -> Result<(), Failure> {
+        let mut encodings: Vec<Option<Vec<u8>>> = vec![None; fields.len()];
+        self.expect('{')?;
+        while !self.take('}') {
+            let name = self
+                .identifier()
+                .ok_or_else(|| self.failure("expected a field name"))?;
+            let position =
+                fields.iter().position(|field| field.name == name).ok_or_else(
+                    || self.failure(format!("unknown field `{name}`")),
+                )?;
+            if encodings[position].is_some() {
+                return Err(self.failure(format!("duplicate field `{name}`")));
+            }
+            self.expect(':')?;
+            let mut encoding = Vec::new();
+            self.parse(&fields[position].schema, &mut encoding)?;
+            encodings[position] = Some(encoding);
+            if !self.take(',') {
+                self.expect('}')?;
+                break;
+            }
+        }
+        let count = encodings.iter().take_while(|e| e.is_some()).count();
+        if count < fields.len()
+            && (!appendable || encodings[count..].iter().any(Option::is_some))
+        {
+            return Err(
+                self.failure(format!("missing field `{}`", fields[count].name))
+            );
+        }
+        if appendable {
+            self.write(&count, output)?;
+        }
+        output.extend(encodings.into_iter().flatten().flatten());
+        Ok(())
+    }
+
+    fn parse_integer<T>(
+        &mut self,
+        output: &mut Vec<u8>,
+    ) -> Result<(), Failure>
+    where
+        T: TryFrom<i128> + serde::Serialize,
+    {
+        self.skip_whitespace();
+        let offset = self.offset;
+        let value = T::try_from(self.number::<i128>()?).map_err(|_| {
+            Failure::new(offset, "integer out of range for its type")
+        })?;
+        self.write(&value, output)
+    }
+
+    fn parse_list(
+        &mut self,
+        open: char,
+        close: char,
+        mut element: impl FnMut(&mut Self, &mut Vec<u8>) -> Result<(), Failure>,
+        output: &mut Vec<u8>,
+    ) -> Result<(), Failure> {
+        self.expect(open)?;
+        let mut count = 0_u64;
+        let mut elements = Vec::new();
+        while !self.take(close) {
+            element(self, &mut elements)?;
+            count += 1;
+            if !self.take(',') {
+                self.expect(close)?;
+                break;
+            }
+        }
+        self.write(&count, output)?;
+        output.extend(elements);
+        Ok(())
+    }
+
+    fn parse_value(
+        &mut self,
+        schema: &'s Schema,
+        output: &mut Vec<u8>,
+    ) -> Result<(), Failure> {
+        match schema {
+            Schema::Option(schema) => {
+                if self.keyword("None") {
+                    output.push(0);
+                    Ok(())
+                } else if self.keyword("Some") {
+                    output.push(1);
+                    self.expect('(')?;
+                    self.parse(schema, output)?;
+                    self.expect(')')
+                } else {
+                    Err(self.failure("expected `None` or `Some`"))
+                }
+            },
+            Schema::Seq(schema) => self.parse_list(
+                '[',
+                ']',
+                |parser, output| parser.parse(schema, output),
+                output,
+            ),
+            Schema::Map {
+                key,
+                value,
+            } => self.parse_list(
+                '{',
+                '}',
+                |parser, output| {
+                    parser.parse(key, output)?;
+                    parser.expect(':')?;
+                    parser.parse(value, output)
+                },
+                output,
+            ),
+            Schema::Tuple(schemas) => self.parse_all(schemas, output),
+            Schema::UnitStruct {
+                name,
+            } => {
+                if !self.name(name)? {
+                    self.expect('(')?;
+                    self.expect(')')?;
+                }
+                Ok(())
+            },
+            Schema::NewtypeStruct {
+                name,
+                value,
+            } if is_appendable(name) => {
+                match self.scopes.resolve_struct(value) {
+                    Some(schema) => self.parse_appendable(schema, output),
+                    None => self.parse(value, output),
+                }
+            },
+            Schema::NewtypeStruct {
+                name,
+                value,
+            } => {
+                check_supported(self.offset, name)?;
+                if self.name(name)? {
+                    self.expect('(')?;
+                    self.parse(value, output)?;
+                    self.expect(')')
+                } else {
+                    self.parse(value, output)
+                }
+            },
+            Schema::TupleStruct {
+                name,
+                fields,
+            } => {
+                self.name(name)?;
+                self.parse_all(fields, output)
+            },
+            Schema::Struct {
+                name,
+                fields,
+            } => {
+                self.name(name)?;
+                self.parse_fields(fields, false, output)
+            },
+            Schema::Enum {
+                variants,
+                ..
+            } => self.parse_variant(variants, output),
+            Schema::Ref {
+                name,
+            } => {
+                let schema = self.scopes.resolve(name).ok_or_else(|| {
+                    self.failure(format!("unknown type `{name}`"))
+                })?;
+                self.parse_value(schema, output)
+            },
+            _ => self.parse_primitive(schema, output),
+        }
+    }
+
+    fn parse_primitive(
+        &mut self,
+        schema: &Schema,
+        output: &mut Vec<u8>,
+    ) -> Result<(), Failure> {
+        match schema {
+            Schema::Unit => {
+                self.expect('(')?;
+                self.expect(')')
+            },
+            Schema::Bool => {
+                let value = if self.keyword("true") {
+                    true
+                } else if self.keyword("false") {
+                    false
+                } else {
+                    return Err(self.failure("expected `true` or `false`"));
+                };
+                self.write(&value, output)
+            },
+            Schema::I8 => self.parse_integer::<i8>(output),
+            Schema::I16 => self.parse_integer::<i16>(output),
+            Schema::I32 => self.parse_integer::<i32>(output),
+            Schema::I64 => self.parse_integer::<i64>(output),
+            Schema::U8 => self.parse_integer::<u8>(output),
+            Schema::U16 => self.parse_integer::<u16>(output),
+            Schema::U32 => self.parse_integer::<u32>(output),
+            Schema::U64 => self.parse_integer::<u64>(output),
+            Schema::F32 => {
+                let value = self.number::<f32>()?;
+                self.write(&value, output)
+            },
+            Schema::F64 => {
+                let value = self.number::<f64>()?;
+                self.write(&value, output)
+            },
+            Schema::Char => {
+                let offset = self.offset;
+                let value = self.literal('\'')?;
+                let mut chars = value.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(value), None) => self.write(&value, output),
+                    _ => {
+                        Err(Failure::new(offset, "expected a single character"))
+                    },
+                }
+            },
+            Schema::Str => {
+                let value = self.literal('"')?;
+                self.write(&value, output)
+            },
+            Schema::Bytes => {
+                self.skip_whitespace();
+                self.expect('b')?;
+                let value = self.literal('"')?;
+                let bytes = value
+                    .chars()
+                    .map(u8::try_from)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| self.failure("expected bytes"))?;
+                self.write(&bytes, output)
+            },
+            _ => unreachable!("only primitive types are parsed here"),
+        }
+    }
+
+    fn parse_variant(
+        &mut self,
+        variants: &'s [Variant],
+        output: &mut Vec<u8>,
+    ) -> Result<(), Failure> {
+        let offset = self.offset;
+        let name = self
+            .identifier()
+            .ok_or_else(|| self.failure("expected a variant name"))?;
+        let variant =
+            variants.iter().find(|variant| variant.name == name).ok_or_else(
+                || Failure::new(offset, format!("unknown variant `{name}`")),
+            )?;
+        self.write(&variant.index, output)?;
+        match &variant.shape {
+            VariantShape::Unit => Ok(()),
+            VariantShape::Newtype(schema) => {
+                self.expect('(')?;
+                self.parse(schema, output)?;
+                self.expect(')')
+            },
+            VariantShape::Tuple(schemas) => self.parse_all(schemas, output),
+            VariantShape::Struct(fields) => {
+                self.parse_fields(fields, false, output)
+            },
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.text[self.offset..];
+        self.offset += rest.len() - rest.trim_start().len();
+    }
+
+    fn take(
+        &mut self,
+        expected: char,
+    ) -> bool {
+        self.skip_whitespace();
+        if self.text[self.offset..].starts_with(expected) {
+            self.offset += expected.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn write<T>(
+        &self,
+        value: &T,
+        output: &mut Vec<u8>,
+    ) -> Result<(), Failure>
+    where
+        T: ?Sized + serde::Serialize,
+    {
+        let encoding =
+            to_bytes(value).map_err(|error| self.failure(error.to_string()))?;
+        output.extend(encoding);
+        Ok(())
+    }
+}
+
+/// Encode the value given as text, in the syntax produced by [`render`],
+/// according to the given schema.  Offsets in failures are byte offsets
+/// within the text.
+///
+/// [`render`]: fn.render.html
+pub fn parse(
+    text: &str,
+    schema: &Schema,
+) -> Result<Vec<u8>, Failure> {
+    let mut parser = Parser {
+        text,
+        offset: 0,
+        scopes: Scopes::new(),
+    };
+    let mut output = Vec::new();
+    parser.parse(schema, &mut output)?;
+    parser.skip_whitespace();
+    if parser.offset < text.len() {
+        return Err(parser.failure("unexpected text after value"));
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::decode;
+    use serialization::{
+        schema_of,
+        to_bytes,
+        Appendable,
+        Shared,
+    };
+    use std::{
+        collections::BTreeMap,
+        rc::Rc,
+    };
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct Record {
+        id: u32,
+        name: String,
+        scores: Vec<i16>,
+        ratio: f64,
+        initial: char,
+        digest: Digest,
+        labels: BTreeMap<String, Option<Shape>>,
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct Digest(#[serde(with = "serde_bytes_compat")] Vec<u8>);
+
+    mod serde_bytes_compat {
+        pub fn serialize<S>(
+            bytes: &[u8],
+            serializer: S,
+        ) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            serializer.serialize_bytes(bytes)
+        }
+
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+        where
+            D: serde::Deserializer<'de>,
+        {
+            struct Visitor;
+
+            impl serde::de::Visitor<'_> for Visitor {
+                type Value = Vec<u8>;
+
+                fn expecting(
+                    &self,
+                    f: &mut std::fmt::Formatter,
+                ) -> std::fmt::Result {
+                    write!(f, "bytes")
+                }
+
+                fn visit_bytes<E>(
+                    self,
+                    v: &[u8],
+                ) -> Result<Self::Value, E> {
+                    Ok(v.to_vec())
+                }
+            }
+
+            deserializer.deserialize_bytes(Visitor)
+        }
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    enum Shape {
+        Point,
+        Circle(f32),
+        Rect {
+            width: u8,
+            height: u8,
+        },
+    }
+
+    #[test]
+    fn round_trip() {
+        let schema = schema_of::<Record>().unwrap();
+        let mut labels = BTreeMap::new();
+        labels.insert(String::from("a"), Some(Shape::Circle(1.5)));
+        labels.insert(String::from("b"), None);
+        labels.insert(
+            String::from("c\n"),
+            Some(Shape::Rect {
+                width: 2,
+                height: 3,
+            }),
+        );
+        labels.insert(String::from("d"), Some(Shape::Point));
+        let record = Record {
+            id: 300,
+            name: String::from("caf\u{e9}"),
+            scores: vec![-1, 1000],
+            ratio: 0.25,
+            initial: '"',
+            digest: Digest(vec![0, b'A', 0xFF]),
+            labels,
+        };
+        let bytes = to_bytes(&record).unwrap();
+        let text = render(&decode(&bytes, &schema).unwrap());
+        assert_eq!(
+            concat!(
+                r#"Record { id: 300, name: "café", scores: [-1, 1000], "#,
+                r#"ratio: 0.25, initial: '"', digest: Digest(b"\x00A\xff"), "#,
+                r#"labels: {"a": Some(Circle(1.5)), "b": None, "#,
+                r#""c\n": Some(Rect { width: 2, height: 3 }), "#,
+                r#""d": Some(Point)} }"#
+            ),
+            text
+        );
+        assert_eq!(bytes, parse(&text, &schema).unwrap());
+    }
+
+    #[test]
+    fn parse_fields_in_any_order() {
+        let schema = schema_of::<(u8, Shape)>().unwrap();
+        assert_eq!(
+            to_bytes(&(7_u8, Shape::Rect {
+                width: 1,
+                height: 2,
+            }))
+            .unwrap(),
+            parse("( 7 , Rect { height: 2, width: 1, } )", &schema).unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        let schema = schema_of::<Shape>().unwrap();
+        let failure = parse("Rect { width: 1 }", &schema).unwrap_err();
+        assert_eq!("missing field `height`", failure.message);
+        let failure =
+            parse("Rect { width: 256, height: 1 }", &schema).unwrap_err();
+        assert_eq!(14, failure.offset);
+        let failure = parse("Square", &schema).unwrap_err();
+        assert_eq!("unknown variant `Square`", failure.message);
+        let failure = parse("Point Point", &schema).unwrap_err();
+        assert_eq!(6, failure.offset);
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct Old {
+        a: u8,
+    }
+
+    #[derive(Default, serde::Serialize, serde::Deserialize)]
+    struct New {
+        a: u8,
+        b: u16,
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct Wrapped<T> {
+        s: Appendable<T>,
+        t: u8,
+    }
+
+    #[test]
+    fn round_trip_appendable() {
+        let schema = schema_of::<Wrapped<New>>().unwrap();
+        let bytes = to_bytes(&Wrapped {
+            s: Appendable(New {
+                a: 1,
+                b: 300,
+            }),
+            t: 7,
+        })
+        .unwrap();
+        let text = render(&decode(&bytes, &schema).unwrap());
+        assert_eq!("Wrapped { s: New { a: 1, b: 300 }, t: 7 }", text);
+        assert_eq!(bytes, parse(&text, &schema).unwrap());
+    }
+
+    #[test]
+    fn parse_appendable_without_trailing_fields() {
+        let schema = schema_of::<Wrapped<New>>().unwrap();
+        let old = to_bytes(&Wrapped {
+            s: Appendable(Old {
+                a: 1,
+            }),
+            t: 7,
+        })
+        .unwrap();
+        let bytes =
+            parse("Wrapped { s: New { a: 1 }, t: 7 }", &schema).unwrap();
+        assert_eq!(old, bytes);
+        let text = render(&decode(&bytes, &schema).unwrap());
+        assert_eq!("Wrapped { s: New { a: 1 }, t: 7 }", text);
+        let failure = parse("{ s: { b: 2 }, t: 7 }", &schema).unwrap_err();
+        assert_eq!("missing field `a`", failure.message);
+    }
+
+    #[test]
+    fn parse_shared_unsupported() {
+        let schema = schema_of::<Shared<Rc<u8>>>().unwrap();
+        let failure = parse("1", &schema).unwrap_err();
+        assert_eq!(0, failure.offset);
+        assert!(failure.message.contains("not supported"));
+    }
+}
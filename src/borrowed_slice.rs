@@ -0,0 +1,312 @@
+use bytemuck::Pod;
+use std::{
+    fmt,
+    marker::PhantomData,
+    ops::Deref,
+};
+
+// These are the names of the newtype structs through which a borrowed slice
+// passes its data to a serializer or deserializer, one for each power of two
+// alignment up to 16.  The serializer and deserializer of this crate
+// recognize them and use the aligned-array layout for the bytes inside.
+const ALIGNED_NAMES: [&str; 5] = [
+    "$serialization::Aligned1",
+    "$serialization::Aligned2",
+    "$serialization::Aligned4",
+    "$serialization::Aligned8",
+    "$serialization::Aligned16",
+];
+
+// This marks data laid out in the byte order of the machine which encoded
+// it, so that a machine with the other byte order can reject it.
+pub(crate) const NATIVE_BYTE_ORDER: u8 = if cfg!(target_endian = "big") {
+    1
+} else {
+    0
+};
+
+// Return the alignment in bytes which is requested by the newtype struct
+// with the given name, if it is one of the names used by borrowed slices.
+pub(crate) fn alignment(name: &str) -> Option<usize> {
+    ALIGNED_NAMES
+        .iter()
+        .position(|aligned_name| *aligned_name == name)
+        .map(|index| 1 << index)
+}
+
+fn aligned_name<T>() -> &'static str {
+    let index = std::mem::align_of::<T>().trailing_zeros() as usize;
+    ALIGNED_NAMES[index.min(ALIGNED_NAMES.len() - 1)]
+}
+
+/// This is a slice of plain numbers borrowed directly from the bytes being
+/// decoded, without copying them, in the way that a `&[u8]` or `&str` can be
+/// borrowed.
+///
+/// To make this possible, the elements are encoded in the byte order of the
+/// machine doing the encoding, and preceded by enough padding to align them
+/// naturally, relative to the start of the buffer being encoded into.
+/// The encoding is the number of bytes following, a byte identifying the
+/// byte order (0 for little-endian or 1 for big-endian), a byte holding the
+/// number of padding bytes, the padding, and then the elements.
+///
+/// When decoding, an [`Error::ByteOrderMismatch`] is returned if the
+/// elements were encoded by a machine with the other byte order, and an
+/// [`Error::DataMisaligned`] is returned if the elements are not aligned in
+/// memory.  The latter happens if the buffer being decoded does not start
+/// at an address aligned for the element type.  Buffers allocated by a
+/// `Vec<u8>` are aligned well enough on common platforms, but this is not
+/// guaranteed.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate serialization;
+/// use serialization::BorrowedSlice;
+///
+/// let samples = [1.5_f64, -2.0, 0.25];
+/// let encoding =
+///     serialization::to_bytes(&BorrowedSlice::new(&samples[..])).unwrap();
+/// let decoded: BorrowedSlice<f64> =
+///     serialization::from_bytes(&encoding).unwrap();
+/// assert_eq!(&samples[..], &decoded[..]);
+/// ```
+///
+/// [`Error::ByteOrderMismatch`]: enum.Error.html#variant.ByteOrderMismatch
+/// [`Error::DataMisaligned`]: enum.Error.html#variant.DataMisaligned
+pub struct BorrowedSlice<'de, T> {
+    elements: &'de [T],
+}
+
+impl<'de, T> BorrowedSlice<'de, T> {
+    /// Return the borrowed elements, for as long as the data they were
+    /// borrowed from.
+    #[must_use]
+    pub fn as_slice(&self) -> &'de [T] {
+        self.elements
+    }
+
+    /// Wrap the given elements so that they are encoded in the
+    /// aligned-array layout.
+    #[must_use]
+    pub fn new(elements: &'de [T]) -> Self {
+        Self {
+            elements,
+        }
+    }
+}
+
+impl<T> Clone for BorrowedSlice<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for BorrowedSlice<'_, T> {}
+
+impl<T> fmt::Debug for BorrowedSlice<'_, T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        f.debug_list().entries(self.elements).finish()
+    }
+}
+
+impl<T> Deref for BorrowedSlice<'_, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.elements
+    }
+}
+
+pub(crate) struct Bytes<'a>(pub(crate) &'a [u8]);
+
+impl serde::Serialize for Bytes<'_> {
+    fn serialize<S>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+impl<T> serde::Serialize for BorrowedSlice<'_, T>
+where
+    T: Pod,
+{
+    fn serialize<S>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_newtype_struct(
+            aligned_name::<T>(),
+            &Bytes(bytemuck::cast_slice(self.elements)),
+        )
+    }
+}
+
+struct Visitor<T> {
+    element: PhantomData<T>,
+}
+
+impl<'de, T> serde::de::Visitor<'de> for Visitor<T>
+where
+    T: Pod,
+{
+    type Value = BorrowedSlice<'de, T>;
+
+    fn expecting(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        write!(f, "aligned {}-byte elements", std::mem::size_of::<T>())
+    }
+
+    fn visit_newtype_struct<D>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_bytes(self)
+    }
+
+    fn visit_borrowed_bytes<E>(
+        self,
+        bytes: &'de [u8],
+    ) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        bytemuck::try_cast_slice(bytes).map(BorrowedSlice::new).map_err(
+            |error| match error {
+                bytemuck::PodCastError::OutputSliceWouldHaveSlop => {
+                    E::invalid_length(bytes.len(), &self)
+                },
+                error => E::custom(error),
+            },
+        )
+    }
+}
+
+impl<'de, T> serde::Deserialize<'de> for BorrowedSlice<'de, T>
+where
+    T: Pod,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(aligned_name::<T>(), Visitor {
+            element: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        from_bytes,
+        to_bytes,
+        to_bytes_into,
+        Error,
+    };
+
+    // Copy encoded data into a buffer aligned for any element type, at the
+    // given offset from the aligned address.
+    fn aligned(
+        bytes: &[u8],
+        offset: usize,
+    ) -> Vec<u128> {
+        let mut storage = vec![0_u128; bytes.len() / 16 + 2];
+        bytemuck::cast_slice_mut::<_, u8>(&mut storage)
+            [offset..offset + bytes.len()]
+            .copy_from_slice(bytes);
+        storage
+    }
+
+    #[test]
+    fn serialize_layout() {
+        let serialization = to_bytes(&BorrowedSlice::new(&[1_u32][..]));
+        assert!(serialization.is_ok());
+        let serialization = serialization.unwrap();
+        let mut expected = vec![7, NATIVE_BYTE_ORDER, 1, 0];
+        expected.extend_from_slice(&1_u32.to_ne_bytes());
+        assert_eq!(expected, serialization);
+        let mut buffer = vec![0xFF];
+        assert!(to_bytes_into(&BorrowedSlice::new(&[1_u32][..]), &mut buffer)
+            .is_ok());
+        let mut expected = vec![0xFF, 6, NATIVE_BYTE_ORDER, 0];
+        expected.extend_from_slice(&1_u32.to_ne_bytes());
+        assert_eq!(expected, buffer);
+    }
+
+    #[test]
+    fn round_trip_borrows_input() {
+        let values = [0x0102_u16, 0x0304, 0x0506];
+        for prefix in 0..4 {
+            let mut encoding = vec![0; prefix];
+            assert!(to_bytes_into(
+                &(BorrowedSlice::new(&values[..]), 7_u8),
+                &mut encoding
+            )
+            .is_ok());
+            let storage = aligned(&encoding, 0);
+            let input = &bytemuck::cast_slice::<_, u8>(&storage)
+                [prefix..encoding.len()];
+            let (decoded, after): (BorrowedSlice<u16>, u8) =
+                from_bytes(input).unwrap();
+            assert_eq!(&values[..], &decoded[..]);
+            assert_eq!(7, after);
+            let input_range = input.as_ptr_range();
+            assert!(input_range.contains(&decoded.as_ptr().cast()));
+        }
+    }
+
+    #[test]
+    fn deserialize_misaligned() {
+        let encoding = to_bytes(&BorrowedSlice::new(&[1.0_f64][..])).unwrap();
+        let storage = aligned(&encoding, 1);
+        let input =
+            &bytemuck::cast_slice::<_, u8>(&storage)[1..=encoding.len()];
+        assert!(matches!(
+            from_bytes::<BorrowedSlice<f64>>(input),
+            Err(Error::DataMisaligned)
+        ));
+    }
+
+    #[test]
+    fn deserialize_other_byte_order() {
+        let mut encoding = to_bytes(&BorrowedSlice::new(&[1_u64][..])).unwrap();
+        encoding[1] ^= 1;
+        let storage = aligned(&encoding, 0);
+        let input = &bytemuck::cast_slice::<_, u8>(&storage)[..encoding.len()];
+        assert!(matches!(
+            from_bytes::<BorrowedSlice<u64>>(input),
+            Err(Error::ByteOrderMismatch)
+        ));
+    }
+
+    #[test]
+    fn deserialize_partial_element() {
+        let storage = aligned(&[6, NATIVE_BYTE_ORDER, 1, 0, 1, 2, 3][..], 0);
+        let input = &bytemuck::cast_slice::<_, u8>(&storage)[..7];
+        assert!(matches!(
+            from_bytes::<BorrowedSlice<u16>>(input),
+            Err(Error::Message(_))
+        ));
+    }
+}
@@ -0,0 +1,793 @@
+//! This module exports functions with the C calling convention for
+//! building, encoding, decoding, inspecting and freeing [`Object`] values
+//! from other languages.  The declarations for C and C++ are in
+//! `include/serialization.h`, which is generated from this module when the
+//! crate is built with the `capi` feature, and checked by the tests to
+//! match it.  A static library to link with them is built by
+//! `cargo rustc --lib --features capi --crate-type staticlib`.
+//!
+//! Values are handled through pointers to the opaque `SerializationValue`
+//! type.  Every value returned by a function whose name contains `new` or by
+//! [`serialization_decode`] is owned by the caller, and must be given back
+//! with [`serialization_value_free`], unless it is added to a vector or
+//! collection, which then owns it.  Values returned by functions which look
+//! inside other values are borrowed, and remain valid only until the value
+//! which holds them is changed or freed.  Strings are passed as a pointer to
+//! UTF-8 text and its length in bytes, and are not terminated by a zero
+//! byte.
+//!
+//! Functions which can fail return a [`Status`], and write their results
+//! through the pointers they are given only when they succeed.
+//!
+//! [`Object`]: ../enum.Object.html
+//! [`serialization_decode`]: fn.serialization_decode.html
+//! [`serialization_value_free`]: fn.serialization_value_free.html
+//! [`Status`]: enum.Status.html
+
+use super::{
+    from_bytes,
+    to_bytes,
+    Collection,
+    Object,
+};
+use std::{
+    net::Ipv4Addr,
+    os::raw::c_char,
+};
+
+/// This is a dynamic value, which holds an `Object` and is opaque to C.
+// The layout is given through `cfg_attr` so that the header generator does
+// not see it, and declares the type as opaque rather than as an alias for
+// `Object`, which C knows nothing about.
+#[cfg_attr(all(), repr(transparent))]
+pub struct Value(Object);
+
+/// This identifies the type of the contents of a value.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    /// The value is empty.
+    Empty,
+
+    /// The value holds a boolean.
+    Boolean,
+
+    /// The value holds a signed 32-bit integer.
+    Integer,
+
+    /// The value holds an unsigned 64-bit integer.
+    UnsignedInteger,
+
+    /// The value holds a double-precision floating-point number.
+    Decimal,
+
+    /// The value holds a string.
+    String,
+
+    /// The value holds a vector of other values.
+    Vector,
+
+    /// The value holds a set of other values with distinct names.
+    Collection,
+
+    /// The value holds an IPv4 address.
+    IpAddress,
+
+    /// The value holds a vector of signed 32-bit integers.
+    IntegerVector,
+
+    /// The value holds a vector of unsigned 32-bit integers.
+    UnsignedIntegerVector,
+}
+
+/// This is the outcome of a function which can fail.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    /// The function succeeded.
+    Ok,
+
+    /// A pointer which is required was null.
+    NullArgument,
+
+    /// A value did not have the kind which the function requires.
+    WrongKind,
+
+    /// A string was not valid UTF-8.
+    InvalidUtf8,
+
+    /// An index was not less than the number of elements.
+    OutOfRange,
+
+    /// A collection had no value with the given name.
+    NotFound,
+
+    /// A value could not be encoded.
+    EncodeFailed,
+
+    /// The bytes given did not hold the encoding of a value.
+    DecodeFailed,
+}
+
+// Return the slice with the given start and length, treating a null start
+// as an empty slice if the length is zero.
+unsafe fn slice<'a, T>(
+    data: *const T,
+    len: usize,
+) -> Option<&'a [T]> {
+    if data.is_null() {
+        if len == 0 {
+            Some(&[])
+        } else {
+            None
+        }
+    } else {
+        Some(std::slice::from_raw_parts(data, len))
+    }
+}
+
+// Return the UTF-8 string with the given start and length in bytes.
+unsafe fn text<'a>(
+    data: *const c_char,
+    len: usize,
+) -> std::result::Result<&'a str, Status> {
+    let bytes = slice(data.cast::<u8>(), len).ok_or(Status::NullArgument)?;
+    std::str::from_utf8(bytes).map_err(|_| Status::InvalidUtf8)
+}
+
+// Store the given result through the given pointer, unless it is null.
+unsafe fn write<T>(
+    out: *mut T,
+    result: T,
+) -> Status {
+    if out.is_null() {
+        Status::NullArgument
+    } else {
+        out.write(result);
+        Status::Ok
+    }
+}
+
+fn new(object: Object) -> *mut Value {
+    Box::into_raw(Box::new(Value(object)))
+}
+
+// Return a pointer to the given object as a value held by another value.
+fn borrow(object: &Object) -> *const Value {
+    // `Value` is a transparent wrapper around `Object`, so a pointer to the
+    // one is also a pointer to the other.
+    std::ptr::from_ref(object).cast::<Value>()
+}
+
+// Look up the element of the given value at the given index, after using
+// the given function to find the elements of the value.
+unsafe fn element<'a, T>(
+    value: *const Value,
+    index: usize,
+    elements: impl FnOnce(&'a Object) -> Option<&'a [T]>,
+) -> std::result::Result<&'a T, Status> {
+    let object = &value.as_ref().ok_or(Status::NullArgument)?.0;
+    elements(object)
+        .ok_or(Status::WrongKind)?
+        .get(index)
+        .ok_or(Status::OutOfRange)
+}
+
+/// Return a new empty value.
+#[no_mangle]
+pub extern "C" fn serialization_value_new_empty() -> *mut Value {
+    new(Object::Empty)
+}
+
+/// Return a new value holding the given boolean.
+#[no_mangle]
+pub extern "C" fn serialization_value_new_boolean(value: bool) -> *mut Value {
+    new(Object::Boolean(value))
+}
+
+/// Return a new value holding the given signed integer.
+#[no_mangle]
+pub extern "C" fn serialization_value_new_integer(value: i32) -> *mut Value {
+    new(Object::Integer(value))
+}
+
+/// Return a new value holding the given unsigned integer.
+#[no_mangle]
+pub extern "C" fn serialization_value_new_unsigned_integer(
+    value: u64
+) -> *mut Value {
+    new(Object::UnsignedInteger(value))
+}
+
+/// Return a new value holding the given floating-point number.
+#[no_mangle]
+pub extern "C" fn serialization_value_new_decimal(value: f64) -> *mut Value {
+    new(Object::Decimal(value))
+}
+
+/// Return a new value holding a copy of the given string, or null if the
+/// string is not valid UTF-8.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes, or be null if `len` is zero.
+#[no_mangle]
+pub unsafe extern "C" fn serialization_value_new_string(
+    data: *const c_char,
+    len: usize,
+) -> *mut Value {
+    text(data, len).map_or(std::ptr::null_mut(), |text| new(Object::from(text)))
+}
+
+/// Return a new value holding an empty vector of values.
+#[no_mangle]
+pub extern "C" fn serialization_value_new_vector() -> *mut Value {
+    new(Object::Vector(Vec::new()))
+}
+
+/// Return a new value holding an empty collection of values.
+#[no_mangle]
+pub extern "C" fn serialization_value_new_collection() -> *mut Value {
+    new(Object::Collection(Collection::new()))
+}
+
+/// Return a new value holding the given IPv4 address, whose most
+/// significant byte is the first octet of its dotted rendering.
+#[no_mangle]
+pub extern "C" fn serialization_value_new_ip_address(
+    address: u32
+) -> *mut Value {
+    new(Object::IpAddress(Ipv4Addr::from(address)))
+}
+
+/// Return a new value holding a copy of the given signed integers.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable integers, or be null if `len` is
+/// zero.
+#[no_mangle]
+pub unsafe extern "C" fn serialization_value_new_integer_vector(
+    data: *const i32,
+    len: usize,
+) -> *mut Value {
+    slice(data, len).map_or(std::ptr::null_mut(), |elements| {
+        new(Object::IntegerVector(elements.to_vec()))
+    })
+}
+
+/// Return a new value holding a copy of the given unsigned integers.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable integers, or be null if `len` is
+/// zero.
+#[no_mangle]
+pub unsafe extern "C" fn serialization_value_new_unsigned_integer_vector(
+    data: *const u32,
+    len: usize,
+) -> *mut Value {
+    slice(data, len).map_or(std::ptr::null_mut(), |elements| {
+        new(Object::UnsignedIntegerVector(elements.to_vec()))
+    })
+}
+
+/// Free the given value, along with any values it holds.  Nothing is done
+/// if the pointer is null.
+///
+/// # Safety
+///
+/// `value` must be null or have been returned by this library, and must
+/// not be owned by another value, nor used again afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn serialization_value_free(value: *mut Value) {
+    if !value.is_null() {
+        drop(Box::from_raw(value));
+    }
+}
+
+/// Append the given element to the given vector value.  The vector takes
+/// ownership of the element, which is freed if it cannot be appended.
+///
+/// # Safety
+///
+/// `vector` must be null or point to a value, and `element` must be null
+/// or own a value which is not the vector or held by it.
+#[no_mangle]
+pub unsafe extern "C" fn serialization_vector_push(
+    vector: *mut Value,
+    element: *mut Value,
+) -> Status {
+    if element.is_null() {
+        return Status::NullArgument;
+    }
+    let element = Box::from_raw(element).0;
+    match vector.as_mut() {
+        Some(Value(Object::Vector(elements))) => {
+            elements.push(element);
+            Status::Ok
+        },
+        Some(_) => Status::WrongKind,
+        None => Status::NullArgument,
+    }
+}
+
+/// Put the given element in the given collection value under the given
+/// name, replacing any value it had under that name.  The collection takes
+/// ownership of the element, which is freed if it cannot be put in the
+/// collection.
+///
+/// # Safety
+///
+/// `collection` must be null or point to a value, `name` must point to
+/// `name_len` readable bytes, and `element` must be null or own a value
+/// which is not the collection or held by it.
+#[no_mangle]
+pub unsafe extern "C" fn serialization_collection_set(
+    collection: *mut Value,
+    name: *const c_char,
+    name_len: usize,
+    element: *mut Value,
+) -> Status {
+    if element.is_null() {
+        return Status::NullArgument;
+    }
+    let element = Box::from_raw(element).0;
+    let name = match text(name, name_len) {
+        Ok(name) => name,
+        Err(status) => return status,
+    };
+    match collection.as_mut() {
+        Some(Value(Object::Collection(collection))) => {
+            collection.set_object(name, element);
+            Status::Ok
+        },
+        Some(_) => Status::WrongKind,
+        None => Status::NullArgument,
+    }
+}
+
+/// Encode the given value.  On success, the encoding is stored through
+/// `data` and its length through `len`, and must be given back with
+/// `serialization_bytes_free`.
+///
+/// # Safety
+///
+/// `value` must be null or point to a value, and `data` and `len` must be
+/// null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn serialization_encode(
+    value: *const Value,
+    data: *mut *mut u8,
+    len: *mut usize,
+) -> Status {
+    let value = match value.as_ref() {
+        Some(value) if !data.is_null() && !len.is_null() => value,
+        _ => return Status::NullArgument,
+    };
+    match to_bytes(&value.0) {
+        Ok(encoding) => {
+            let encoding = encoding.into_boxed_slice();
+            len.write(encoding.len());
+            data.write(Box::into_raw(encoding).cast::<u8>());
+            Status::Ok
+        },
+        Err(_) => Status::EncodeFailed,
+    }
+}
+
+/// Free an encoding returned by `serialization_encode`.  Nothing is done if
+/// the pointer is null.
+///
+/// # Safety
+///
+/// `data` must be null or have been returned by `serialization_encode`
+/// along with `len`, and must not be used again afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn serialization_bytes_free(
+    data: *mut u8,
+    len: usize,
+) {
+    if !data.is_null() {
+        drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(data, len)));
+    }
+}
+
+/// Decode a value from the given bytes.  On success, a new value is stored
+/// through `value`.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes, or be null if `len` is zero,
+/// and `value` must be null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn serialization_decode(
+    data: *const u8,
+    len: usize,
+    value: *mut *mut Value,
+) -> Status {
+    let bytes = match slice(data, len) {
+        Some(bytes) if !value.is_null() => bytes,
+        _ => return Status::NullArgument,
+    };
+    match from_bytes(bytes) {
+        Ok(object) => write(value, new(object)),
+        Err(_) => Status::DecodeFailed,
+    }
+}
+
+/// Return the kind of the given value, treating a null pointer as an empty
+/// value.
+///
+/// # Safety
+///
+/// `value` must be null or point to a value.
+#[no_mangle]
+pub unsafe extern "C" fn serialization_value_kind(value: *const Value) -> Kind {
+    match value.as_ref().map(|value| &value.0) {
+        None | Some(Object::Empty) => Kind::Empty,
+        Some(Object::Boolean(_)) => Kind::Boolean,
+        Some(Object::Integer(_)) => Kind::Integer,
+        Some(Object::UnsignedInteger(_)) => Kind::UnsignedInteger,
+        Some(Object::Decimal(_)) => Kind::Decimal,
+        Some(Object::String(_)) => Kind::String,
+        Some(Object::Vector(_)) => Kind::Vector,
+        Some(Object::Collection(_)) => Kind::Collection,
+        Some(Object::IpAddress(_)) => Kind::IpAddress,
+        Some(Object::IntegerVector(_)) => Kind::IntegerVector,
+        Some(Object::UnsignedIntegerVector(_)) => Kind::UnsignedIntegerVector,
+    }
+}
+
+/// Store the boolean held by the given value through `out`.
+///
+/// # Safety
+///
+/// `value` must be null or point to a value, and `out` must be null or
+/// writable.
+#[no_mangle]
+pub unsafe extern "C" fn serialization_value_boolean(
+    value: *const Value,
+    out: *mut bool,
+) -> Status {
+    match value.as_ref() {
+        Some(value) => value
+            .0
+            .as_bool()
+            .map_or(Status::WrongKind, |result| write(out, result)),
+        None => Status::NullArgument,
+    }
+}
+
+/// Store the signed integer held by the given value through `out`.
+///
+/// # Safety
+///
+/// `value` must be null or point to a value, and `out` must be null or
+/// writable.
+#[no_mangle]
+pub unsafe extern "C" fn serialization_value_integer(
+    value: *const Value,
+    out: *mut i32,
+) -> Status {
+    match value.as_ref() {
+        Some(value) => value
+            .0
+            .as_integer()
+            .map_or(Status::WrongKind, |result| write(out, result)),
+        None => Status::NullArgument,
+    }
+}
+
+/// Store the unsigned integer held by the given value through `out`.
+///
+/// # Safety
+///
+/// `value` must be null or point to a value, and `out` must be null or
+/// writable.
+#[no_mangle]
+pub unsafe extern "C" fn serialization_value_unsigned_integer(
+    value: *const Value,
+    out: *mut u64,
+) -> Status {
+    match value.as_ref() {
+        Some(value) => value
+            .0
+            .as_unsigned_integer()
+            .map_or(Status::WrongKind, |result| write(out, result)),
+        None => Status::NullArgument,
+    }
+}
+
+/// Store the floating-point number held by the given value through `out`.
+///
+/// # Safety
+///
+/// `value` must be null or point to a value, and `out` must be null or
+/// writable.
+#[no_mangle]
+pub unsafe extern "C" fn serialization_value_decimal(
+    value: *const Value,
+    out: *mut f64,
+) -> Status {
+    match value.as_ref() {
+        Some(value) => value
+            .0
+            .as_decimal()
+            .map_or(Status::WrongKind, |result| write(out, result)),
+        None => Status::NullArgument,
+    }
+}
+
+/// Store the start and length in bytes of the string held by the given
+/// value through `data` and `len`.  The string is borrowed from the value.
+///
+/// # Safety
+///
+/// `value` must be null or point to a value, and `data` and `len` must be
+/// null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn serialization_value_string(
+    value: *const Value,
+    data: *mut *const c_char,
+    len: *mut usize,
+) -> Status {
+    match value.as_ref() {
+        Some(_) if data.is_null() || len.is_null() => Status::NullArgument,
+        Some(value) => value.0.as_str().map_or(Status::WrongKind, |result| {
+            len.write(result.len());
+            write(data, result.as_ptr().cast::<c_char>())
+        }),
+        None => Status::NullArgument,
+    }
+}
+
+/// Store the IPv4 address held by the given value through `out`, with the
+/// first octet of its dotted rendering in the most significant byte.
+///
+/// # Safety
+///
+/// `value` must be null or point to a value, and `out` must be null or
+/// writable.
+#[no_mangle]
+pub unsafe extern "C" fn serialization_value_ip_address(
+    value: *const Value,
+    out: *mut u32,
+) -> Status {
+    match value.as_ref() {
+        Some(value) => value
+            .0
+            .as_ip_address()
+            .map_or(Status::WrongKind, |result| write(out, u32::from(result))),
+        None => Status::NullArgument,
+    }
+}
+
+/// Store the number of elements held by the given value through `out`.
+/// This is the number of values in a vector or collection, or of integers
+/// in a vector of integers.
+///
+/// # Safety
+///
+/// `value` must be null or point to a value, and `out` must be null or
+/// writable.
+#[no_mangle]
+pub unsafe extern "C" fn serialization_value_len(
+    value: *const Value,
+    out: *mut usize,
+) -> Status {
+    let len = match value.as_ref().map(|value| &value.0) {
+        Some(Object::Vector(elements)) => elements.len(),
+        Some(Object::Collection(collection)) => collection.len(),
+        Some(Object::IntegerVector(elements)) => elements.len(),
+        Some(Object::UnsignedIntegerVector(elements)) => elements.len(),
+        Some(_) => return Status::WrongKind,
+        None => return Status::NullArgument,
+    };
+    write(out, len)
+}
+
+/// Store the element at the given index of the given vector value through
+/// `out`.  The element is borrowed from the vector.
+///
+/// # Safety
+///
+/// `vector` must be null or point to a value, and `out` must be null or
+/// writable.
+#[no_mangle]
+pub unsafe extern "C" fn serialization_vector_get(
+    vector: *const Value,
+    index: usize,
+    out: *mut *const Value,
+) -> Status {
+    match element(vector, index, Object::as_vector) {
+        Ok(element) => write(out, borrow(element)),
+        Err(status) => status,
+    }
+}
+
+/// Store the integer at the given index of the given vector of signed
+/// integers through `out`.
+///
+/// # Safety
+///
+/// `vector` must be null or point to a value, and `out` must be null or
+/// writable.
+#[no_mangle]
+pub unsafe extern "C" fn serialization_integer_vector_get(
+    vector: *const Value,
+    index: usize,
+    out: *mut i32,
+) -> Status {
+    match element(vector, index, Object::as_integer_vector) {
+        Ok(element) => write(out, *element),
+        Err(status) => status,
+    }
+}
+
+/// Store the integer at the given index of the given vector of unsigned
+/// integers through `out`.
+///
+/// # Safety
+///
+/// `vector` must be null or point to a value, and `out` must be null or
+/// writable.
+#[no_mangle]
+pub unsafe extern "C" fn serialization_unsigned_integer_vector_get(
+    vector: *const Value,
+    index: usize,
+    out: *mut u32,
+) -> Status {
+    match element(vector, index, Object::as_unsigned_integer_vector) {
+        Ok(element) => write(out, *element),
+        Err(status) => status,
+    }
+}
+
+/// Store the value which the given collection value has under the given
+/// name through `out`.  The value is borrowed from the collection.
+///
+/// # Safety
+///
+/// `collection` must be null or point to a value, `name` must point to
+/// `name_len` readable bytes, and `out` must be null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn serialization_collection_get(
+    collection: *const Value,
+    name: *const c_char,
+    name_len: usize,
+    out: *mut *const Value,
+) -> Status {
+    let name = match text(name, name_len) {
+        Ok(name) => name,
+        Err(status) => return status,
+    };
+    match collection.as_ref().map(|collection| &collection.0) {
+        Some(Object::Collection(collection)) => collection
+            .get_object(name)
+            .map_or(Status::NotFound, |element| write(out, borrow(element))),
+        Some(_) => Status::WrongKind,
+        None => Status::NullArgument,
+    }
+}
+
+/// Store the name and value of the given entry of a collection through
+/// `name`, `name_len` and `out`, or return the given status if there is no
+/// entry.  Nothing is written unless all three may be.
+unsafe fn write_entry(
+    entry: Option<(&str, &Object)>,
+    missing: Status,
+    name: *mut *const c_char,
+    name_len: *mut usize,
+    out: *mut *const Value,
+) -> Status {
+    if name.is_null() || name_len.is_null() || out.is_null() {
+        return Status::NullArgument;
+    }
+    entry.map_or(missing, |(entry_name, element)| {
+        name.write(entry_name.as_ptr().cast::<c_char>());
+        name_len.write(entry_name.len());
+        write(out, borrow(element))
+    })
+}
+
+/// Store the name and value of the entry at the given index of the given
+/// collection value, in order of their names, through `name`, `name_len`
+/// and `out`.  Both are borrowed from the collection.  This takes time in
+/// proportion to the index, so `serialization_collection_first` and
+/// `serialization_collection_next` are better suited to visiting every
+/// entry.
+///
+/// # Safety
+///
+/// `collection` must be null or point to a value, and `name`, `name_len`
+/// and `out` must be null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn serialization_collection_entry(
+    collection: *const Value,
+    index: usize,
+    name: *mut *const c_char,
+    name_len: *mut usize,
+    out: *mut *const Value,
+) -> Status {
+    match collection.as_ref().map(|collection| &collection.0) {
+        Some(Object::Collection(collection)) => write_entry(
+            collection.iter().nth(index),
+            Status::OutOfRange,
+            name,
+            name_len,
+            out,
+        ),
+        Some(_) => Status::WrongKind,
+        None => Status::NullArgument,
+    }
+}
+
+/// Store the name and value of the entry with the first name in the given
+/// collection value through `name`, `name_len` and `out`.  Both are
+/// borrowed from the collection.  `OutOfRange` is returned if the
+/// collection is empty.
+///
+/// # Safety
+///
+/// `collection` must be null or point to a value, and `name`, `name_len`
+/// and `out` must be null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn serialization_collection_first(
+    collection: *const Value,
+    name: *mut *const c_char,
+    name_len: *mut usize,
+    out: *mut *const Value,
+) -> Status {
+    match collection.as_ref().map(|collection| &collection.0) {
+        Some(Object::Collection(collection)) => write_entry(
+            collection.first_object(),
+            Status::OutOfRange,
+            name,
+            name_len,
+            out,
+        ),
+        Some(_) => Status::WrongKind,
+        None => Status::NullArgument,
+    }
+}
+
+/// Store the name and value of the entry whose name follows `after` in the
+/// given collection value through `name`, `name_len` and `out`.  Both are
+/// borrowed from the collection.  `OutOfRange` is returned if `after` is
+/// the last name, and `NotFound` if the collection has no such name.
+///
+/// # Safety
+///
+/// `collection` must be null or point to a value, `after` must point to
+/// `after_len` readable bytes, and `name`, `name_len` and `out` must be
+/// null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn serialization_collection_next(
+    collection: *const Value,
+    after: *const c_char,
+    after_len: usize,
+    name: *mut *const c_char,
+    name_len: *mut usize,
+    out: *mut *const Value,
+) -> Status {
+    let after = match text(after, after_len) {
+        Ok(after) => after,
+        Err(status) => return status,
+    };
+    match collection.as_ref().map(|collection| &collection.0) {
+        Some(Object::Collection(collection)) => write_entry(
+            collection.next_object(after),
+            if collection.has_object(after) {
+                Status::OutOfRange
+            } else {
+                Status::NotFound
+            },
+            name,
+            name_len,
+            out,
+        ),
+        Some(_) => Status::WrongKind,
+        None => Status::NullArgument,
+    }
+}
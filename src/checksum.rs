@@ -0,0 +1,256 @@
+use super::{
+    from_bytes,
+    to_bytes,
+    Error,
+    Result,
+};
+
+/// This is implemented by algorithms which compute a checksum of encoded
+/// data, for use with [`to_bytes_checked_with`] and
+/// [`from_bytes_checked_with`].
+///
+/// [`to_bytes_checked_with`]: fn.to_bytes_checked_with.html
+/// [`from_bytes_checked_with`]: fn.from_bytes_checked_with.html
+pub trait Checksum {
+    /// Compute the checksum of the given data.  The result must always have
+    /// the length returned by [`length`].
+    ///
+    /// [`length`]: #tymethod.length
+    fn checksum(
+        &self,
+        data: &[u8],
+    ) -> Vec<u8>;
+
+    /// Return the number of bytes in the checksums computed by this
+    /// algorithm.
+    fn length(&self) -> usize;
+}
+
+/// This is the CRC-32C (Castagnoli) checksum algorithm, which is used by
+/// [`to_bytes_checked`] and [`from_bytes_checked`].  The checksum is
+/// appended as four bytes, most significant byte first.
+///
+/// [`to_bytes_checked`]: fn.to_bytes_checked.html
+/// [`from_bytes_checked`]: fn.from_bytes_checked.html
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Crc32c;
+
+impl Crc32c {
+    /// Compute the CRC-32C of the given data.
+    #[allow(clippy::cast_possible_truncation)]
+    #[must_use]
+    pub fn compute(data: &[u8]) -> u32 {
+        !data.iter().fold(!0_u32, |crc, byte| {
+            CRC32C_TABLE[usize::from((crc as u8) ^ byte)] ^ (crc >> 8)
+        })
+    }
+}
+
+impl Checksum for Crc32c {
+    fn checksum(
+        &self,
+        data: &[u8],
+    ) -> Vec<u8> {
+        Self::compute(data).to_be_bytes().to_vec()
+    }
+
+    fn length(&self) -> usize {
+        4
+    }
+}
+
+const CRC32C_POLYNOMIAL: u32 = 0x82F6_3B78;
+
+#[allow(clippy::cast_possible_truncation)]
+const CRC32C_TABLE: [u32; 256] = {
+    let mut table = [0_u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 0 {
+                crc >> 1
+            } else {
+                (crc >> 1) ^ CRC32C_POLYNOMIAL
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// This function is used to encode a value into a sequence of bytes, as
+/// [`to_bytes`] does, followed by a CRC-32C of those bytes, so that
+/// corruption of the data can be detected by [`from_bytes_checked`].
+///
+/// # Errors
+///
+/// This function returns the same errors as [`to_bytes`].
+///
+/// [`to_bytes`]: fn.to_bytes.html
+/// [`from_bytes_checked`]: fn.from_bytes_checked.html
+pub fn to_bytes_checked<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: ?Sized + serde::Serialize,
+{
+    to_bytes_checked_with(value, &Crc32c)
+}
+
+/// This function is used to encode a value into a sequence of bytes, as
+/// [`to_bytes`] does, followed by a checksum of those bytes computed by the
+/// given algorithm.
+///
+/// # Errors
+///
+/// This function returns the same errors as [`to_bytes`].
+///
+/// [`to_bytes`]: fn.to_bytes.html
+pub fn to_bytes_checked_with<T, C>(
+    value: &T,
+    checksum: &C,
+) -> Result<Vec<u8>>
+where
+    T: ?Sized + serde::Serialize,
+    C: ?Sized + Checksum,
+{
+    let mut buffer = to_bytes(value)?;
+    let trailer = checksum.checksum(&buffer);
+    buffer.extend(trailer);
+    Ok(buffer)
+}
+
+/// This function is used to decode a value from a sequence of bytes made
+/// by [`to_bytes_checked`], verifying the CRC-32C at the end before
+/// decoding the value.
+///
+/// # Errors
+///
+/// [`Error::ChecksumMismatch`] is returned if the checksum does not match
+/// the data, and [`Error::ValueTruncated`] is returned if there are not
+/// enough bytes to hold a checksum.  Otherwise this function returns the
+/// same errors as [`from_bytes`].
+///
+/// [`to_bytes_checked`]: fn.to_bytes_checked.html
+/// [`from_bytes`]: fn.from_bytes.html
+/// [`Error::ChecksumMismatch`]: enum.Error.html#variant.ChecksumMismatch
+/// [`Error::ValueTruncated`]: enum.Error.html#variant.ValueTruncated
+pub fn from_bytes_checked<'de, T>(bytes: &'de [u8]) -> Result<T>
+where
+    T: serde::Deserialize<'de>,
+{
+    from_bytes_checked_with(bytes, &Crc32c)
+}
+
+/// This function is used to decode a value from a sequence of bytes made
+/// by [`to_bytes_checked_with`], verifying the checksum at the end, using
+/// the given algorithm, before decoding the value.
+///
+/// # Errors
+///
+/// This function returns the same errors as [`from_bytes_checked`].
+///
+/// [`to_bytes_checked_with`]: fn.to_bytes_checked_with.html
+/// [`from_bytes_checked`]: fn.from_bytes_checked.html
+pub fn from_bytes_checked_with<'de, T, C>(
+    bytes: &'de [u8],
+    checksum: &C,
+) -> Result<T>
+where
+    T: serde::Deserialize<'de>,
+    C: ?Sized + Checksum,
+{
+    let data_length = bytes
+        .len()
+        .checked_sub(checksum.length())
+        .ok_or(Error::ValueTruncated)?;
+    let (data, trailer) = bytes.split_at(data_length);
+    if checksum.checksum(data) == trailer {
+        from_bytes(data)
+    } else {
+        Err(Error::ChecksumMismatch(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32c_check_value() {
+        assert_eq!(0xE306_9283, Crc32c::compute(b"123456789"));
+        assert_eq!(0, Crc32c::compute(b""));
+    }
+
+    #[test]
+    fn serialize_checked() {
+        let serialization = to_bytes_checked("123456789");
+        assert!(serialization.is_ok());
+        let serialization = serialization.unwrap();
+        assert_eq!(
+            &[9, 49, 50, 51, 52, 53, 54, 55, 56, 57, 0x36, 0xB2, 0x9A, 0xE0][..],
+            serialization
+        );
+    }
+
+    #[test]
+    fn deserialize_checked() {
+        let serialization = to_bytes_checked(&(42_u32, "foo")).unwrap();
+        let deserialization = from_bytes_checked(&serialization);
+        assert!(deserialization.is_ok());
+        let deserialization: (u32, &str) = deserialization.unwrap();
+        assert_eq!((42, "foo"), deserialization);
+    }
+
+    #[test]
+    fn deserialize_checked_corrupt() {
+        let mut serialization = to_bytes_checked(&(42_u32, "foo")).unwrap();
+        for i in 0..serialization.len() {
+            serialization[i] ^= 0x10;
+            let deserialization: Result<(u32, &str)> =
+                from_bytes_checked(&serialization);
+            assert!(matches!(deserialization, Err(Error::ChecksumMismatch(0))));
+            serialization[i] ^= 0x10;
+        }
+    }
+
+    #[test]
+    fn deserialize_checked_truncated() {
+        let deserialization: Result<u8> = from_bytes_checked(&[1, 2, 3][..]);
+        assert!(matches!(deserialization, Err(Error::ValueTruncated)));
+    }
+
+    #[test]
+    fn custom_checksum() {
+        struct Sum;
+
+        impl Checksum for Sum {
+            fn checksum(
+                &self,
+                data: &[u8],
+            ) -> Vec<u8> {
+                vec![data
+                    .iter()
+                    .fold(0_u8, |sum, byte| sum.wrapping_add(*byte))]
+            }
+
+            fn length(&self) -> usize {
+                1
+            }
+        }
+
+        let serialization = to_bytes_checked_with(&[1_u8, 2, 3][..], &Sum);
+        assert!(serialization.is_ok());
+        let serialization = serialization.unwrap();
+        assert_eq!(&[3, 1, 2, 3, 9][..], serialization);
+        let deserialization: Result<Vec<u8>> =
+            from_bytes_checked_with(&serialization, &Sum);
+        assert!(deserialization.is_ok());
+        assert_eq!(vec![1, 2, 3], deserialization.unwrap());
+        let deserialization: Result<Vec<u8>> =
+            from_bytes_checked_with(&[3, 1, 2, 3, 8][..], &Sum);
+        assert!(matches!(deserialization, Err(Error::ChecksumMismatch(0))));
+    }
+}
@@ -0,0 +1,272 @@
+use super::Object;
+use std::{
+    collections::BTreeMap,
+    ops::Bound,
+};
+
+/// This is a set of named [`Object`] values, equivalent to the C++
+/// `SerializedCollection` class.
+///
+/// Objects are kept in the order of their names, and a collection is
+/// encoded exactly as the C++ class encodes it: the number of objects,
+/// followed by the name and encoding of each object, in name order.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate serialization;
+/// use serialization::{
+///     Collection,
+///     Object,
+/// };
+///
+/// let mut settings = Collection::new();
+/// settings.set_object("port", Object::UnsignedInteger(8080));
+/// settings.set_object("host", Object::from("localhost"));
+/// assert!(settings.has_object("port"));
+/// assert_eq!(
+///     Some("localhost"),
+///     settings.get_object("host").and_then(Object::as_str)
+/// );
+///
+/// let mut names = Vec::new();
+/// let mut next = settings.first_object();
+/// while let Some((name, _)) = next {
+///     names.push(name);
+///     next = settings.next_object(name);
+/// }
+/// assert_eq!(vec!["host", "port"], names);
+/// ```
+///
+/// [`Object`]: enum.Object.html
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Collection {
+    objects: BTreeMap<String, Object>,
+}
+
+impl Collection {
+    /// Return the object with the first name in the collection, along with
+    /// its name, or `None` if the collection is empty.
+    #[must_use]
+    pub fn first_object(&self) -> Option<(&str, &Object)> {
+        self.objects.iter().next().map(|(name, object)| (name.as_str(), object))
+    }
+
+    /// Return the object with the given name, if the collection has one.
+    #[must_use]
+    pub fn get_object(
+        &self,
+        name: &str,
+    ) -> Option<&Object> {
+        self.objects.get(name)
+    }
+
+    /// Return a mutable reference to the object with the given name, if the
+    /// collection has one.
+    pub fn get_object_mut(
+        &mut self,
+        name: &str,
+    ) -> Option<&mut Object> {
+        self.objects.get_mut(name)
+    }
+
+    /// Determine whether or not the collection has an object with the
+    /// given name.
+    #[must_use]
+    pub fn has_object(
+        &self,
+        name: &str,
+    ) -> bool {
+        self.objects.contains_key(name)
+    }
+
+    /// Return `true` if the collection has no objects.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    /// Return an iterator over the names and objects in the collection, in
+    /// name order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Object)> {
+        self.objects.iter().map(|(name, object)| (name.as_str(), object))
+    }
+
+    /// Return the number of objects in the collection.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// Return a new collection which has no objects.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the object whose name follows the given name in the
+    /// collection, along with its name.  `None` is returned if the given
+    /// name is the last in the collection, or if the collection has no
+    /// object with the given name.
+    #[must_use]
+    pub fn next_object(
+        &self,
+        name: &str,
+    ) -> Option<(&str, &Object)> {
+        if !self.has_object(name) {
+            return None;
+        }
+        self.objects
+            .range::<str, _>((Bound::Excluded(name), Bound::Unbounded))
+            .next()
+            .map(|(name, object)| (name.as_str(), object))
+    }
+
+    /// Remove the object with the given name from the collection, returning
+    /// it if the collection had one.
+    pub fn remove_object(
+        &mut self,
+        name: &str,
+    ) -> Option<Object> {
+        self.objects.remove(name)
+    }
+
+    /// Put the given object in the collection under the given name,
+    /// returning any object which the collection previously had under the
+    /// same name.
+    pub fn set_object<N, O>(
+        &mut self,
+        name: N,
+        object: O,
+    ) -> Option<Object>
+    where
+        N: Into<String>,
+        O: Into<Object>,
+    {
+        self.objects.insert(name.into(), object.into())
+    }
+}
+
+impl<N, O> std::iter::FromIterator<(N, O)> for Collection
+where
+    N: Into<String>,
+    O: Into<Object>,
+{
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = (N, O)>,
+    {
+        Self {
+            objects: iter
+                .into_iter()
+                .map(|(name, object)| (name.into(), object.into()))
+                .collect(),
+        }
+    }
+}
+
+impl IntoIterator for Collection {
+    type IntoIter = std::collections::btree_map::IntoIter<String, Object>;
+    type Item = (String, Object);
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.objects.into_iter()
+    }
+}
+
+impl serde::Serialize for Collection {
+    fn serialize<S>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.objects.serialize(serializer)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Collection {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Ok(Self {
+            objects: BTreeMap::deserialize(deserializer)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        from_bytes,
+        to_bytes,
+        Result,
+    };
+
+    #[test]
+    fn operations() {
+        let mut collection = Collection::new();
+        assert!(collection.is_empty());
+        assert!(collection.first_object().is_none());
+        assert!(collection.set_object("b", true).is_none());
+        assert!(collection.set_object("a", 1).is_none());
+        assert_eq!(Some(Object::Integer(1)), collection.set_object("a", 2));
+        assert_eq!(2, collection.len());
+        assert!(collection.has_object("a"));
+        assert!(!collection.has_object("c"));
+        assert_eq!(Some(&Object::Integer(2)), collection.get_object("a"));
+        assert!(collection.get_object("c").is_none());
+        assert_eq!(Some(("a", &Object::Integer(2))), collection.first_object());
+        assert_eq!(
+            Some(("b", &Object::Boolean(true))),
+            collection.next_object("a")
+        );
+        assert!(collection.next_object("b").is_none());
+        assert!(collection.next_object("aa").is_none());
+        assert_eq!(Some(Object::Integer(2)), collection.remove_object("a"));
+        assert!(collection.remove_object("a").is_none());
+        assert_eq!(
+            Some(("b", &Object::Boolean(true))),
+            collection.first_object()
+        );
+    }
+
+    #[test]
+    fn serialize_collection() {
+        let collection: Collection =
+            vec![("b", Object::Boolean(true)), ("a", Object::Integer(1))]
+                .into_iter()
+                .collect();
+        let serialization = to_bytes(&collection);
+        assert!(serialization.is_ok());
+        let serialization = serialization.unwrap();
+        assert_eq!(
+            &[2, 1, 0x61, 3, 1, 0x6E, 1, 1, 0x62, 3, 1, 0x62, 1][..],
+            serialization
+        );
+        let serialization = to_bytes(&Object::Collection(collection));
+        assert!(serialization.is_ok());
+        let serialization = serialization.unwrap();
+        assert_eq!(
+            &[15, 1, 0x63, 2, 1, 0x61, 3, 1, 0x6E, 1, 1, 0x62, 3, 1, 0x62, 1][..],
+            serialization
+        );
+    }
+
+    #[test]
+    fn deserialize_collection() {
+        let deserialization: Result<Object> = from_bytes(
+            &[15, 1, 0x63, 2, 1, 0x62, 3, 1, 0x62, 1, 1, 0x61, 3, 1, 0x6E, 1][..],
+        );
+        assert!(deserialization.is_ok());
+        let deserialization = deserialization.unwrap();
+        let collection = deserialization.as_collection().unwrap();
+        assert_eq!(
+            vec![("a", &Object::Integer(1)), ("b", &Object::Boolean(true))],
+            collection.iter().collect::<Vec<_>>()
+        );
+    }
+}
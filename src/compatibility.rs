@@ -0,0 +1,1243 @@
+use super::{
+    appendable,
+    borrowed_slice,
+    schema::type_name,
+    shared,
+    Field,
+    Schema,
+    Variant,
+    VariantShape,
+};
+use std::fmt;
+
+/// This classifies a [`Change`] between two schemas by whether or not data
+/// encoded with the older schema can still be decoded with the newer one.
+///
+/// [`Change`]: struct.Change.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compatibility {
+    /// Data encoded with the older schema can no longer be decoded
+    /// correctly; decoding may fail, or may silently produce wrong values.
+    Breaking,
+
+    /// Data encoded with the older schema still decodes to the same values.
+    BackwardCompatible,
+}
+
+/// This is the kind of a [`Change`] found between two schemas.
+///
+/// [`Change`]: struct.Change.html
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// A field was added.  Since fields are encoded by position, older data
+    /// does not have a value for it.
+    FieldAdded {
+        /// This is the name of the field.
+        name: String,
+    },
+
+    /// A field was removed.
+    FieldRemoved {
+        /// This is the name of the field.
+        name: String,
+    },
+
+    /// A field at the same position, with a compatible layout, was given a
+    /// new name.  Names are not encoded, so this does not affect the data.
+    FieldRenamed {
+        /// This is the older name of the field.
+        from: String,
+
+        /// This is the newer name of the field.
+        to: String,
+    },
+
+    /// A field was added at the end of an [`Appendable`] struct.  Older
+    /// data stops before it, and it is given its default value.
+    ///
+    /// [`Appendable`]: struct.Appendable.html
+    TrailingFieldAdded {
+        /// This is the name of the field.
+        name: String,
+    },
+
+    /// A field was removed from the end of an [`Appendable`] struct.  Its
+    /// value in older data is skipped.
+    ///
+    /// [`Appendable`]: struct.Appendable.html
+    TrailingFieldRemoved {
+        /// This is the name of the field.
+        name: String,
+    },
+
+    /// A field was moved to a different position.
+    FieldReordered {
+        /// This is the name of the field.
+        name: String,
+
+        /// This is the older position of the field.
+        from: usize,
+
+        /// This is the newer position of the field.
+        to: usize,
+    },
+
+    /// An integer was changed to a narrower type with the same encoding,
+    /// which may not be able to hold values in older data.
+    IntegerNarrowed {
+        /// This is the older type.
+        from: Schema,
+
+        /// This is the newer type.
+        to: Schema,
+    },
+
+    /// An integer was changed to a wider type with the same encoding.
+    IntegerWidened {
+        /// This is the older type.
+        from: Schema,
+
+        /// This is the newer type.
+        to: Schema,
+    },
+
+    /// A value was changed to a type with a different layout.
+    TypeChanged {
+        /// This describes the older type.
+        from: String,
+
+        /// This describes the newer type.
+        to: String,
+    },
+
+    /// A struct or enum was given a new name.  Names of types are not
+    /// encoded, so this does not affect the data.
+    TypeRenamed {
+        /// This is the older name of the type.
+        from: String,
+
+        /// This is the newer name of the type.
+        to: String,
+    },
+
+    /// A variant was added to an enum.  Older data never selects it.
+    VariantAdded {
+        /// This is the name of the variant.
+        name: String,
+
+        /// This is the index of the variant.
+        index: u32,
+    },
+
+    /// A variant was removed from an enum.
+    VariantRemoved {
+        /// This is the name of the variant.
+        name: String,
+
+        /// This is the index which the variant had.
+        index: u32,
+    },
+
+    /// A variant with the same index, and with a compatible layout, was
+    /// given a new name.  Names are not encoded, so this does not affect
+    /// the data.
+    VariantRenamed {
+        /// This is the older name of the variant.
+        from: String,
+
+        /// This is the newer name of the variant.
+        to: String,
+    },
+
+    /// A variant was given a different index, such as by inserting another
+    /// variant before it, so that older data selects a different variant.
+    VariantReindexed {
+        /// This is the name of the variant.
+        name: String,
+
+        /// This is the older index of the variant.
+        from: u32,
+
+        /// This is the newer index of the variant.
+        to: u32,
+    },
+}
+
+/// This is a difference found by [`check_compatibility`] between a stored
+/// schema and the current one.
+///
+/// [`check_compatibility`]: fn.check_compatibility.html
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Change {
+    /// This locates the changed value within the schema, as a list of
+    /// field names, tuple positions, and variant names, separated by dots.
+    /// It is empty if the change is at the top of the schema.
+    pub path: String,
+
+    /// This describes the change.
+    pub kind: ChangeKind,
+}
+
+impl Change {
+    /// Classify the change by whether or not data encoded with the stored
+    /// schema can still be decoded with the current one.
+    #[must_use]
+    pub fn compatibility(&self) -> Compatibility {
+        match self.kind {
+            ChangeKind::FieldRenamed {
+                ..
+            }
+            | ChangeKind::IntegerWidened {
+                ..
+            }
+            | ChangeKind::TrailingFieldAdded {
+                ..
+            }
+            | ChangeKind::TrailingFieldRemoved {
+                ..
+            }
+            | ChangeKind::TypeRenamed {
+                ..
+            }
+            | ChangeKind::VariantAdded {
+                ..
+            }
+            | ChangeKind::VariantRenamed {
+                ..
+            } => Compatibility::BackwardCompatible,
+            _ => Compatibility::Breaking,
+        }
+    }
+
+    /// Return `true` if data encoded with the stored schema can no longer
+    /// be decoded correctly with the current one.
+    #[must_use]
+    pub fn is_breaking(&self) -> bool {
+        self.compatibility() == Compatibility::Breaking
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path)?;
+        }
+        match &self.kind {
+            ChangeKind::FieldAdded {
+                name,
+            } => write!(f, "field `{name}` added"),
+            ChangeKind::FieldRemoved {
+                name,
+            } => write!(f, "field `{name}` removed"),
+            ChangeKind::FieldRenamed {
+                from,
+                to,
+            } => write!(f, "field `{from}` renamed to `{to}`"),
+            ChangeKind::TrailingFieldAdded {
+                name,
+            } => write!(f, "field `{name}` added at the end"),
+            ChangeKind::TrailingFieldRemoved {
+                name,
+            } => write!(f, "field `{name}` removed from the end"),
+            ChangeKind::FieldReordered {
+                name,
+                from,
+                to,
+            } => write!(f, "field `{name}` moved from position {from} to {to}"),
+            ChangeKind::IntegerNarrowed {
+                from,
+                to,
+            } => write!(
+                f,
+                "integer narrowed from {} to {}",
+                describe(from),
+                describe(to)
+            ),
+            ChangeKind::IntegerWidened {
+                from,
+                to,
+            } => write!(
+                f,
+                "integer widened from {} to {}",
+                describe(from),
+                describe(to)
+            ),
+            ChangeKind::TypeChanged {
+                from,
+                to,
+            } => write!(f, "type changed from {from} to {to}"),
+            ChangeKind::TypeRenamed {
+                from,
+                to,
+            } => write!(f, "type `{from}` renamed to `{to}`"),
+            ChangeKind::VariantAdded {
+                name,
+                index,
+            } => write!(f, "variant `{name}` added at index {index}"),
+            ChangeKind::VariantRemoved {
+                name,
+                index,
+            } => write!(f, "variant `{name}` removed from index {index}"),
+            ChangeKind::VariantRenamed {
+                from,
+                to,
+            } => write!(f, "variant `{from}` renamed to `{to}`"),
+            ChangeKind::VariantReindexed {
+                name,
+                from,
+                to,
+            } => write!(f, "variant `{name}` moved from index {from} to {to}"),
+        }
+    }
+}
+
+/// This function is used to find the differences between the schema with
+/// which data was stored and the current schema of the same type, as
+/// determined by [`schema_of`], and to classify each difference as
+/// breaking or backward compatible.
+///
+/// Fields and variants are matched by name, and types are compared by the
+/// layout of their encoding, so that wrapping a value in a newtype struct
+/// or renaming a type is not reported as a breaking change.  Wrapping a
+/// value in [`Appendable`], [`Shared`], or [`BorrowedSlice`] does change
+/// its layout, however, and fields may be added to or removed from the end
+/// of an appendable struct without breaking older data.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate serialization;
+/// # extern crate serde;
+/// use serialization::{
+///     check_compatibility,
+///     schema_of,
+/// };
+///
+/// mod v1 {
+///     #[derive(serde::Deserialize)]
+///     pub enum Status {
+///         Active,
+///         Retired,
+///     }
+/// }
+///
+/// mod v2 {
+///     #[derive(serde::Deserialize)]
+///     pub enum Status {
+///         Active,
+///         Suspended,
+///         Retired,
+///     }
+/// }
+///
+/// let stored = schema_of::<v1::Status>().unwrap();
+/// let current = schema_of::<v2::Status>().unwrap();
+/// let changes = check_compatibility(&stored, &current);
+/// assert_eq!(
+///     vec![
+///         "Retired: variant `Retired` moved from index 1 to 2",
+///         "Suspended: variant `Suspended` added at index 1",
+///     ],
+///     changes.iter().map(ToString::to_string).collect::<Vec<_>>()
+/// );
+/// assert!(changes[0].is_breaking());
+/// assert!(!changes[1].is_breaking());
+/// ```
+///
+/// [`schema_of`]: fn.schema_of.html
+/// [`Appendable`]: struct.Appendable.html
+/// [`Shared`]: struct.Shared.html
+/// [`BorrowedSlice`]: struct.BorrowedSlice.html
+#[must_use]
+pub fn check_compatibility(
+    stored: &Schema,
+    current: &Schema,
+) -> Vec<Change> {
+    let mut changes = Vec::new();
+    compare(&mut changes, "", stored, current);
+    changes
+}
+
+fn describe(schema: &Schema) -> String {
+    match schema {
+        Schema::Unit => String::from("unit"),
+        Schema::Bool => String::from("bool"),
+        Schema::I8 => String::from("i8"),
+        Schema::I16 => String::from("i16"),
+        Schema::I32 => String::from("i32"),
+        Schema::I64 => String::from("i64"),
+        Schema::U8 => String::from("u8"),
+        Schema::U16 => String::from("u16"),
+        Schema::U32 => String::from("u32"),
+        Schema::U64 => String::from("u64"),
+        Schema::F32 => String::from("f32"),
+        Schema::F64 => String::from("f64"),
+        Schema::Char => String::from("char"),
+        Schema::Str => String::from("string"),
+        Schema::Bytes => String::from("bytes"),
+        Schema::Option(_) => String::from("option"),
+        Schema::Seq(_) => String::from("sequence"),
+        Schema::Map {
+            ..
+        } => String::from("map"),
+        Schema::Tuple(schemas) => format!("tuple of {}", schemas.len()),
+        Schema::NewtypeStruct {
+            name,
+            value,
+        } if name == appendable::NAME => {
+            format!("appendable {}", describe(value))
+        },
+        Schema::NewtypeStruct {
+            name,
+            value,
+        } if name == shared::NAME => format!("shared {}", describe(value)),
+        Schema::NewtypeStruct {
+            name,
+            ..
+        } if borrowed_slice::alignment(name).is_some() => format!(
+            "slice aligned to {} bytes",
+            borrowed_slice::alignment(name).unwrap_or(1)
+        ),
+        Schema::UnitStruct {
+            name,
+        }
+        | Schema::NewtypeStruct {
+            name,
+            ..
+        }
+        | Schema::TupleStruct {
+            name,
+            ..
+        }
+        | Schema::Struct {
+            name,
+            ..
+        } => format!("struct `{name}`"),
+        Schema::Enum {
+            name,
+            ..
+        } => format!("enum `{name}`"),
+        Schema::Ref {
+            name,
+        } => format!("`{name}`"),
+    }
+}
+
+fn describe_shape(shape: &VariantShape) -> String {
+    match shape {
+        VariantShape::Unit => String::from("unit variant"),
+        VariantShape::Newtype(_) => String::from("newtype variant"),
+        VariantShape::Tuple(schemas) => {
+            format!("tuple variant of {}", schemas.len())
+        },
+        VariantShape::Struct(_) => String::from("struct variant"),
+    }
+}
+
+// Integers which share an encoding are ranked by width, so that a change
+// between them can be reported as widening or narrowing.
+fn integer_rank(schema: &Schema) -> Option<(bool, u8)> {
+    match schema {
+        Schema::I16 => Some((true, 0)),
+        Schema::I32 => Some((true, 1)),
+        Schema::I64 => Some((true, 2)),
+        Schema::U16 => Some((false, 0)),
+        Schema::U32 => Some((false, 1)),
+        Schema::U64 => Some((false, 2)),
+        _ => None,
+    }
+}
+
+// Return `true` if the given name is that of one of the newtype structs
+// through which the wrappers of this crate pass, which give the values
+// they wrap layouts of their own.
+fn is_wrapper(name: &str) -> bool {
+    name == appendable::NAME
+        || name == shared::NAME
+        || borrowed_slice::alignment(name).is_some()
+}
+
+// Other newtype structs are encoded exactly as the values they wrap.
+fn is_plain_newtype(schema: &Schema) -> bool {
+    matches!(
+        schema,
+        Schema::NewtypeStruct {
+            name,
+            ..
+        } if !is_wrapper(name)
+    )
+}
+
+fn unwrap_newtype(schema: &Schema) -> &Schema {
+    match schema {
+        Schema::NewtypeStruct {
+            value,
+            ..
+        } if is_plain_newtype(schema) => value,
+        _ => schema,
+    }
+}
+
+fn join(
+    path: &str,
+    segment: &str,
+) -> String {
+    if path.is_empty() {
+        segment.to_string()
+    } else {
+        format!("{path}.{segment}")
+    }
+}
+
+fn push(
+    changes: &mut Vec<Change>,
+    path: &str,
+    kind: ChangeKind,
+) {
+    changes.push(Change {
+        path: path.to_string(),
+        kind,
+    });
+}
+
+fn compare_names(
+    changes: &mut Vec<Change>,
+    path: &str,
+    stored: &Schema,
+    current: &Schema,
+) {
+    if std::mem::discriminant(stored) != std::mem::discriminant(current) {
+        return;
+    }
+    if let (Some(name), Some(current_name)) =
+        (type_name(stored), type_name(current))
+    {
+        if name != current_name
+            && !is_wrapper(name)
+            && !is_wrapper(current_name)
+        {
+            push(changes, path, ChangeKind::TypeRenamed {
+                from: name.to_string(),
+                to: current_name.to_string(),
+            });
+        }
+    }
+}
+
+fn compare(
+    changes: &mut Vec<Change>,
+    path: &str,
+    stored: &Schema,
+    current: &Schema,
+) {
+    compare_names(changes, path, stored, current);
+    match (stored, current) {
+        _ if is_plain_newtype(stored) || is_plain_newtype(current) => compare(
+            changes,
+            path,
+            unwrap_newtype(stored),
+            unwrap_newtype(current),
+        ),
+        (
+            Schema::NewtypeStruct {
+                name,
+                value,
+            },
+            Schema::NewtypeStruct {
+                name: current_name,
+                value: current_value,
+            },
+        ) if name == current_name => {
+            compare_wrapped(changes, path, name, value, current_value);
+        },
+        (Schema::Option(schema), Schema::Option(current))
+        | (Schema::Seq(schema), Schema::Seq(current)) => {
+            compare(changes, path, schema, current);
+        },
+        (
+            Schema::Map {
+                key,
+                value,
+            },
+            Schema::Map {
+                key: current_key,
+                value: current_value,
+            },
+        ) => {
+            compare(changes, &join(path, "key"), key, current_key);
+            compare(changes, &join(path, "value"), value, current_value);
+        },
+        (
+            Schema::Tuple(schemas)
+            | Schema::TupleStruct {
+                fields: schemas,
+                ..
+            },
+            Schema::Tuple(current_schemas)
+            | Schema::TupleStruct {
+                fields: current_schemas,
+                ..
+            },
+        ) => compare_positional(
+            changes,
+            path,
+            stored,
+            current,
+            schemas,
+            current_schemas,
+        ),
+        (
+            Schema::Unit
+            | Schema::UnitStruct {
+                ..
+            },
+            Schema::Unit
+            | Schema::UnitStruct {
+                ..
+            },
+        )
+        | (
+            Schema::Ref {
+                ..
+            },
+            Schema::Ref {
+                ..
+            },
+        ) => {},
+        (
+            Schema::Struct {
+                fields,
+                ..
+            },
+            Schema::Struct {
+                fields: current_fields,
+                ..
+            },
+        ) => compare_fields(changes, path, fields, current_fields, false),
+        (
+            Schema::Enum {
+                variants,
+                ..
+            },
+            Schema::Enum {
+                variants: current_variants,
+                ..
+            },
+        ) => compare_variants(changes, path, variants, current_variants),
+        _ => compare_values(changes, path, stored, current),
+    }
+}
+
+// Compare the values wrapped by two wrappers of the same kind.
+fn compare_wrapped(
+    changes: &mut Vec<Change>,
+    path: &str,
+    name: &str,
+    stored: &Schema,
+    current: &Schema,
+) {
+    match (stored, current) {
+        (
+            Schema::Struct {
+                fields,
+                ..
+            },
+            Schema::Struct {
+                fields: current_fields,
+                ..
+            },
+        ) if name == appendable::NAME => {
+            compare_names(changes, path, stored, current);
+            compare_fields(changes, path, fields, current_fields, true);
+        },
+        _ => compare(changes, path, stored, current),
+    }
+}
+
+fn compare_values(
+    changes: &mut Vec<Change>,
+    path: &str,
+    stored: &Schema,
+    current: &Schema,
+) {
+    if stored == current {
+        return;
+    }
+    let kind = match (integer_rank(stored), integer_rank(current)) {
+        (Some((signed, rank)), Some((current_signed, current_rank)))
+            if signed == current_signed =>
+        {
+            if rank < current_rank {
+                ChangeKind::IntegerWidened {
+                    from: stored.clone(),
+                    to: current.clone(),
+                }
+            } else {
+                ChangeKind::IntegerNarrowed {
+                    from: stored.clone(),
+                    to: current.clone(),
+                }
+            }
+        },
+        _ => ChangeKind::TypeChanged {
+            from: describe(stored),
+            to: describe(current),
+        },
+    };
+    push(changes, path, kind);
+}
+
+fn compare_positional(
+    changes: &mut Vec<Change>,
+    path: &str,
+    stored: &Schema,
+    current: &Schema,
+    schemas: &[Schema],
+    current_schemas: &[Schema],
+) {
+    if schemas.len() == current_schemas.len() {
+        for (index, (schema, current)) in
+            schemas.iter().zip(current_schemas).enumerate()
+        {
+            compare(changes, &join(path, &index.to_string()), schema, current);
+        }
+    } else {
+        push(changes, path, ChangeKind::TypeChanged {
+            from: describe(stored),
+            to: describe(current),
+        });
+    }
+}
+
+// Compare the fields of two versions of a struct.  If the struct is
+// appendable, fields past the end of the shorter version may be added or
+// removed.
+fn compare_fields(
+    changes: &mut Vec<Change>,
+    path: &str,
+    fields: &[Field],
+    current_fields: &[Field],
+    appendable: bool,
+) {
+    let stored_has = |name: &str| fields.iter().any(|field| field.name == name);
+    let current_has =
+        |name: &str| current_fields.iter().any(|field| field.name == name);
+    for (position, field) in fields.iter().enumerate() {
+        let field_path = join(path, &field.name);
+        if let Some(current_position) =
+            current_fields.iter().position(|current| current.name == field.name)
+        {
+            if position != current_position {
+                push(changes, &field_path, ChangeKind::FieldReordered {
+                    name: field.name.clone(),
+                    from: position,
+                    to: current_position,
+                });
+            }
+            compare(
+                changes,
+                &field_path,
+                &field.schema,
+                &current_fields[current_position].schema,
+            );
+            continue;
+        }
+        match current_fields.get(position) {
+            Some(current) if !stored_has(&current.name) => {
+                push(changes, &field_path, ChangeKind::FieldRenamed {
+                    from: field.name.clone(),
+                    to: current.name.clone(),
+                });
+                compare(
+                    changes,
+                    &join(path, &current.name),
+                    &field.schema,
+                    &current.schema,
+                );
+            },
+            _ if appendable && position >= current_fields.len() => {
+                push(changes, &field_path, ChangeKind::TrailingFieldRemoved {
+                    name: field.name.clone(),
+                });
+            },
+            _ => push(changes, &field_path, ChangeKind::FieldRemoved {
+                name: field.name.clone(),
+            }),
+        }
+    }
+    for (position, current) in current_fields.iter().enumerate() {
+        let renamed =
+            fields.get(position).is_some_and(|field| !current_has(&field.name));
+        if !stored_has(&current.name) && !renamed {
+            let name = current.name.clone();
+            let kind = if appendable && position >= fields.len() {
+                ChangeKind::TrailingFieldAdded {
+                    name,
+                }
+            } else {
+                ChangeKind::FieldAdded {
+                    name,
+                }
+            };
+            push(changes, &join(path, &current.name), kind);
+        }
+    }
+}
+
+fn compare_variants(
+    changes: &mut Vec<Change>,
+    path: &str,
+    variants: &[Variant],
+    current_variants: &[Variant],
+) {
+    let stored_has =
+        |name: &str| variants.iter().any(|variant| variant.name == name);
+    let current_has = |name: &str| {
+        current_variants.iter().any(|variant| variant.name == name)
+    };
+    for variant in variants {
+        let variant_path = join(path, &variant.name);
+        if let Some(current) =
+            current_variants.iter().find(|current| current.name == variant.name)
+        {
+            if variant.index != current.index {
+                push(changes, &variant_path, ChangeKind::VariantReindexed {
+                    name: variant.name.clone(),
+                    from: variant.index,
+                    to: current.index,
+                });
+            }
+            compare_shapes(
+                changes,
+                &variant_path,
+                &variant.shape,
+                &current.shape,
+            );
+            continue;
+        }
+        match current_variants
+            .iter()
+            .find(|current| current.index == variant.index)
+        {
+            Some(current) if !stored_has(&current.name) => {
+                push(changes, &variant_path, ChangeKind::VariantRenamed {
+                    from: variant.name.clone(),
+                    to: current.name.clone(),
+                });
+                compare_shapes(
+                    changes,
+                    &join(path, &current.name),
+                    &variant.shape,
+                    &current.shape,
+                );
+            },
+            _ => push(changes, &variant_path, ChangeKind::VariantRemoved {
+                name: variant.name.clone(),
+                index: variant.index,
+            }),
+        }
+    }
+    for current in current_variants {
+        let renamed = variants
+            .iter()
+            .find(|variant| variant.index == current.index)
+            .is_some_and(|variant| !current_has(&variant.name));
+        if !stored_has(&current.name) && !renamed {
+            push(
+                changes,
+                &join(path, &current.name),
+                ChangeKind::VariantAdded {
+                    name: current.name.clone(),
+                    index: current.index,
+                },
+            );
+        }
+    }
+}
+
+fn compare_shapes(
+    changes: &mut Vec<Change>,
+    path: &str,
+    stored: &VariantShape,
+    current: &VariantShape,
+) {
+    match (stored, current) {
+        (VariantShape::Unit, VariantShape::Unit) => {},
+        (VariantShape::Newtype(schema), VariantShape::Newtype(current)) => {
+            compare(changes, path, schema, current);
+        },
+        (
+            VariantShape::Tuple(schemas),
+            VariantShape::Tuple(current_schemas),
+        ) if schemas.len() == current_schemas.len() => {
+            for (index, (schema, current)) in
+                schemas.iter().zip(current_schemas).enumerate()
+            {
+                compare(
+                    changes,
+                    &join(path, &index.to_string()),
+                    schema,
+                    current,
+                );
+            }
+        },
+        (
+            VariantShape::Struct(fields),
+            VariantShape::Struct(current_fields),
+        ) => {
+            compare_fields(changes, path, fields, current_fields, false);
+        },
+        _ => push(changes, path, ChangeKind::TypeChanged {
+            from: describe_shape(stored),
+            to: describe_shape(current),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        schema_of,
+        Appendable,
+        BorrowedSlice,
+        Shared,
+    };
+    use std::rc::Rc;
+
+    mod v1 {
+        #[derive(serde::Deserialize)]
+        #[allow(dead_code)]
+        pub struct Account {
+            pub id: u32,
+            pub name: String,
+            pub balance: i64,
+            pub flags: u8,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[allow(dead_code)]
+        pub enum Event {
+            Opened(Account),
+            Closed {
+                id: u32,
+            },
+            Frozen,
+        }
+    }
+
+    mod v2 {
+        #[derive(serde::Deserialize)]
+        #[allow(dead_code)]
+        pub struct UserId(pub u64);
+
+        #[derive(serde::Deserialize)]
+        #[allow(dead_code)]
+        pub struct Customer {
+            pub id: UserId,
+            pub balance: i32,
+            pub display_name: String,
+            pub flags: u16,
+            pub email: Option<String>,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[allow(dead_code)]
+        pub enum Event {
+            Opened(Customer),
+            Closed {
+                id: u64,
+            },
+            Locked,
+            Deleted,
+        }
+    }
+
+    fn summary(changes: &[Change]) -> Vec<(String, bool)> {
+        changes
+            .iter()
+            .map(|change| (change.to_string(), change.is_breaking()))
+            .collect()
+    }
+
+    #[test]
+    fn identical() {
+        let schema = schema_of::<v1::Event>().unwrap();
+        assert!(check_compatibility(&schema, &schema).is_empty());
+    }
+
+    #[test]
+    fn struct_changes() {
+        let stored = schema_of::<v1::Account>().unwrap();
+        let current = schema_of::<v2::Customer>().unwrap();
+        assert_eq!(
+            vec![
+                (String::from("type `Account` renamed to `Customer`"), false),
+                (String::from("id: integer widened from u32 to u64"), false),
+                (String::from("name: field `name` removed"), true),
+                (
+                    String::from(
+                        "balance: field `balance` moved from position 2 to 1"
+                    ),
+                    true
+                ),
+                (
+                    String::from("balance: integer narrowed from i64 to i32"),
+                    true
+                ),
+                (String::from("flags: type changed from u8 to u16"), true),
+                (
+                    String::from("display_name: field `display_name` added"),
+                    true
+                ),
+                (String::from("email: field `email` added"), true),
+            ],
+            summary(&check_compatibility(&stored, &current))
+        );
+    }
+
+    #[test]
+    fn enum_changes() {
+        let stored = schema_of::<v1::Event>().unwrap();
+        let current = schema_of::<v2::Event>().unwrap();
+        let changes = check_compatibility(&stored, &current);
+        assert!(changes.contains(&Change {
+            path: String::from("Closed.id"),
+            kind: ChangeKind::IntegerWidened {
+                from: Schema::U32,
+                to: Schema::U64,
+            },
+        }));
+        assert!(changes.contains(&Change {
+            path: String::from("Frozen"),
+            kind: ChangeKind::VariantRenamed {
+                from: String::from("Frozen"),
+                to: String::from("Locked"),
+            },
+        }));
+        assert!(changes.contains(&Change {
+            path: String::from("Deleted"),
+            kind: ChangeKind::VariantAdded {
+                name: String::from("Deleted"),
+                index: 3,
+            },
+        }));
+        assert!(changes
+            .iter()
+            .all(|change| !change.path.starts_with("Frozen")
+                || !change.is_breaking()));
+    }
+
+    #[test]
+    fn variant_removed_and_reindexed() {
+        #[derive(serde::Deserialize)]
+        #[allow(dead_code)]
+        enum Before {
+            A,
+            B(u8),
+            C,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[allow(dead_code)]
+        enum After {
+            A,
+            C,
+        }
+
+        let stored = schema_of::<Before>().unwrap();
+        let current = schema_of::<After>().unwrap();
+        assert_eq!(
+            vec![
+                (String::from("type `Before` renamed to `After`"), false),
+                (String::from("B: variant `B` removed from index 1"), true),
+                (String::from("C: variant `C` moved from index 2 to 1"), true),
+            ],
+            summary(&check_compatibility(&stored, &current))
+        );
+    }
+
+    #[test]
+    fn layout_changes() {
+        assert_eq!(
+            vec![(String::from("type changed from option to u32"), true)],
+            summary(&check_compatibility(
+                &schema_of::<Option<u32>>().unwrap(),
+                &schema_of::<u32>().unwrap()
+            ))
+        );
+        assert_eq!(
+            vec![(
+                String::from("type changed from tuple of 2 to tuple of 3"),
+                true
+            )],
+            summary(&check_compatibility(
+                &schema_of::<(u8, u8)>().unwrap(),
+                &schema_of::<(u8, u8, u8)>().unwrap()
+            ))
+        );
+        assert_eq!(
+            vec![(
+                String::from("value: integer widened from i16 to i64"),
+                false
+            )],
+            summary(&check_compatibility(
+                &schema_of::<std::collections::BTreeMap<String, i16>>()
+                    .unwrap(),
+                &schema_of::<std::collections::BTreeMap<String, i64>>()
+                    .unwrap()
+            ))
+        );
+    }
+
+    #[test]
+    fn wrappers_change_layout() {
+        #[derive(serde::Deserialize)]
+        #[allow(dead_code)]
+        struct Inner {
+            id: u32,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[allow(dead_code)]
+        struct Plain {
+            inner: Inner,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[allow(dead_code)]
+        struct Wrapped {
+            inner: Appendable<Inner>,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[allow(dead_code)]
+        struct SharedInner {
+            inner: Shared<Rc<Inner>>,
+        }
+
+        let plain = schema_of::<Plain>().unwrap();
+        let wrapped = schema_of::<Wrapped>().unwrap();
+        assert_eq!(
+            vec![
+                (String::from("type `Plain` renamed to `Wrapped`"), false),
+                (
+                    String::from(
+                        "inner: type changed from struct `Inner` to \
+                         appendable struct `Inner`"
+                    ),
+                    true
+                ),
+            ],
+            summary(&check_compatibility(&plain, &wrapped))
+        );
+        assert_eq!(
+            vec![
+                (String::from("type `Wrapped` renamed to `Plain`"), false),
+                (
+                    String::from(
+                        "inner: type changed from appendable struct `Inner` \
+                         to struct `Inner`"
+                    ),
+                    true
+                ),
+            ],
+            summary(&check_compatibility(&wrapped, &plain))
+        );
+        assert_eq!(
+            vec![
+                (String::from("type `Plain` renamed to `SharedInner`"), false),
+                (
+                    String::from(
+                        "inner: type changed from struct `Inner` to shared \
+                         struct `Inner`"
+                    ),
+                    true
+                ),
+            ],
+            summary(&check_compatibility(
+                &plain,
+                &schema_of::<SharedInner>().unwrap()
+            ))
+        );
+        assert_eq!(
+            vec![(
+                String::from(
+                    "type changed from sequence to slice aligned to 4 bytes"
+                ),
+                true
+            )],
+            summary(&check_compatibility(
+                &schema_of::<Vec<u32>>().unwrap(),
+                &schema_of::<BorrowedSlice<'static, u32>>().unwrap()
+            ))
+        );
+        assert_eq!(
+            vec![(
+                String::from(
+                    "type changed from slice aligned to 2 bytes to slice \
+                     aligned to 8 bytes"
+                ),
+                true
+            )],
+            summary(&check_compatibility(
+                &schema_of::<BorrowedSlice<'static, u16>>().unwrap(),
+                &schema_of::<BorrowedSlice<'static, u64>>().unwrap()
+            ))
+        );
+    }
+
+    #[test]
+    fn appendable_trailing_fields() {
+        #[derive(serde::Deserialize)]
+        #[allow(dead_code)]
+        struct Version1 {
+            id: u32,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[allow(dead_code)]
+        struct Version2 {
+            id: u32,
+            #[serde(default)]
+            name: String,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[allow(dead_code)]
+        struct Inserted {
+            #[serde(default)]
+            name: String,
+            id: u32,
+        }
+
+        let version1 = schema_of::<Appendable<Version1>>().unwrap();
+        let version2 = schema_of::<Appendable<Version2>>().unwrap();
+        assert_eq!(
+            vec![
+                (String::from("type `Version1` renamed to `Version2`"), false),
+                (String::from("name: field `name` added at the end"), false),
+            ],
+            summary(&check_compatibility(&version1, &version2))
+        );
+        assert_eq!(
+            vec![
+                (String::from("type `Version2` renamed to `Version1`"), false),
+                (
+                    String::from("name: field `name` removed from the end"),
+                    false
+                ),
+            ],
+            summary(&check_compatibility(&version2, &version1))
+        );
+        assert!(check_compatibility(
+            &version1,
+            &schema_of::<Appendable<Inserted>>().unwrap()
+        )
+        .iter()
+        .any(Change::is_breaking));
+    }
+}
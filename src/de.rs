@@ -1,4 +1,10 @@
 use super::{
+    appendable,
+    borrowed_slice,
+    shared::{
+        self,
+        Table,
+    },
     Error,
     Result,
 };
@@ -12,6 +18,10 @@ use std::marker::PhantomData;
 pub struct Deserializer<'de> {
     buffer: &'de [u8],
     offset: usize,
+    appendable: Option<usize>,
+    strings: Option<Vec<&'de str>>,
+    shared: Option<Table>,
+    indexed: bool,
 }
 
 impl<'de> Deserializer<'de> {
@@ -33,6 +43,41 @@ impl<'de> Deserializer<'de> {
         Self {
             buffer,
             offset: 0,
+            appendable: None,
+            strings: None,
+            shared: None,
+            indexed: false,
+        }
+    }
+
+    /// Return a new deserializer that deserializes from the given buffer,
+    /// as [`new`] does, but decodes structs and sequences in the indexed
+    /// layout made by [`Serializer::new_indexed`].
+    ///
+    /// [`new`]: #method.new
+    /// [`Serializer::new_indexed`]:
+    /// struct.Serializer.html#method.new_indexed
+    #[must_use]
+    pub fn new_indexed(buffer: &'de [u8]) -> Self {
+        Self {
+            indexed: true,
+            ..Self::new(buffer)
+        }
+    }
+
+    /// Return a new deserializer that deserializes from the given buffer,
+    /// as [`new`] does, but decodes strings in the interned layout made by
+    /// [`Serializer::new_interned`].  Strings are shared between all the
+    /// values decoded by the deserializer.
+    ///
+    /// [`new`]: #method.new
+    /// [`Serializer::new_interned`]:
+    /// struct.Serializer.html#method.new_interned
+    #[must_use]
+    pub fn new_interned(buffer: &'de [u8]) -> Self {
+        Self {
+            strings: Some(Vec::new()),
+            ..Self::new(buffer)
         }
     }
 
@@ -42,51 +87,180 @@ impl<'de> Deserializer<'de> {
         self.offset
     }
 
+    // Limit a number of elements read from the input to the number of bytes
+    // left, so that a corrupt or hostile length cannot cause a collection
+    // to allocate far more memory than the input could ever fill.  Most
+    // elements take at least one byte, so this is rarely an underestimate.
+    fn bounded_len(
+        &self,
+        len: usize,
+    ) -> usize {
+        len.min(self.remaining().len())
+    }
+
+    // Decode a struct with the given number of fields in the appendable
+    // layout, leaving any fields it lacks to the visitor and skipping any
+    // extra fields it has.
+    fn deserialize_appendable<V>(
+        &mut self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        // Strings and shared values first found in the struct are dropped
+        // from their tables at its end, as the encoder does, since they may
+        // be in skipped fields.
+        let mut body = Deserializer::new(self.parse_bytes()?);
+        body.strings = self.strings.take();
+        body.shared = self.shared.take();
+        body.indexed = self.indexed;
+        let strings = body.strings.as_ref().map_or(0, Vec::len);
+        let shared = body.shared.as_ref().map_or(0, Table::len);
+        let result = body.parse_usize().and_then(|count| {
+            if body.indexed {
+                body.take(count.checked_mul(4).ok_or(Error::ValueTruncated)?)?;
+            }
+            serde::Deserializer::deserialize_tuple(
+                &mut body,
+                count.min(len),
+                visitor,
+            )
+        });
+        self.strings = body.strings.take().map(|mut table| {
+            table.truncate(strings);
+            table
+        });
+        self.shared = body.shared.take();
+        if let Some(table) = &self.shared {
+            table.truncate(shared);
+        }
+        result
+    }
+
+    // Decode a struct or sequence in the indexed layout, with the given
+    // number of elements, of which the visitor reads at most `len`.  The
+    // table of offsets is only used to find the end of the elements, so
+    // that any the visitor does not read are skipped.
+    fn deserialize_indexed<V>(
+        &mut self,
+        count: usize,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        let table =
+            self.take(count.checked_mul(4).ok_or(Error::ValueTruncated)?)?;
+        let mut end = [0; 4];
+        if let Some(last) = table.len().checked_sub(4) {
+            end.copy_from_slice(&table[last..]);
+        }
+        let end = self.offset + u32::from_be_bytes(end) as usize;
+        if end > self.buffer.len() {
+            return Err(Error::ValueTruncated);
+        }
+        let value = serde::Deserializer::deserialize_tuple(
+            &mut *self,
+            count.min(len),
+            visitor,
+        )?;
+        self.offset = self.offset.max(end);
+        Ok(value)
+    }
+
+    // Decode a value in the shared layout, handing the visitor the pointer
+    // to an earlier value if it is a reference to one, or taking a pointer
+    // to the value from the visitor otherwise.
+    fn deserialize_shared<V>(
+        &mut self,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        let prefix = self.parse_usize()?;
+        let table = self.shared.get_or_insert_with(Table::new);
+        if let Some(index) = prefix.checked_sub(1) {
+            let pointer =
+                table.get(index).ok_or(Error::SharedUnknown(index))?;
+            shared::offer(pointer);
+            let result = visitor.visit_unit();
+            shared::take();
+            return result;
+        }
+        let index = table.push(0, None);
+        shared::request();
+        let result = visitor.visit_newtype_struct(&mut *self);
+        if let (Some(pointer), Some(table)) = (shared::take(), &self.shared) {
+            table.set(index, pointer);
+        }
+        result
+    }
+
+    // Decode bytes in the aligned-array layout of `BorrowedSlice`, checking
+    // that they have the byte order of this machine and are aligned in
+    // memory to the given number of bytes.
+    fn parse_aligned(
+        &mut self,
+        alignment: usize,
+    ) -> Result<&'de [u8]> {
+        let bytes = self.parse_bytes()?;
+        let (&byte_order, rest) =
+            bytes.split_first().ok_or(Error::ValueTruncated)?;
+        let (&padding, rest) =
+            rest.split_first().ok_or(Error::ValueTruncated)?;
+        if byte_order != borrowed_slice::NATIVE_BYTE_ORDER {
+            return Err(Error::ByteOrderMismatch);
+        }
+        let data =
+            rest.get(usize::from(padding)..).ok_or(Error::ValueTruncated)?;
+        if data.as_ptr() as usize % alignment != 0 {
+            return Err(Error::DataMisaligned);
+        }
+        Ok(data)
+    }
+
     fn parse_bool(&mut self) -> Result<bool> {
-        self.buffer.iter().next().map_or(Err(Error::ValueTruncated), |byte| {
-            self.buffer = &self.buffer[1..];
-            self.offset += 1;
-            Ok(*byte != 0)
-        })
+        Ok(self.take_byte()? != 0)
     }
 
     #[allow(clippy::cast_possible_wrap)]
     fn parse_i8(&mut self) -> Result<i8> {
-        self.buffer.iter().next().map_or(Err(Error::ValueTruncated), |byte| {
-            self.buffer = &self.buffer[1..];
-            self.offset += 1;
-            Ok(*byte as i8)
-        })
+        Ok(self.take_byte()? as i8)
     }
 
     #[allow(clippy::cast_possible_truncation)]
     fn parse_i16(&mut self) -> Result<i16> {
-        Ok(self.parse_i64(Some(i64::from(std::i16::MAX)))? as i16)
+        Ok(self.parse_i64(Some(i64::from(i16::MAX)))? as i16)
     }
 
     #[allow(clippy::cast_possible_truncation)]
     fn parse_i32(&mut self) -> Result<i32> {
-        Ok(self.parse_i64(Some(i64::from(std::i32::MAX)))? as i32)
+        Ok(self.parse_i64(Some(i64::from(i32::MAX)))? as i32)
     }
 
-    #[allow(clippy::cast_lossless)]
     fn parse_i64(
         &mut self,
         max: Option<i64>,
     ) -> Result<i64> {
-        let mut it = self.buffer.iter();
-        let first = it.next().ok_or(Error::ValueTruncated)?;
-        self.buffer = &self.buffer[1..];
-        self.offset += 1;
-        let mut more = (first & 0x80) != 0;
+        let rest = self.remaining();
+        let first = *rest.first().ok_or(Error::ValueTruncated)?;
         let negative = (first & 0x40) != 0;
-        let mut value = (first & 0x3F) as i64;
-        while more {
-            let next = it.next().ok_or(Error::ValueTruncated)?;
-            self.buffer = &self.buffer[1..];
+        let mut value = i64::from(first & 0x3F);
+        if (first & 0x80) == 0 {
             self.offset += 1;
+            return Ok(if negative {
+                -value
+            } else {
+                value
+            });
+        }
+        for (index, next) in rest.iter().enumerate().skip(1) {
             let lsb = next & 0x7F;
-            more = (next & 0x80) != 0;
+            let more = (next & 0x80) != 0;
             // Special case: the negative of exactly one value, `i64::MIN`,
             // won't fit in 64 bits, so we detect it here and return it
             // early before we would overflow.
@@ -95,68 +269,68 @@ impl<'de> Deserializer<'de> {
                 && lsb == 0
                 && value == 0x0100_0000_0000_0000_i64
             {
+                self.offset += index + 1;
                 return Ok(i64::MIN);
             }
-            value = value.checked_mul(128).ok_or(Error::IntegerOverflow)?;
-            value += lsb as i64;
+            value = value.checked_mul(128).ok_or(Error::IntegerOverflow)?
+                + i64::from(lsb);
             if let Some(max) = max {
                 if (negative && value > (max + 1)) || (!negative && value > max)
                 {
                     return Err(Error::IntegerOverflow);
                 }
             }
+            if !more {
+                self.offset += index + 1;
+                return Ok(if negative {
+                    -value
+                } else {
+                    value
+                });
+            }
         }
-        Ok(if negative {
-            -value
-        } else {
-            value
-        })
+        Err(Error::ValueTruncated)
     }
 
     fn parse_u8(&mut self) -> Result<u8> {
-        self.buffer.iter().next().map_or(Err(Error::ValueTruncated), |byte| {
-            self.buffer = &self.buffer[1..];
-            self.offset += 1;
-            Ok(*byte)
-        })
+        self.take_byte()
     }
 
     #[allow(clippy::cast_possible_truncation)]
     fn parse_u16(&mut self) -> Result<u16> {
-        Ok(self.parse_u64(Some(u64::from(std::u16::MAX)))? as u16)
+        Ok(self.parse_u64(Some(u64::from(u16::MAX)))? as u16)
     }
 
     #[allow(clippy::cast_possible_truncation)]
     fn parse_u32(&mut self) -> Result<u32> {
-        Ok(self.parse_u64(Some(u64::from(std::u32::MAX)))? as u32)
+        Ok(self.parse_u64(Some(u64::from(u32::MAX)))? as u32)
     }
 
-    #[allow(clippy::cast_lossless)]
     fn parse_u64(
         &mut self,
         max: Option<u64>,
     ) -> Result<u64> {
-        let mut it = self.buffer.iter();
-        let first = it.next().ok_or(Error::ValueTruncated)?;
-        self.buffer = &self.buffer[1..];
-        self.offset += 1;
-        let mut more = (first & 0x80) != 0;
-        let mut value = (first & 0x7F) as u64;
-        while more {
-            let next = it.next().ok_or(Error::ValueTruncated)?;
-            self.buffer = &self.buffer[1..];
+        let rest = self.remaining();
+        let first = *rest.first().ok_or(Error::ValueTruncated)?;
+        let mut value = u64::from(first & 0x7F);
+        if (first & 0x80) == 0 {
             self.offset += 1;
-            let lsb = next & 0x7F;
-            more = (next & 0x80) != 0;
-            value = value.checked_mul(128).ok_or(Error::IntegerOverflow)?;
-            value += lsb as u64;
+            return Ok(value);
+        }
+        for (index, next) in rest.iter().enumerate().skip(1) {
+            value = value.checked_mul(128).ok_or(Error::IntegerOverflow)?
+                + u64::from(next & 0x7F);
             if let Some(max) = max {
                 if value > max {
                     return Err(Error::IntegerOverflow);
                 }
             }
+            if (next & 0x80) == 0 {
+                self.offset += index + 1;
+                return Ok(value);
+            }
         }
-        Ok(value)
+        Err(Error::ValueTruncated)
     }
 
     #[allow(clippy::cast_possible_truncation)]
@@ -164,45 +338,16 @@ impl<'de> Deserializer<'de> {
         Ok(self.parse_u64(None)? as usize)
     }
 
-    #[allow(clippy::cast_lossless)]
     fn parse_f32(&mut self) -> Result<f32> {
-        if self.buffer.len() < 4 {
-            return Err(Error::ValueTruncated);
-        }
-        let mut value = 0_u32;
-        for _ in 0..4 {
-            value <<= 8;
-            value += self.buffer[0] as u32;
-            self.buffer = &self.buffer[1..];
-            self.offset += 1;
-        }
-        unsafe {
-            let value = *(&value as *const u32).cast::<f32>();
-            Ok(value)
-        }
+        Ok(f32::from_be_bytes(self.take_array()?))
     }
 
-    #[allow(clippy::cast_lossless)]
     fn parse_f64(&mut self) -> Result<f64> {
-        if self.buffer.len() < 8 {
-            return Err(Error::ValueTruncated);
-        }
-        let mut value = 0_u64;
-        for _ in 0..8 {
-            value <<= 8;
-            value += self.buffer[0] as u64;
-            self.buffer = &self.buffer[1..];
-            self.offset += 1;
-        }
-        unsafe {
-            let value = *(&value as *const u64).cast::<f64>();
-            Ok(value)
-        }
+        Ok(f64::from_be_bytes(self.take_array()?))
     }
 
     fn parse_char(&mut self) -> Result<char> {
-        let mut it = self.buffer.iter();
-        let byte1 = it.next().ok_or(Error::ValueTruncated)?;
+        let byte1 = self.remaining().first().ok_or(Error::ValueTruncated)?;
         let n = match byte1 {
             0x00..=0x7F => 1,
             0xC0..=0xDF => 2,
@@ -210,60 +355,95 @@ impl<'de> Deserializer<'de> {
             0xF0..=0xF7 => 4,
             _ => return Err(Error::InvalidUtf8(None)),
         };
-        if self.buffer.len() < n {
-            return Err(Error::ValueTruncated);
-        }
-        let ch = std::str::from_utf8(&self.buffer[0..n])
+        let encoding =
+            self.remaining().get(..n).ok_or(Error::ValueTruncated)?;
+        let ch = std::str::from_utf8(encoding)
             .map_err(|source| Error::InvalidUtf8(Some(source)))?
             .chars()
             .next()
             .ok_or(Error::InvalidUtf8(None))?;
-        self.buffer = &self.buffer[n..];
         self.offset += n;
         Ok(ch)
     }
 
-    #[allow(clippy::cast_possible_truncation)]
+    // Decode a string in the interned layout, which is either a new entry
+    // for the table of strings or a reference to an earlier one.
+    fn parse_interned_str(&mut self) -> Result<&'de str> {
+        let prefix = self.parse_usize()?;
+        let index = prefix >> 1;
+        if (prefix & 1) != 0 {
+            return self
+                .strings
+                .as_ref()
+                .and_then(|strings| strings.get(index).copied())
+                .ok_or(Error::StringUnknown(index));
+        }
+        let value = self.parse_text(index)?;
+        if let Some(strings) = &mut self.strings {
+            strings.push(value);
+        }
+        Ok(value)
+    }
+
     fn parse_str(&mut self) -> Result<&'de str> {
-        let len = self.parse_u64(None)? as usize;
-        if self.buffer.len() < len {
-            Err(Error::ValueTruncated)
-        } else {
-            let value = std::str::from_utf8(&self.buffer[0..len])
-                .map_err(|source| Error::InvalidUtf8(Some(source)))?;
-            self.buffer = &self.buffer[len..];
-            self.offset += len;
-            Ok(value)
+        if self.strings.is_some() {
+            return self.parse_interned_str();
         }
+        let len = self.parse_usize()?;
+        self.parse_text(len)
+    }
+
+    fn parse_text(
+        &mut self,
+        len: usize,
+    ) -> Result<&'de str> {
+        let bytes = self.remaining().get(..len).ok_or(Error::ValueTruncated)?;
+        let value = std::str::from_utf8(bytes)
+            .map_err(|source| Error::InvalidUtf8(Some(source)))?;
+        self.offset += len;
+        Ok(value)
     }
 
-    #[allow(clippy::cast_possible_truncation)]
     fn parse_bytes(&mut self) -> Result<&'de [u8]> {
-        let len = self.parse_u64(None)? as usize;
-        if self.buffer.len() < len {
-            Err(Error::ValueTruncated)
-        } else {
-            let value = &self.buffer[0..len];
-            self.buffer = &self.buffer[len..];
-            self.offset += len;
-            Ok(value)
-        }
+        let len = self.parse_usize()?;
+        self.take(len)
     }
 
     fn parse_option(&mut self) -> Result<Option<&mut Self>> {
-        let mut it = self.buffer.iter();
-        let next = it.next().ok_or(Error::ValueTruncated)?;
-        self.buffer = &self.buffer[1..];
-        self.offset += 1;
-        Ok(match next {
+        Ok(match self.take_byte()? {
             0 => None,
             _ => Some(self),
         })
     }
+
+    fn remaining(&self) -> &'de [u8] {
+        &self.buffer[self.offset..]
+    }
+
+    fn take(
+        &mut self,
+        len: usize,
+    ) -> Result<&'de [u8]> {
+        let bytes = self.remaining().get(..len).ok_or(Error::ValueTruncated)?;
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn take_byte(&mut self) -> Result<u8> {
+        let byte = *self.remaining().first().ok_or(Error::ValueTruncated)?;
+        self.offset += 1;
+        Ok(byte)
+    }
 }
 
 #[allow(clippy::missing_errors_doc)]
-impl<'a, 'de> serde::Deserializer<'de> for &'a mut Deserializer<'de> {
+impl<'de> serde::Deserializer<'de> for &mut Deserializer<'de> {
     type Error = Error;
 
     fn deserialize_any<V>(
@@ -472,12 +652,27 @@ impl<'a, 'de> serde::Deserializer<'de> for &'a mut Deserializer<'de> {
 
     fn deserialize_newtype_struct<V>(
         self,
-        _name: &'static str,
+        name: &'static str,
         visitor: V,
     ) -> Result<V::Value>
     where
         V: serde::de::Visitor<'de>,
     {
+        if let Some(alignment) = borrowed_slice::alignment(name) {
+            let bytes = self.parse_aligned(alignment)?;
+            return visitor.visit_newtype_struct(
+                serde::de::value::BorrowedBytesDeserializer::new(bytes),
+            );
+        }
+        if name == shared::NAME {
+            return self.deserialize_shared(visitor);
+        }
+        if name == appendable::NAME {
+            self.appendable = Some(self.offset);
+            let result = visitor.visit_newtype_struct(&mut *self);
+            self.appendable = None;
+            return result;
+        }
         visitor.visit_newtype_struct(self)
     }
 
@@ -489,6 +684,9 @@ impl<'a, 'de> serde::Deserializer<'de> for &'a mut Deserializer<'de> {
         V: serde::de::Visitor<'de>,
     {
         let len = self.parse_usize()?;
+        if self.indexed {
+            return self.deserialize_indexed(len, len, visitor);
+        }
         self.deserialize_tuple(len, visitor)
     }
 
@@ -505,7 +703,7 @@ impl<'a, 'de> serde::Deserializer<'de> for &'a mut Deserializer<'de> {
             len: usize,
         }
 
-        impl<'a, 'de> serde::de::SeqAccess<'de> for Seq<'a, 'de> {
+        impl<'de> serde::de::SeqAccess<'de> for Seq<'_, 'de> {
             type Error = Error;
 
             fn next_element_seed<T>(
@@ -523,8 +721,13 @@ impl<'a, 'de> serde::Deserializer<'de> for &'a mut Deserializer<'de> {
                     })
                     .transpose()
             }
+
+            fn size_hint(&self) -> Option<usize> {
+                Some(self.de.bounded_len(self.len))
+            }
         }
 
+        self.appendable = None;
         visitor.visit_seq(Seq {
             de: self,
             len,
@@ -555,7 +758,7 @@ impl<'a, 'de> serde::Deserializer<'de> for &'a mut Deserializer<'de> {
             len: usize,
         }
 
-        impl<'a, 'de> serde::de::MapAccess<'de> for Map<'a, 'de> {
+        impl<'de> serde::de::MapAccess<'de> for Map<'_, 'de> {
             type Error = Error;
 
             fn next_key_seed<K>(
@@ -581,6 +784,10 @@ impl<'a, 'de> serde::Deserializer<'de> for &'a mut Deserializer<'de> {
                 self.len -= 1;
                 seed.deserialize(&mut *self.de)
             }
+
+            fn size_hint(&self) -> Option<usize> {
+                Some(self.de.bounded_len(self.len))
+            }
         }
 
         let len = self.parse_usize()?;
@@ -599,6 +806,13 @@ impl<'a, 'de> serde::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: serde::de::Visitor<'de>,
     {
+        if self.appendable.take() == Some(self.offset) {
+            return self.deserialize_appendable(fields.len(), visitor);
+        }
+        if self.indexed {
+            let count = self.parse_usize()?;
+            return self.deserialize_indexed(count, fields.len(), visitor);
+        }
         self.deserialize_tuple(fields.len(), visitor)
     }
 
@@ -613,7 +827,7 @@ impl<'a, 'de> serde::Deserializer<'de> for &'a mut Deserializer<'de> {
     {
         struct Enum<'a, 'de> {
             de: &'a mut Deserializer<'de>,
-            variant: usize,
+            id: u64,
         }
 
         impl<'a, 'de> serde::de::EnumAccess<'de> for Enum<'a, 'de> {
@@ -627,21 +841,26 @@ impl<'a, 'de> serde::Deserializer<'de> for &'a mut Deserializer<'de> {
             where
                 V: serde::de::DeserializeSeed<'de>,
             {
-                Ok((
-                    seed.deserialize(
-                        serde::de::IntoDeserializer::into_deserializer(
-                            self.variant,
+                // The seed only rejects an id which names no variant, such
+                // as one from a newer version of the enum.
+                let id = self.id;
+                let variant = seed
+                    .deserialize(
+                        serde::de::IntoDeserializer::<Error>::into_deserializer(
+                            id,
                         ),
-                    )?,
-                    self.de,
-                ))
+                    )
+                    .map_err(|_| Error::UnknownVariant(id))?;
+                Ok((variant, self.de))
             }
         }
 
-        let variant = self.parse_usize()?;
+        // Every id is given to the visitor, so that enums with a
+        // `#[serde(other)]` variant can accept ids they do not know.
+        let id = self.parse_u64(None)?;
         visitor.visit_enum(Enum {
             de: self,
-            variant,
+            id,
         })
     }
 
@@ -664,9 +883,13 @@ impl<'a, 'de> serde::Deserializer<'de> for &'a mut Deserializer<'de> {
     {
         Err(Error::TypeUnknown)
     }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
 }
 
-impl<'a, 'de> serde::de::VariantAccess<'de> for &'a mut Deserializer<'de> {
+impl<'de> serde::de::VariantAccess<'de> for &mut Deserializer<'de> {
     type Error = Error;
 
     fn unit_variant(self) -> Result<()> {
@@ -716,7 +939,7 @@ pub struct StreamDeserializer<'de, T> {
     output: PhantomData<T>,
 }
 
-impl<'de, T> StreamDeserializer<'de, T> {
+impl<T> StreamDeserializer<'_, T> {
     pub fn offset(&self) -> usize {
         self.offset
     }
@@ -729,7 +952,7 @@ where
     type Item = Result<T>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.de.buffer.is_empty() {
+        if self.de.remaining().is_empty() {
             None
         } else {
             let next = T::deserialize(&mut self.de);
@@ -760,6 +983,93 @@ where
     T::deserialize(&mut deserializer)
 }
 
+/// This function is used to decode a value from a sequence of bytes into
+/// an existing value, reusing its allocations where possible.  Strings,
+/// vectors, and tuples of them keep their capacity when they are
+/// overwritten this way, which avoids allocating when the same value is
+/// decoded into repeatedly.  Derived structs only do so if `serde_derive`
+/// is built with its `deserialize_in_place` feature, which the `in_place`
+/// feature of this crate turns on; otherwise they are replaced wholesale,
+/// just as with [`from_bytes`].
+///
+/// # Examples
+///
+/// With the `in_place` feature enabled, decoding a derived struct into the
+/// same place twice keeps the buffers of its fields:
+///
+/// ```rust
+/// # extern crate serialization;
+/// # extern crate serde;
+/// #[derive(serde::Deserialize)]
+/// struct Message {
+///     text: String,
+/// }
+///
+/// let mut message = Message {
+///     text: String::with_capacity(64),
+/// };
+/// serialization::from_bytes_into(&mut message, &[2, b'h', b'i'][..]).unwrap();
+/// assert_eq!("hi", message.text);
+/// # #[cfg(feature = "in_place")]
+/// assert_eq!(64, message.text.capacity());
+/// ```
+///
+/// # Errors
+///
+/// This function may return the same kinds of errors as [`from_bytes`].
+/// If it does, `place` may have been partially overwritten, but is still
+/// a valid value of its type.
+pub fn from_bytes_into<'de, T>(
+    place: &mut T,
+    bytes: &'de [u8],
+) -> Result<()>
+where
+    T: serde::Deserialize<'de>,
+{
+    let mut deserializer = Deserializer::new(bytes);
+    T::deserialize_in_place(&mut deserializer, place)
+}
+
+/// This function is used to decode a value from a sequence of bytes made
+/// by [`to_bytes_interned`].  Every string decoded, including repeated ones,
+/// is borrowed from the input.
+///
+/// # Errors
+///
+/// [`Error::StringUnknown`] is returned if a string refers to an entry of
+/// the table of strings which has not been decoded.  Otherwise this
+/// function returns the same errors as [`from_bytes`].
+///
+/// [`to_bytes_interned`]: fn.to_bytes_interned.html
+/// [`from_bytes`]: fn.from_bytes.html
+/// [`Error::StringUnknown`]: enum.Error.html#variant.StringUnknown
+pub fn from_bytes_interned<'de, T>(bytes: &'de [u8]) -> Result<T>
+where
+    T: serde::Deserialize<'de>,
+{
+    let mut deserializer = Deserializer::new_interned(bytes);
+    T::deserialize(&mut deserializer)
+}
+
+/// This function is used to decode a value from a sequence of bytes made
+/// by [`to_bytes_indexed`].  A struct encoded with more fields than it has,
+/// such as by a newer version of it, is decoded with the extra fields
+/// skipped.
+///
+/// # Errors
+///
+/// This function returns the same errors as [`from_bytes`].
+///
+/// [`to_bytes_indexed`]: fn.to_bytes_indexed.html
+/// [`from_bytes`]: fn.from_bytes.html
+pub fn from_bytes_indexed<'de, T>(bytes: &'de [u8]) -> Result<T>
+where
+    T: serde::Deserialize<'de>,
+{
+    let mut deserializer = Deserializer::new_indexed(bytes);
+    T::deserialize(&mut deserializer)
+}
+
 #[cfg(test)]
 #[allow(clippy::non_ascii_literal)]
 mod tests {
@@ -768,7 +1078,7 @@ mod tests {
     #[test]
     fn deserialize_bool() {
         for (expected, value) in &[(false, &[0][..]), (true, &[1][..])] {
-            let deserialization = from_bytes(value);
+            let deserialization = from_bytes::<bool>(value);
             assert!(deserialization.is_ok());
             let deserialization = deserialization.unwrap();
             assert_eq!(*expected, deserialization);
@@ -778,7 +1088,7 @@ mod tests {
     #[test]
     fn deserialize_i8() {
         for (expected, value) in &[(42_i8, &[42][..]), (-42_i8, &[0xD6][..])] {
-            let deserialization = from_bytes(value);
+            let deserialization = from_bytes::<i8>(value);
             assert!(deserialization.is_ok());
             let deserialization = deserialization.unwrap();
             assert_eq!(*expected, deserialization);
@@ -797,7 +1107,7 @@ mod tests {
             (32767_i16, &[0x81, 0xFF, 0x7F][..]),
             (-32768_i16, &[0xC2, 0x80, 0x00][..]),
         ] {
-            let serialization = from_bytes(value);
+            let serialization = from_bytes::<i16>(value);
             assert!(serialization.is_ok());
             let serialization = serialization.unwrap();
             assert_eq!(*expected, serialization);
@@ -822,7 +1132,7 @@ mod tests {
             (2_000_000_000_i32, &[0x87, 0xB9, 0xD6, 0xA8, 0x00][..]),
             (-2_000_000_000_i32, &[0xC7, 0xB9, 0xD6, 0xA8, 0x00][..]),
         ] {
-            let deserialization = from_bytes(value);
+            let deserialization = from_bytes::<i32>(value);
             assert!(deserialization.is_ok());
             let deserialization = deserialization.unwrap();
             assert_eq!(*expected, deserialization);
@@ -855,7 +1165,7 @@ mod tests {
                 &[0xC1, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x00][..],
             ),
         ] {
-            let deserialization = from_bytes(value);
+            let deserialization = from_bytes::<i64>(value);
             assert!(deserialization.is_ok());
             let deserialization = deserialization.unwrap();
             assert_eq!(*expected, deserialization);
@@ -869,7 +1179,7 @@ mod tests {
     #[test]
     fn deserialize_u8() {
         for (expected, value) in &[(42_u8, &[42][..]), (255_u8, &[0xFF][..])] {
-            let deserialization = from_bytes(value);
+            let deserialization = from_bytes::<u8>(value);
             assert!(deserialization.is_ok());
             let deserialization = deserialization.unwrap();
             assert_eq!(*expected, deserialization);
@@ -884,7 +1194,7 @@ mod tests {
             (9001_u16, &[0xC6, 0x29][..]),
             (40000_u16, &[0x82, 0xB8, 0x40][..]),
         ] {
-            let deserialization = from_bytes(value);
+            let deserialization = from_bytes::<u16>(value);
             assert!(deserialization.is_ok());
             let deserialization = deserialization.unwrap();
             assert_eq!(*expected, deserialization);
@@ -903,7 +1213,7 @@ mod tests {
             (30_000_000_u32, &[0x8E, 0xA7, 0x87, 0x00][..]),
             (4_000_000_000_u32, &[0x8E, 0xF3, 0xAC, 0xD0, 0x00][..]),
         ] {
-            let deserialization = from_bytes(value);
+            let deserialization = from_bytes::<u32>(value);
             assert!(deserialization.is_ok());
             let deserialization = deserialization.unwrap();
             assert_eq!(*expected, deserialization);
@@ -924,7 +1234,7 @@ mod tests {
                 &[0x81, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x7F][..],
             ),
         ] {
-            let deserialization = from_bytes(value);
+            let deserialization = from_bytes::<u64>(value);
             assert!(deserialization.is_ok());
             let deserialization = deserialization.unwrap();
             assert_eq!(*expected, deserialization);
@@ -942,7 +1252,7 @@ mod tests {
             (3.141_592_5_f32, &[0x40, 0x49, 0x0F, 0xDA][..]),
             (-10_f32, &[0xC1, 0x20, 0x00, 0x00][..]),
         ] {
-            let deserialization = from_bytes(value);
+            let deserialization = from_bytes::<f32>(value);
             assert!(deserialization.is_ok());
             let deserialization = deserialization.unwrap();
             assert_eq!(*expected, deserialization);
@@ -960,7 +1270,7 @@ mod tests {
             ),
             (-10_f64, &[0xC0, 0x24, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00][..]),
         ] {
-            let deserialization = from_bytes(value);
+            let deserialization = from_bytes::<f64>(value);
             assert!(deserialization.is_ok());
             let deserialization = deserialization.unwrap();
             assert_eq!(*expected, deserialization);
@@ -1240,7 +1550,7 @@ mod tests {
     #[test]
     fn deserialize_iterate_complete() {
         let mut deserializer =
-            Deserializer::new(&[1, 0x81, 0x7F, 3][..]).iterate();
+            Deserializer::new(&[1, 0x81, 0x7F, 3][..]).iterate::<u32>();
         assert_eq!(0, deserializer.offset());
         assert_eq!(1, deserializer.next().unwrap().unwrap());
         assert_eq!(1, deserializer.offset());
@@ -1253,11 +1563,250 @@ mod tests {
 
     #[test]
     fn deserialize_iterate_incomplete() {
-        let mut deserializer = Deserializer::new(&[1, 0x81][..]).iterate();
+        let mut deserializer =
+            Deserializer::new(&[1, 0x81][..]).iterate::<u32>();
         assert_eq!(0, deserializer.offset());
         assert_eq!(1, deserializer.next().unwrap().unwrap());
         assert_eq!(1, deserializer.offset());
         assert!(deserializer.next().unwrap().is_err());
         assert_eq!(1, deserializer.offset());
     }
+
+    #[test]
+    fn deserialize_ip_addr() {
+        use std::net::{
+            IpAddr,
+            Ipv4Addr,
+            Ipv6Addr,
+        };
+        let deserialization = from_bytes(&[192, 168, 1, 2][..]);
+        assert!(deserialization.is_ok());
+        let deserialization: Ipv4Addr = deserialization.unwrap();
+        assert_eq!(Ipv4Addr::new(192, 168, 1, 2), deserialization);
+        let deserialization = from_bytes(
+            &[1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1][..],
+        );
+        assert!(deserialization.is_ok());
+        let deserialization: IpAddr = deserialization.unwrap();
+        assert_eq!(IpAddr::V6(Ipv6Addr::LOCALHOST), deserialization);
+    }
+
+    #[test]
+    fn deserialize_socket_addr() {
+        use std::net::{
+            Ipv4Addr,
+            SocketAddr,
+        };
+        let deserialization = from_bytes(&[0, 127, 0, 0, 1, 0xBF, 0x10][..]);
+        assert!(deserialization.is_ok());
+        let deserialization: SocketAddr = deserialization.unwrap();
+        assert_eq!(
+            SocketAddr::from((Ipv4Addr::LOCALHOST, 8080)),
+            deserialization
+        );
+    }
+
+    #[test]
+    fn deserialize_into_reuses_string() {
+        let mut value = String::with_capacity(64);
+        let buffer = value.as_ptr();
+        assert!(from_bytes_into(&mut value, &[3, b'a', b'b', b'c'][..]).is_ok());
+        assert_eq!("abc", value);
+        assert_eq!(64, value.capacity());
+        assert_eq!(buffer, value.as_ptr());
+    }
+
+    #[test]
+    fn deserialize_into_reuses_vec_elements() {
+        let mut value: Vec<_> =
+            (0..3).map(|_| String::with_capacity(16)).collect();
+        let buffers: Vec<_> =
+            value.iter().map(|element| element.as_ptr()).collect();
+        let bytes = [2, 1, b'x', 2, b'y', b'z'];
+        assert!(from_bytes_into(&mut value, &bytes[..]).is_ok());
+        assert_eq!(vec!["x", "yz"], value);
+        assert_eq!(3, value.capacity());
+        assert_eq!(
+            buffers[..2],
+            value.iter().map(|element| element.as_ptr()).collect::<Vec<_>>()[..]
+        );
+        let bytes = [3, 0, 0, 1, b'w'];
+        assert!(from_bytes_into(&mut value, &bytes[..]).is_ok());
+        assert_eq!(vec!["", "", "w"], value);
+    }
+
+    #[test]
+    fn deserialize_into_tuple() {
+        let mut value = (0_u16, String::with_capacity(8), vec![0_u8; 8]);
+        let bytes = [0x83, 0x74, 2, b'h', b'i', 3, 7, 8, 9];
+        assert!(from_bytes_into(&mut value, &bytes[..]).is_ok());
+        assert_eq!((500, String::from("hi"), vec![7, 8, 9]), value);
+        assert_eq!(8, value.1.capacity());
+        assert_eq!(8, value.2.capacity());
+    }
+
+    #[cfg(feature = "in_place")]
+    #[test]
+    fn deserialize_into_reuses_struct_fields() {
+        #[derive(serde::Deserialize, PartialEq, Debug)]
+        struct Record {
+            name: String,
+            samples: Vec<u16>,
+        }
+
+        let mut value = Record {
+            name: String::new(),
+            samples: Vec::new(),
+        };
+        let bytes = [5, b'f', b'i', b'r', b's', b't', 3, 1, 2, 3];
+        assert!(from_bytes_into(&mut value, &bytes[..]).is_ok());
+        let name = (value.name.as_ptr(), value.name.capacity());
+        let samples = (value.samples.as_ptr(), value.samples.capacity());
+        let bytes = [4, b'l', b'a', b's', b't', 2, 9, 8];
+        assert!(from_bytes_into(&mut value, &bytes[..]).is_ok());
+        assert_eq!(
+            Record {
+                name: String::from("last"),
+                samples: vec![9, 8],
+            },
+            value
+        );
+        assert_eq!(name, (value.name.as_ptr(), value.name.capacity()));
+        assert_eq!(samples, (value.samples.as_ptr(), value.samples.capacity()));
+    }
+
+    #[test]
+    fn deserialize_into_truncated() {
+        let mut value = vec![String::from("kept")];
+        assert!(matches!(
+            from_bytes_into(&mut value, &[2, 1, b'x'][..]),
+            Err(Error::ValueTruncated)
+        ));
+    }
+
+    #[test]
+    fn integer_round_trip() {
+        for shift in 0..64 {
+            for unsigned in [(1_u64 << shift) - 1, 1_u64 << shift] {
+                let bytes = crate::to_bytes(&unsigned).unwrap();
+                assert_eq!(unsigned, from_bytes::<u64>(&bytes).unwrap());
+                #[allow(clippy::cast_possible_wrap)]
+                for signed in
+                    [unsigned as i64, (unsigned as i64).wrapping_neg()]
+                {
+                    let bytes = crate::to_bytes(&signed).unwrap();
+                    assert_eq!(signed, from_bytes::<i64>(&bytes).unwrap());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn deserialize_seq_reserves_length() {
+        let value: Vec<u16> = from_bytes(&[3, 1, 2, 3][..]).unwrap();
+        assert_eq!(vec![1, 2, 3], value);
+        assert_eq!(3, value.capacity());
+    }
+
+    #[test]
+    fn deserialize_seq_hostile_length() {
+        let bytes = [0x8F, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x7F, 1];
+        assert!(matches!(
+            from_bytes::<Vec<u64>>(&bytes[..]),
+            Err(Error::ValueTruncated)
+        ));
+        assert!(matches!(
+            from_bytes::<std::collections::HashMap<u8, u8>>(&bytes[..]),
+            Err(Error::ValueTruncated)
+        ));
+    }
+
+    #[test]
+    fn deserialize_unknown_variant() {
+        #[derive(serde::Deserialize, Debug)]
+        enum Light {
+            Off,
+            On,
+        }
+        assert!(matches!(
+            from_bytes::<Light>(&[2][..]),
+            Err(Error::UnknownVariant(2))
+        ));
+        assert!(matches!(
+            from_bytes::<Light>(&[0x82, 0x00][..]),
+            Err(Error::UnknownVariant(256))
+        ));
+    }
+
+    #[test]
+    fn deserialize_other_variant() {
+        #[derive(serde::Deserialize, Debug, PartialEq)]
+        enum Light {
+            Off,
+            On,
+            #[serde(other)]
+            Unknown,
+        }
+        let deserialization = from_bytes::<Light>(&[1][..]);
+        assert!(deserialization.is_ok());
+        let deserialization = deserialization.unwrap();
+        assert_eq!(Light::On, deserialization);
+        let deserialization = from_bytes::<Light>(&[5][..]);
+        assert!(deserialization.is_ok());
+        let deserialization = deserialization.unwrap();
+        assert_eq!(Light::Unknown, deserialization);
+    }
+
+    #[test]
+    fn deserialize_interned() {
+        let encoding = [4, b'a', b'b', 3, 2, b'c', 1, 1];
+        let deserialization: Result<(&str, Vec<&str>)> =
+            from_bytes_interned(&encoding[..]);
+        assert!(deserialization.is_ok());
+        let (first, rest) = deserialization.unwrap();
+        assert_eq!(("ab", vec!["c", "ab", "ab"]), (first, rest.clone()));
+        assert_eq!(encoding[1..].as_ptr(), rest[2].as_ptr());
+        let owned: Vec<String> =
+            from_bytes_interned(&[2, 2, b'c', 1][..]).unwrap();
+        assert_eq!(vec![String::from("c"); 2], owned);
+        assert!(matches!(
+            from_bytes_interned::<Vec<&str>>(&[2, 2, b'c', 3][..]),
+            Err(Error::StringUnknown(1))
+        ));
+    }
+
+    #[test]
+    fn deserialize_indexed() {
+        #[derive(serde::Deserialize, PartialEq, Debug)]
+        struct Point<'a> {
+            x: u16,
+            name: &'a str,
+        }
+        let encoding = [
+            3, 0, 0, 0, 2, 0, 0, 0, 4, 0, 0, 0, 5, 0x82, 0x2C, 1, b'a', 9, 2,
+            0, 0, 0, 1, 0, 0, 0, 2, 1, 0, 7,
+        ];
+        let deserialization: Result<(Point, Point, u8)> =
+            from_bytes_indexed(&encoding[..]);
+        assert!(deserialization.is_ok());
+        let deserialization = deserialization.unwrap();
+        assert_eq!(
+            (
+                Point {
+                    x: 300,
+                    name: "a",
+                },
+                Point {
+                    x: 1,
+                    name: "",
+                },
+                7
+            ),
+            deserialization
+        );
+        assert!(matches!(
+            from_bytes_indexed::<Vec<u8>>(&[2, 0, 0, 0, 1, 0, 0, 0, 3, 1][..]),
+            Err(Error::ValueTruncated)
+        ));
+    }
 }
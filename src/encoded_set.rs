@@ -0,0 +1,296 @@
+use super::{
+    to_bytes,
+    Result,
+};
+use std::fmt;
+
+/// This is a container of unique values, where two values are considered
+/// the same if they encode to the same sequence of bytes.  It mirrors the
+/// `Add` and `Remove` methods of the C++ `SerializedVector` class, which
+/// compare elements in the same way.
+///
+/// Values keep the order in which they were first inserted.  The set is
+/// encoded exactly like a `Vec<T>` holding the same values, which is also
+/// the layout of the C++ `SerializedVector` class.  When a set is decoded,
+/// any value whose encoding duplicates that of an earlier value is dropped.
+///
+/// An `EncodedSet<`[`Object`]`>` holds the same elements, in the same encoding,
+/// as a C++ `SerializedVector`.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate serialization;
+/// use serialization::EncodedSet;
+///
+/// let mut tags = EncodedSet::new();
+/// assert!(tags.insert(String::from("red")).unwrap());
+/// assert!(tags.insert(String::from("blue")).unwrap());
+/// assert!(!tags.insert(String::from("red")).unwrap());
+/// assert!(tags.contains("blue").unwrap());
+/// assert!(tags.remove("blue").unwrap());
+/// assert_eq!(&["red"][..], tags.as_slice());
+/// ```
+///
+/// [`Object`]: enum.Object.html
+#[derive(Clone)]
+pub struct EncodedSet<T> {
+    elements: Vec<T>,
+    encodings: Vec<Vec<u8>>,
+}
+
+impl<T> EncodedSet<T> {
+    /// Return a slice of the values in the set, in the order in which they
+    /// were inserted.
+    #[must_use]
+    pub fn as_slice(&self) -> &[T] {
+        &self.elements
+    }
+
+    /// Determine whether or not the set holds a value with the same
+    /// encoding as the given value.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the given value cannot be encoded.
+    pub fn contains<Q>(
+        &self,
+        value: &Q,
+    ) -> Result<bool>
+    where
+        Q: ?Sized + serde::Serialize,
+    {
+        let encoding = to_bytes(value)?;
+        Ok(self.position(&encoding).is_some())
+    }
+
+    /// Consume the set, returning a vector of the values it held, in the
+    /// order in which they were inserted.
+    #[must_use]
+    pub fn into_vec(self) -> Vec<T> {
+        self.elements
+    }
+
+    /// Return `true` if the set holds no values.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// Return an iterator over the values in the set, in the order in which
+    /// they were inserted.
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.elements.iter()
+    }
+
+    /// Return the number of values in the set.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    /// Return a new set which holds no values.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            elements: Vec::new(),
+            encodings: Vec::new(),
+        }
+    }
+
+    fn position(
+        &self,
+        encoding: &[u8],
+    ) -> Option<usize> {
+        self.encodings.iter().position(|other| other == encoding)
+    }
+
+    /// Remove the value with the same encoding as the given value, if
+    /// the set holds one.  Return `true` if a value was removed.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the given value cannot be encoded.
+    pub fn remove<Q>(
+        &mut self,
+        value: &Q,
+    ) -> Result<bool>
+    where
+        Q: ?Sized + serde::Serialize,
+    {
+        let encoding = to_bytes(value)?;
+        Ok(self
+            .position(&encoding)
+            .map(|index| {
+                self.elements.remove(index);
+                self.encodings.remove(index);
+            })
+            .is_some())
+    }
+}
+
+impl<T> EncodedSet<T>
+where
+    T: serde::Serialize,
+{
+    /// Build a set from the values in the given vector, dropping any value
+    /// whose encoding duplicates that of an earlier value.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if any value cannot be encoded.
+    pub fn from_vec(values: Vec<T>) -> Result<Self> {
+        let mut set = Self::new();
+        for value in values {
+            set.insert(value)?;
+        }
+        Ok(set)
+    }
+
+    /// Add the given value to the end of the set, unless the set already
+    /// holds a value with the same encoding.  Return `true` if the value
+    /// was added.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the given value cannot be encoded.
+    pub fn insert(
+        &mut self,
+        value: T,
+    ) -> Result<bool> {
+        let encoding = to_bytes(&value)?;
+        if self.position(&encoding).is_some() {
+            Ok(false)
+        } else {
+            self.elements.push(value);
+            self.encodings.push(encoding);
+            Ok(true)
+        }
+    }
+}
+
+impl<T> Default for EncodedSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for EncodedSet<T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        f.debug_set().entries(&self.elements).finish()
+    }
+}
+
+impl<T> IntoIterator for EncodedSet<T> {
+    type IntoIter = std::vec::IntoIter<T>;
+    type Item = T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.elements.into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a EncodedSet<T> {
+    type IntoIter = std::slice::Iter<'a, T>;
+    type Item = &'a T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.elements.iter()
+    }
+}
+
+impl<T> serde::Serialize for EncodedSet<T>
+where
+    T: serde::Serialize,
+{
+    fn serialize<S>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_seq(&self.elements)
+    }
+}
+
+impl<'de, T> serde::Deserialize<'de> for EncodedSet<T>
+where
+    T: serde::Deserialize<'de> + serde::Serialize,
+{
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let values = Vec::deserialize(deserializer)?;
+        Self::from_vec(values).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::from_bytes;
+
+    #[test]
+    fn insert_compares_encodings() {
+        let mut set = EncodedSet::new();
+        assert!(set.insert(200_u32).unwrap());
+        assert!(set.insert(7_u32).unwrap());
+        assert!(!set.insert(200_u32).unwrap());
+        assert_eq!(&[200, 7][..], set.as_slice());
+        assert!(set.contains(&200_u64).unwrap());
+        assert!(!set.contains(&200_u8).unwrap());
+    }
+
+    #[test]
+    fn remove() {
+        let mut set = EncodedSet::from_vec(vec!["a", "b", "c"]).unwrap();
+        assert!(set.remove("b").unwrap());
+        assert!(!set.remove("b").unwrap());
+        assert_eq!(&["a", "c"][..], set.as_slice());
+        assert!(set.insert("b").unwrap());
+        assert_eq!(&["a", "c", "b"][..], set.as_slice());
+    }
+
+    #[test]
+    fn serialize_as_sequence() {
+        let set = EncodedSet::from_vec(vec!['a', 'b', 'a']).unwrap();
+        let serialization = to_bytes(&set);
+        assert!(serialization.is_ok());
+        let serialization = serialization.unwrap();
+        assert_eq!(&[2, 97, 98][..], serialization);
+        assert_eq!(to_bytes(&vec!['a', 'b']).unwrap(), serialization);
+    }
+
+    #[test]
+    fn deserialize_drops_duplicates() {
+        let deserialization = from_bytes(&[3, 97, 98, 97][..]);
+        assert!(deserialization.is_ok());
+        let deserialization: EncodedSet<char> = deserialization.unwrap();
+        assert_eq!(&['a', 'b'][..], deserialization.as_slice());
+    }
+
+    #[test]
+    fn objects_match_cpp_vector() {
+        use crate::Object;
+        let mut set = EncodedSet::new();
+        assert!(set.insert(Object::from("x")).unwrap());
+        assert!(set.insert(Object::Integer(1)).unwrap());
+        assert!(!set.insert(Object::from("x")).unwrap());
+        assert!(!set.contains(&Object::UnsignedInteger(1)).unwrap());
+        let serialization = to_bytes(&Object::Vector(set.into_vec()));
+        assert!(serialization.is_ok());
+        let serialization = serialization.unwrap();
+        assert_eq!(
+            &[12, 1, 0x76, 2, 4, 1, 0x73, 1, 0x78, 3, 1, 0x6E, 1][..],
+            serialization
+        );
+    }
+}
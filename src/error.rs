@@ -37,6 +37,80 @@ pub enum Error {
     /// cannot determine them during deserialization.
     #[error("cannot determine identifiers in this format")]
     IdentifierUnknown,
+
+    /// The checksum at the end of the input buffer does not match the
+    /// data before it, which indicates that the data is corrupt.  This
+    /// holds the offset of the data which was checked, such as the start of
+    /// a record in a [`RecordLog`], or zero for a whole buffer.
+    ///
+    /// [`RecordLog`]: struct.RecordLog.html
+    #[error("checksum does not match data at offset {0}")]
+    ChecksumMismatch(u64),
+
+    /// A schema could not be traced because the type contains itself in a
+    /// way which no choice of optional values or enum variants can end.
+    #[error("type contains itself without end")]
+    RecursionUnbounded,
+
+    /// An array of elements borrowed from the input buffer is not aligned
+    /// in memory for its element type.
+    #[error("array data is not aligned for its element type")]
+    DataMisaligned,
+
+    /// An array of elements borrowed from the input buffer was encoded on a
+    /// machine with the opposite byte order.
+    #[error("array data has the wrong byte order for this machine")]
+    ByteOrderMismatch,
+
+    /// An enum was encoded with a variant id which the enum does not have,
+    /// such as one from a newer version of the enum.
+    #[error("unknown enum variant id {0}")]
+    UnknownVariant(u64),
+
+    /// A string encoded in the interned layout refers to an entry of the
+    /// table of strings which has not been decoded.
+    #[error("reference to unknown string {0}")]
+    StringUnknown(usize),
+
+    /// A value encoded in the shared layout refers to an earlier shared
+    /// value which has not been decoded, such as one which contains it.
+    #[error("reference to unknown shared value {0}")]
+    SharedUnknown(usize),
+
+    /// A patch was applied to a value other than the one it was made from,
+    /// so the parts it changes are not found in the value.
+    #[error("patch does not fit the value it is applied to")]
+    PatchMismatch,
+
+    /// A struct or sequence encoded in the indexed layout takes more bytes
+    /// than its table of offsets can address.
+    #[error("value too large for the indexed layout")]
+    IndexedTooLarge,
+
+    /// A struct viewed in place has no field with the given name, or was
+    /// encoded without it.
+    #[error("no field named {0}")]
+    FieldUnknown(String),
+
+    /// A struct or sequence viewed in place has no element at the given
+    /// position.
+    #[error("no element at position {0}")]
+    IndexOutOfRange(usize),
+
+    /// A value viewed in place was asked for an element, but is not a struct
+    /// or sequence.
+    #[error("value has no elements")]
+    ElementsUnknown,
+
+    /// An error occurred reading or writing a file.
+    #[error("input/output error")]
+    Io(#[source] std::io::Error),
+
+    /// A JSON value did not fit the layout it was transcoded with.  This
+    /// holds the path to the value within the JSON, such as
+    /// `$.items[3].name`.
+    #[error("JSON value does not fit the layout at {0}")]
+    JsonMismatch(String),
 }
 
 impl serde::ser::Error for Error {
@@ -0,0 +1,426 @@
+use serde::de::{
+    Error as _,
+    Unexpected,
+};
+use std::{
+    convert::TryFrom,
+    fmt,
+    marker::PhantomData,
+};
+
+/// This is the largest number of elements which a run-length encoded
+/// vector may decode to.  Each run takes only a few bytes however long it
+/// is, so without a limit a tiny hostile input could exhaust memory.
+pub(crate) const MAX_RUN_ELEMENTS: u64 = 1 << 24;
+
+/// This is implemented by the two integer types, `i64` and `u64`, in which
+/// elements of integer vectors are encoded.
+pub(crate) trait Wire:
+    Copy + Default + PartialEq + serde::Serialize + for<'de> serde::Deserialize<'de>
+{
+    fn wrapping_add(
+        self,
+        rhs: Self,
+    ) -> Self;
+
+    fn wrapping_sub(
+        self,
+        rhs: Self,
+    ) -> Self;
+
+    fn unexpected(self) -> Unexpected<'static>;
+}
+
+impl Wire for i64 {
+    fn wrapping_add(
+        self,
+        rhs: Self,
+    ) -> Self {
+        i64::wrapping_add(self, rhs)
+    }
+
+    fn wrapping_sub(
+        self,
+        rhs: Self,
+    ) -> Self {
+        i64::wrapping_sub(self, rhs)
+    }
+
+    fn unexpected(self) -> Unexpected<'static> {
+        Unexpected::Signed(self)
+    }
+}
+
+impl Wire for u64 {
+    fn wrapping_add(
+        self,
+        rhs: Self,
+    ) -> Self {
+        u64::wrapping_add(self, rhs)
+    }
+
+    fn wrapping_sub(
+        self,
+        rhs: Self,
+    ) -> Self {
+        u64::wrapping_sub(self, rhs)
+    }
+
+    fn unexpected(self) -> Unexpected<'static> {
+        Unexpected::Unsigned(self)
+    }
+}
+
+/// This selects the optional transformations applied to the elements of an
+/// integer vector before they are encoded.
+#[derive(Clone, Copy)]
+pub(crate) struct Packing {
+    /// Each element after the first is replaced by its difference from the
+    /// element before it.
+    pub(crate) delta: bool,
+
+    /// Consecutive equal elements are collapsed into a single
+    /// `(element, count)` pair.
+    pub(crate) rle: bool,
+}
+
+pub(crate) fn serialize<W, T, S>(
+    values: &[T],
+    packing: Packing,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    W: Wire,
+    T: Copy + Into<W>,
+    S: serde::Serializer,
+{
+    use serde::ser::SerializeSeq;
+    let mut previous = W::default();
+    let elements = values.iter().map(|value| {
+        let value: W = (*value).into();
+        if packing.delta {
+            let difference = value.wrapping_sub(previous);
+            previous = value;
+            difference
+        } else {
+            value
+        }
+    });
+    if packing.rle {
+        let mut runs: Vec<(W, u64)> = Vec::new();
+        for element in elements {
+            match runs.last_mut() {
+                Some((value, count)) if *value == element => *count += 1,
+                _ => runs.push((element, 1)),
+            }
+        }
+        serializer.collect_seq(runs)
+    } else {
+        let mut seq = serializer.serialize_seq(Some(values.len()))?;
+        for element in elements {
+            seq.serialize_element(&element)?;
+        }
+        seq.end()
+    }
+}
+
+pub(crate) fn deserialize<'de, W, T, D>(
+    packing: Packing,
+    deserializer: D,
+) -> Result<Vec<T>, D::Error>
+where
+    W: Wire,
+    T: TryFrom<W>,
+    D: serde::Deserializer<'de>,
+{
+    struct Visitor<W, T> {
+        packing: Packing,
+        output: PhantomData<(W, T)>,
+    }
+
+    impl<W, T> Visitor<W, T>
+    where
+        W: Wire,
+        T: TryFrom<W>,
+    {
+        fn push<E>(
+            &self,
+            values: &mut Vec<T>,
+            previous: &mut W,
+            element: W,
+        ) -> Result<(), E>
+        where
+            E: serde::de::Error,
+        {
+            let value = if self.packing.delta {
+                previous.wrapping_add(element)
+            } else {
+                element
+            };
+            *previous = value;
+            values.push(T::try_from(value).map_err(|_| {
+                E::invalid_value(value.unexpected(), &"integer in range")
+            })?);
+            Ok(())
+        }
+    }
+
+    impl<'de, W, T> serde::de::Visitor<'de> for Visitor<W, T>
+    where
+        W: Wire,
+        T: TryFrom<W>,
+    {
+        type Value = Vec<T>;
+
+        fn expecting(
+            &self,
+            f: &mut fmt::Formatter,
+        ) -> fmt::Result {
+            f.write_str("a sequence of integers")
+        }
+
+        fn visit_seq<A>(
+            self,
+            mut seq: A,
+        ) -> Result<Self::Value, A::Error>
+        where
+            A: serde::de::SeqAccess<'de>,
+        {
+            let mut values = Vec::new();
+            let mut previous = W::default();
+            if self.packing.rle {
+                let mut total: u64 = 0;
+                while let Some((element, count)) =
+                    seq.next_element::<(W, u64)>()?
+                {
+                    if count == 0 {
+                        return Err(A::Error::invalid_value(
+                            Unexpected::Unsigned(count),
+                            &"a nonzero run length",
+                        ));
+                    }
+                    total = total
+                        .checked_add(count)
+                        .filter(|&total| total <= MAX_RUN_ELEMENTS)
+                        .ok_or_else(|| {
+                            A::Error::invalid_length(
+                                usize::try_from(total.saturating_add(count))
+                                    .unwrap_or(usize::MAX),
+                                &"at most 16777216 elements",
+                            )
+                        })?;
+                    for _ in 0..count {
+                        self.push(&mut values, &mut previous, element)?;
+                    }
+                }
+            } else {
+                while let Some(element) = seq.next_element::<W>()? {
+                    self.push(&mut values, &mut previous, element)?;
+                }
+            }
+            Ok(values)
+        }
+    }
+
+    deserializer.deserialize_seq(Visitor {
+        packing,
+        output: PhantomData,
+    })
+}
+
+macro_rules! packings {
+    (
+        $wire:ident,
+        $class:literal,
+        $(#[$delta:meta])*
+        mod delta;
+    ) => {
+        const PLAIN: $crate::int_seq::Packing = $crate::int_seq::Packing {
+            delta: false,
+            rle: false,
+        };
+
+        #[doc = concat!(
+            "Encode the given vector in the layout used by the C++\n`",
+            $class,
+            "` class.\n\n",
+            "# Errors\n\n",
+            "Any error returned by the serializer is passed through unchanged.",
+        )]
+        pub fn serialize<T, S>(
+            values: &[T],
+            serializer: S,
+        ) -> Result<S::Ok, S::Error>
+        where
+            T: Copy + Into<$wire>,
+            S: serde::Serializer,
+        {
+            $crate::int_seq::serialize::<$wire, _, _>(values, PLAIN, serializer)
+        }
+
+        #[doc = concat!(
+            "Decode a vector from the layout used by the C++\n`",
+            $class,
+            "` class.\n\n",
+            "# Errors\n\n",
+            "In addition to any error returned by the deserializer, an error ",
+            "is\nreturned if any element does not fit in the element type.",
+        )]
+        pub fn deserialize<'de, T, D>(
+            deserializer: D,
+        ) -> Result<Vec<T>, D::Error>
+        where
+            T: std::convert::TryFrom<$wire>,
+            D: serde::Deserializer<'de>,
+        {
+            $crate::int_seq::deserialize::<$wire, _, _>(PLAIN, deserializer)
+        }
+
+        $(#[$delta])*
+        pub mod delta {
+            const DELTA: $crate::int_seq::Packing = $crate::int_seq::Packing {
+                delta: true,
+                rle: false,
+            };
+
+            /// Encode the given vector as a sequence of differences.
+            ///
+            /// # Errors
+            ///
+            /// Any error returned by the serializer is passed through
+            /// unchanged.
+            pub fn serialize<T, S>(
+                values: &[T],
+                serializer: S,
+            ) -> Result<S::Ok, S::Error>
+            where
+                T: Copy + Into<$wire>,
+                S: serde::Serializer,
+            {
+                $crate::int_seq::serialize::<$wire, _, _>(
+                    values, DELTA, serializer,
+                )
+            }
+
+            /// Decode a vector encoded as a sequence of differences.
+            ///
+            /// # Errors
+            ///
+            /// In addition to any error returned by the deserializer, an
+            /// error is returned if any element does not fit in the element
+            /// type.
+            pub fn deserialize<'de, T, D>(
+                deserializer: D,
+            ) -> Result<Vec<T>, D::Error>
+            where
+                T: std::convert::TryFrom<$wire>,
+                D: serde::Deserializer<'de>,
+            {
+                $crate::int_seq::deserialize::<$wire, _, _>(DELTA, deserializer)
+            }
+        }
+
+        /// This module encodes each run of equal elements as the element
+        /// followed by the length of the run.  A vector may decode to at
+        /// most 16,777,216 elements.
+        pub mod rle {
+            const RLE: $crate::int_seq::Packing = $crate::int_seq::Packing {
+                delta: false,
+                rle: true,
+            };
+
+            /// Encode the given vector as a sequence of runs.
+            ///
+            /// # Errors
+            ///
+            /// Any error returned by the serializer is passed through
+            /// unchanged.
+            pub fn serialize<T, S>(
+                values: &[T],
+                serializer: S,
+            ) -> Result<S::Ok, S::Error>
+            where
+                T: Copy + Into<$wire>,
+                S: serde::Serializer,
+            {
+                $crate::int_seq::serialize::<$wire, _, _>(
+                    values, RLE, serializer,
+                )
+            }
+
+            /// Decode a vector encoded as a sequence of runs.
+            ///
+            /// # Errors
+            ///
+            /// In addition to any error returned by the deserializer, an
+            /// error is returned if any element does not fit in the element
+            /// type, if any run is empty, or if the runs add up to too many
+            /// elements.
+            pub fn deserialize<'de, T, D>(
+                deserializer: D,
+            ) -> Result<Vec<T>, D::Error>
+            where
+                T: std::convert::TryFrom<$wire>,
+                D: serde::Deserializer<'de>,
+            {
+                $crate::int_seq::deserialize::<$wire, _, _>(RLE, deserializer)
+            }
+        }
+
+        /// This module combines the [`delta`] and [`rle`] packings, so that
+        /// runs of equal differences, such as in a vector of consecutive
+        /// identifiers, collapse into a single pair.
+        ///
+        /// [`delta`]: ../delta/index.html
+        /// [`rle`]: ../rle/index.html
+        pub mod delta_rle {
+            const DELTA_RLE: $crate::int_seq::Packing =
+                $crate::int_seq::Packing {
+                    delta: true,
+                    rle: true,
+                };
+
+            /// Encode the given vector as a sequence of runs of differences.
+            ///
+            /// # Errors
+            ///
+            /// Any error returned by the serializer is passed through
+            /// unchanged.
+            pub fn serialize<T, S>(
+                values: &[T],
+                serializer: S,
+            ) -> Result<S::Ok, S::Error>
+            where
+                T: Copy + Into<$wire>,
+                S: serde::Serializer,
+            {
+                $crate::int_seq::serialize::<$wire, _, _>(
+                    values, DELTA_RLE, serializer,
+                )
+            }
+
+            /// Decode a vector encoded as a sequence of runs of differences.
+            ///
+            /// # Errors
+            ///
+            /// In addition to any error returned by the deserializer, an
+            /// error is returned if any element does not fit in the element
+            /// type, if any run is empty, or if the runs add up to too many
+            /// elements.
+            pub fn deserialize<'de, T, D>(
+                deserializer: D,
+            ) -> Result<Vec<T>, D::Error>
+            where
+                T: std::convert::TryFrom<$wire>,
+                D: serde::Deserializer<'de>,
+            {
+                $crate::int_seq::deserialize::<$wire, _, _>(
+                    DELTA_RLE,
+                    deserializer,
+                )
+            }
+        }
+    };
+}
+
+pub(crate) use packings;
@@ -0,0 +1,152 @@
+//! This module provides functions for use with serde's `with` field
+//! attribute in order to encode a vector of signed integers using exactly
+//! the same layout as the C++ `SerializedIntegerVector` class: the number of
+//! elements, followed by each element as a variable-length signed integer.
+//!
+//! Elements of any signed integer type which converts losslessly to `i64`
+//! may be used.  Unlike a plain `Vec<i8>`, which encodes each element as a
+//! single raw byte, a vector encoded through this module always uses the
+//! variable-length encoding, so that it can be read by the C++ class.
+//!
+//! The [`delta`], [`rle`], and [`delta_rle`] submodules provide optional
+//! packings of the same data which are more compact for sorted or
+//! repetitive sequences.  These packings are not understood by the C++
+//! implementation.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # extern crate serialization;
+//! # extern crate serde;
+//! #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+//! struct Samples {
+//!     #[serde(with = "serialization::int_vec")]
+//!     raw: Vec<i8>,
+//!     #[serde(with = "serialization::int_vec::delta")]
+//!     timestamps: Vec<i64>,
+//! }
+//!
+//! let samples = Samples {
+//!     raw: vec![-1, 100],
+//!     timestamps: vec![1_000_000, 1_000_001, 1_000_003],
+//! };
+//! let encoding = serialization::to_bytes(&samples).unwrap();
+//! assert_eq!(&[2, 0x41, 0x80, 0x64, 3, 0xBD, 0x84, 0x40, 1, 2][..], encoding);
+//! assert_eq!(samples, serialization::from_bytes(&encoding).unwrap());
+//! ```
+//!
+//! [`delta`]: delta/index.html
+//! [`rle`]: rle/index.html
+//! [`delta_rle`]: delta_rle/index.html
+
+crate::int_seq::packings! {
+    i64,
+    "SerializedIntegerVector",
+    /// This module encodes each element after the first as its difference from
+    /// the element before it, which keeps the encoding of a sorted vector
+    /// compact even when the elements themselves are large.
+    mod delta;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        from_bytes,
+        to_bytes,
+        Error,
+        Result,
+    };
+
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    struct Plain(#[serde(with = "super")] Vec<i32>);
+
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    struct Delta(#[serde(with = "super::delta")] Vec<i64>);
+
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    struct Rle(#[serde(with = "super::rle")] Vec<i8>);
+
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    struct DeltaRle(#[serde(with = "super::delta_rle")] Vec<i64>);
+
+    #[test]
+    fn plain_matches_cpp_layout() {
+        let value = Plain(vec![42, -42, 4000]);
+        let serialization = to_bytes(&value);
+        assert!(serialization.is_ok());
+        let serialization = serialization.unwrap();
+        assert_eq!(&[3, 0x2A, 0x6A, 0x9F, 0x20][..], serialization);
+        let deserialization: Result<Plain> = from_bytes(&serialization);
+        assert!(deserialization.is_ok());
+        assert_eq!(value, deserialization.unwrap());
+    }
+
+    #[test]
+    fn element_out_of_range() {
+        let deserialization: Result<Rle> = from_bytes(&[1, 0x81, 0x00, 1][..]);
+        assert!(matches!(deserialization, Err(Error::Message(_))));
+    }
+
+    #[test]
+    fn delta_round_trip() {
+        for (value, expected) in &[
+            (vec![], &[0][..]),
+            (vec![100, 101, 99], &[3, 0x80, 0x64, 0x01, 0x42][..]),
+            (
+                vec![i64::MAX, i64::MIN],
+                &[
+                    2, 0x80, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+                    0x7F, 0x01,
+                ][..],
+            ),
+        ] {
+            let value = Delta(value.clone());
+            let serialization = to_bytes(&value);
+            assert!(serialization.is_ok());
+            let serialization = serialization.unwrap();
+            assert_eq!(*expected, serialization);
+            let deserialization: Result<Delta> = from_bytes(&serialization);
+            assert!(deserialization.is_ok());
+            assert_eq!(value, deserialization.unwrap());
+        }
+    }
+
+    #[test]
+    fn rle_round_trip() {
+        let value = Rle(vec![7, 7, 7, -1, 7]);
+        let serialization = to_bytes(&value);
+        assert!(serialization.is_ok());
+        let serialization = serialization.unwrap();
+        assert_eq!(&[3, 7, 3, 0x41, 1, 7, 1][..], serialization);
+        let deserialization: Result<Rle> = from_bytes(&serialization);
+        assert!(deserialization.is_ok());
+        assert_eq!(value, deserialization.unwrap());
+    }
+
+    #[test]
+    fn rle_empty_run() {
+        let deserialization: Result<Rle> = from_bytes(&[1, 7, 0][..]);
+        assert!(matches!(deserialization, Err(Error::Message(_))));
+    }
+
+    #[test]
+    fn rle_run_too_long() {
+        let deserialization: Result<Rle> = from_bytes(
+            &[1, 7, 0x81, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x7F]
+                [..],
+        );
+        assert!(matches!(deserialization, Err(Error::Message(_))));
+    }
+
+    #[test]
+    fn delta_rle_round_trip() {
+        let value = DeltaRle((1000..1100).collect());
+        let serialization = to_bytes(&value);
+        assert!(serialization.is_ok());
+        let serialization = serialization.unwrap();
+        assert_eq!(&[2, 0x87, 0x68, 1, 1, 0x63][..], serialization);
+        let deserialization: Result<DeltaRle> = from_bytes(&serialization);
+        assert!(deserialization.is_ok());
+        assert_eq!(value, deserialization.unwrap());
+    }
+}
@@ -0,0 +1,115 @@
+//! This module provides functions for use with serde's `with` field
+//! attribute in order to encode an [`Ipv4Addr`] using exactly the same
+//! layout as the C++ `SerializedIpAddress` class.
+//!
+//! The C++ implementation stores an IPv4 address as a single 32-bit
+//! integer whose most significant byte is the first octet of the dotted
+//! rendering, and writes that integer to the file in the byte order of the
+//! host.  Since every platform on which the C++ library is deployed is
+//! little-endian, this module always uses little-endian byte order, so that
+//! `192.168.1.2` is encoded as `[2, 1, 168, 192]`.
+//!
+//! Without this module, [`Ipv4Addr`] values are encoded in the compact form
+//! chosen by `serde` for formats which are not human-readable: the four
+//! octets in the order they are written in the dotted rendering.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # extern crate serialization;
+//! # extern crate serde;
+//! use std::net::Ipv4Addr;
+//!
+//! #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+//! struct Peer {
+//!     #[serde(with = "serialization::ip_address")]
+//!     address: Ipv4Addr,
+//!     port: u16,
+//! }
+//!
+//! let peer = Peer {
+//!     address: Ipv4Addr::new(192, 168, 1, 2),
+//!     port: 80,
+//! };
+//! let encoding = serialization::to_bytes(&peer).unwrap();
+//! assert_eq!(&[2, 1, 168, 192, 80][..], encoding);
+//! assert_eq!(peer, serialization::from_bytes(&encoding).unwrap());
+//! ```
+//!
+//! [`Ipv4Addr`]: https://doc.rust-lang.org/std/net/struct.Ipv4Addr.html
+
+use std::net::Ipv4Addr;
+
+/// Encode the given IPv4 address in the layout used by the C++
+/// `SerializedIpAddress` class.
+///
+/// # Errors
+///
+/// Any error returned by the serializer is passed through unchanged.
+pub fn serialize<S>(
+    address: &Ipv4Addr,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    serde::Serialize::serialize(&u32::from(*address).to_le_bytes(), serializer)
+}
+
+/// Decode an IPv4 address from the layout used by the C++
+/// `SerializedIpAddress` class.
+///
+/// # Errors
+///
+/// Any error returned by the deserializer is passed through unchanged.
+pub fn deserialize<'de, D>(deserializer: D) -> Result<Ipv4Addr, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let bytes: [u8; 4] = serde::Deserialize::deserialize(deserializer)?;
+    Ok(Ipv4Addr::from(u32::from_le_bytes(bytes)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        from_bytes,
+        to_bytes,
+        Error,
+        Result,
+    };
+
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    struct Wrapper(#[serde(with = "super")] Ipv4Addr);
+
+    #[test]
+    fn serialize_matches_cpp_layout() {
+        for (value, expected) in &[
+            (Ipv4Addr::new(192, 168, 1, 2), &[2, 1, 168, 192][..]),
+            (Ipv4Addr::new(10, 0, 0, 1), &[1, 0, 0, 10][..]),
+            (Ipv4Addr::UNSPECIFIED, &[0, 0, 0, 0][..]),
+        ] {
+            let serialization = to_bytes(&Wrapper(*value));
+            assert!(serialization.is_ok());
+            let serialization = serialization.unwrap();
+            assert_eq!(*expected, serialization);
+        }
+    }
+
+    #[test]
+    fn deserialize_matches_cpp_layout() {
+        for (expected, value) in &[
+            (Ipv4Addr::new(192, 168, 1, 2), &[2, 1, 168, 192][..]),
+            (Ipv4Addr::new(10, 0, 0, 1), &[1, 0, 0, 10][..]),
+            (Ipv4Addr::BROADCAST, &[255, 255, 255, 255][..]),
+        ] {
+            let deserialization: Result<Wrapper> = from_bytes(value);
+            assert!(deserialization.is_ok());
+            let deserialization = deserialization.unwrap();
+            assert_eq!(Wrapper(*expected), deserialization);
+        }
+        let deserialization: Result<Wrapper> = from_bytes(&[1, 0, 0][..]);
+        assert!(matches!(deserialization, Err(Error::ValueTruncated)));
+    }
+}
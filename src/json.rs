@@ -0,0 +1,1065 @@
+use super::{
+    appendable,
+    borrowed_slice,
+    from_bytes,
+    schema::type_name,
+    shared,
+    to_bytes,
+    to_bytes_into,
+    Collection,
+    Deserializer,
+    Error,
+    Field,
+    Object,
+    Result,
+    Schema,
+    Variant,
+    VariantShape,
+};
+use serde::Deserialize as _;
+use serde_json::{
+    Map,
+    Value,
+};
+use std::convert::TryFrom;
+
+/// This selects how encoded bytes are interpreted when transcoding them to
+/// and from JSON with [`to_json`] and [`from_json`].
+///
+/// [`to_json`]: fn.to_json.html
+/// [`from_json`]: fn.from_json.html
+#[derive(Clone, Copy, Debug)]
+pub enum JsonLayout<'a> {
+    /// The bytes hold an [`Object`], whose encoding tags each value with
+    /// its type, so that no other description of it is needed.
+    ///
+    /// Objects are written as the JSON values closest to them, with IP
+    /// addresses as strings, vectors of integers as arrays, and collections
+    /// as objects.  JSON is read back as the simplest object which holds
+    /// it: whole numbers as integers if they fit, or else unsigned integers
+    /// or decimals, strings as strings, and arrays as vectors.
+    ///
+    /// [`Object`]: enum.Object.html
+    Object,
+
+    /// The bytes hold a value encoded by [`to_bytes`], whose type has the
+    /// given schema.
+    ///
+    /// Values are written as `serde_json` writes the type they were encoded
+    /// from: structs as objects, sequences and tuples as arrays, bytes as
+    /// arrays of numbers, newtype structs as their contents, unit values as
+    /// `null`, and enum variants as their names, or as objects holding their
+    /// contents under their names.  Maps whose keys are strings or integers
+    /// are written as objects, and others as arrays of key-value pairs.
+    /// Fields missing from the end of an [`Appendable`] struct are left out,
+    /// and may be left out of JSON read back.
+    ///
+    /// [`to_bytes`]: fn.to_bytes.html
+    /// [`Appendable`]: struct.Appendable.html
+    Schema(&'a Schema),
+}
+
+// This is the layout of the keys of a map, which decides whether the map
+// is written as a JSON object.
+enum KeyKind {
+    Text,
+    Integer,
+    Other,
+}
+
+// This converts values between encoded bytes and JSON according to a
+// schema, keeping track of the named types which enclose the current
+// position, so that references to them can be followed, and of the path to
+// the current position in the JSON value, for reporting mismatches.
+struct Transcoder<'s> {
+    enclosing: Vec<&'s Schema>,
+    path: Vec<String>,
+}
+
+impl<'s> Transcoder<'s> {
+    fn new() -> Self {
+        Self {
+            enclosing: Vec::new(),
+            path: Vec::new(),
+        }
+    }
+
+    fn decode(
+        &mut self,
+        schema: &'s Schema,
+        de: &mut Deserializer,
+    ) -> Result<Value> {
+        let schema = self.enter(schema)?;
+        let result = self.decode_entered(schema, de);
+        self.enclosing.pop();
+        result
+    }
+
+    fn decode_entered(
+        &mut self,
+        schema: &'s Schema,
+        de: &mut Deserializer,
+    ) -> Result<Value> {
+        Ok(match schema {
+            Schema::Unit
+            | Schema::UnitStruct {
+                ..
+            } => Value::Null,
+            Schema::Bool => Value::from(bool::deserialize(de)?),
+            Schema::I8 => Value::from(i8::deserialize(de)?),
+            Schema::I16 => Value::from(i16::deserialize(de)?),
+            Schema::I32 => Value::from(i32::deserialize(de)?),
+            Schema::I64 => Value::from(i64::deserialize(de)?),
+            Schema::U8 => Value::from(u8::deserialize(de)?),
+            Schema::U16 => Value::from(u16::deserialize(de)?),
+            Schema::U32 => Value::from(u32::deserialize(de)?),
+            Schema::U64 => Value::from(u64::deserialize(de)?),
+            Schema::F32 => Value::from(f32::deserialize(de)?),
+            Schema::F64 => Value::from(f64::deserialize(de)?),
+            Schema::Char => Value::from(char::deserialize(de)?.to_string()),
+            Schema::Str => Value::from(<&str>::deserialize(de)?),
+            Schema::Bytes => Value::from(<&[u8]>::deserialize(de)?),
+            Schema::Option(value) => {
+                if u8::deserialize(&mut *de)? == 0 {
+                    Value::Null
+                } else {
+                    self.decode(value, de)?
+                }
+            },
+            Schema::Seq(element) => {
+                let count = usize::deserialize(&mut *de)?;
+                let mut elements = Vec::new();
+                for _ in 0..count {
+                    elements.push(self.decode(element, de)?);
+                }
+                Value::Array(elements)
+            },
+            Schema::Map {
+                key,
+                value,
+            } => self.decode_map(key, value, de)?,
+            Schema::Tuple(schemas)
+            | Schema::TupleStruct {
+                fields: schemas,
+                ..
+            } => Value::Array(
+                schemas
+                    .iter()
+                    .map(|schema| self.decode(schema, de))
+                    .collect::<Result<_>>()?,
+            ),
+            Schema::NewtypeStruct {
+                name,
+                value,
+            } => {
+                if name == appendable::NAME {
+                    if let Some(fields) = self.struct_fields(value) {
+                        return self.decode_appendable(value, fields, de);
+                    }
+                } else if is_opaque(name) {
+                    return Err(Error::TypeUnknown);
+                }
+                self.decode(value, de)?
+            },
+            Schema::Struct {
+                fields,
+                ..
+            } => Value::Object(self.decode_fields(fields, fields.len(), de)?),
+            Schema::Enum {
+                variants,
+                ..
+            } => {
+                let index = u64::deserialize(&mut *de)?;
+                let variant = variants
+                    .iter()
+                    .find(|variant| u64::from(variant.index) == index)
+                    .ok_or(Error::UnknownVariant(index))?;
+                let contents = match &variant.shape {
+                    VariantShape::Unit => {
+                        return Ok(Value::from(variant.name.as_str()))
+                    },
+                    VariantShape::Newtype(schema) => self.decode(schema, de)?,
+                    VariantShape::Tuple(schemas) => Value::Array(
+                        schemas
+                            .iter()
+                            .map(|schema| self.decode(schema, de))
+                            .collect::<Result<_>>()?,
+                    ),
+                    VariantShape::Struct(fields) => Value::Object(
+                        self.decode_fields(fields, fields.len(), de)?,
+                    ),
+                };
+                let mut object = Map::new();
+                object.insert(variant.name.clone(), contents);
+                Value::Object(object)
+            },
+            Schema::Ref {
+                ..
+            } => return Err(Error::TypeUnknown),
+        })
+    }
+
+    // Decode a struct with the given schema and fields in the appendable
+    // layout, leaving out any fields it lacks and skipping any extra ones.
+    fn decode_appendable(
+        &mut self,
+        schema: &'s Schema,
+        fields: &'s [Field],
+        de: &mut Deserializer,
+    ) -> Result<Value> {
+        let mut body = Deserializer::new(<&[u8]>::deserialize(de)?);
+        let count = usize::deserialize(&mut body)?;
+        self.enter(schema)?;
+        let result = self.decode_fields(fields, count, &mut body);
+        self.enclosing.pop();
+        Ok(Value::Object(result?))
+    }
+
+    fn decode_map(
+        &mut self,
+        key: &'s Schema,
+        value: &'s Schema,
+        de: &mut Deserializer,
+    ) -> Result<Value> {
+        let count = usize::deserialize(&mut *de)?;
+        let object = !matches!(self.key_kind(key), KeyKind::Other);
+        let mut pairs = Vec::new();
+        for _ in 0..count {
+            let key = self.decode(key, de)?;
+            pairs.push((key, self.decode(value, de)?));
+        }
+        Ok(if object {
+            Value::Object(
+                pairs
+                    .into_iter()
+                    .map(|(key, value)| match key {
+                        Value::String(key) => (key, value),
+                        key => (key.to_string(), value),
+                    })
+                    .collect(),
+            )
+        } else {
+            Value::Array(
+                pairs
+                    .into_iter()
+                    .map(|(key, value)| Value::Array(vec![key, value]))
+                    .collect(),
+            )
+        })
+    }
+
+    fn decode_fields(
+        &mut self,
+        fields: &'s [Field],
+        count: usize,
+        de: &mut Deserializer,
+    ) -> Result<Map<String, Value>> {
+        fields
+            .iter()
+            .take(count)
+            .map(|field| {
+                Ok((field.name.clone(), self.decode(&field.schema, de)?))
+            })
+            .collect()
+    }
+
+    fn encode(
+        &mut self,
+        schema: &'s Schema,
+        json: &Value,
+        out: &mut Vec<u8>,
+    ) -> Result<()> {
+        let schema = self.enter(schema)?;
+        let result = self.encode_entered(schema, json, out);
+        self.enclosing.pop();
+        result
+    }
+
+    fn encode_entered(
+        &mut self,
+        schema: &'s Schema,
+        json: &Value,
+        out: &mut Vec<u8>,
+    ) -> Result<()> {
+        match (schema, json) {
+            (
+                Schema::Unit
+                | Schema::UnitStruct {
+                    ..
+                },
+                Value::Null,
+            ) => Ok(()),
+            (Schema::Bool, Value::Bool(value)) => to_bytes_into(value, out),
+            (Schema::I8, _) => self.encode_integer::<i8>(json, out),
+            (Schema::I16, _) => self.encode_integer::<i16>(json, out),
+            (Schema::I32, _) => self.encode_integer::<i32>(json, out),
+            (Schema::I64, _) => self.encode_integer::<i64>(json, out),
+            (Schema::U8, _) => self.encode_integer::<u8>(json, out),
+            (Schema::U16, _) => self.encode_integer::<u16>(json, out),
+            (Schema::U32, _) => self.encode_integer::<u32>(json, out),
+            (Schema::U64, _) => self.encode_integer::<u64>(json, out),
+            #[allow(clippy::cast_possible_truncation)]
+            (Schema::F32, Value::Number(value)) => to_bytes_into(
+                &(value.as_f64().ok_or_else(|| self.mismatch())? as f32),
+                out,
+            ),
+            (Schema::F64, Value::Number(value)) => to_bytes_into(
+                &value.as_f64().ok_or_else(|| self.mismatch())?,
+                out,
+            ),
+            (Schema::Char, Value::String(value)) => {
+                let mut chars = value.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(value), None) => to_bytes_into(&value, out),
+                    _ => Err(self.mismatch()),
+                }
+            },
+            (Schema::Str, Value::String(value)) => to_bytes_into(value, out),
+            (Schema::Bytes, Value::Array(elements)) => {
+                self.encode_bytes(elements, out)
+            },
+            (Schema::Option(_), Value::Null) => {
+                out.push(0);
+                Ok(())
+            },
+            (Schema::Option(value), _) => {
+                out.push(1);
+                self.encode(value, json, out)
+            },
+            (Schema::Seq(element), Value::Array(elements)) => {
+                to_bytes_into(&elements.len(), out)?;
+                self.encode_elements(
+                    std::iter::repeat(&**element),
+                    elements,
+                    out,
+                )
+            },
+            (
+                Schema::Map {
+                    key,
+                    value,
+                },
+                _,
+            ) => self.encode_map(key, value, json, out),
+            (
+                Schema::Tuple(schemas)
+                | Schema::TupleStruct {
+                    fields: schemas,
+                    ..
+                },
+                Value::Array(elements),
+            ) if schemas.len() == elements.len() => {
+                self.encode_elements(schemas.iter(), elements, out)
+            },
+            (
+                Schema::NewtypeStruct {
+                    name,
+                    value,
+                },
+                _,
+            ) => self.encode_newtype(name, value, json, out),
+            (
+                Schema::Struct {
+                    fields,
+                    ..
+                },
+                Value::Object(object),
+            ) => self.encode_fields(fields, object, false, out),
+            (
+                Schema::Enum {
+                    variants,
+                    ..
+                },
+                _,
+            ) => self.encode_variant(variants, json, out),
+            _ => Err(self.mismatch()),
+        }
+    }
+
+    // Encode a newtype struct, which is either the contents alone or, for
+    // an appendable struct, the fields preceded by their length and number.
+    fn encode_newtype(
+        &mut self,
+        name: &str,
+        value: &'s Schema,
+        json: &Value,
+        out: &mut Vec<u8>,
+    ) -> Result<()> {
+        if name == appendable::NAME {
+            if let (Some(fields), Value::Object(object)) =
+                (self.struct_fields(value), json)
+            {
+                let mut body = Vec::new();
+                self.enter(value)?;
+                let result =
+                    self.encode_fields(fields, object, true, &mut body);
+                self.enclosing.pop();
+                result?;
+                to_bytes_into(&body.len(), out)?;
+                out.extend_from_slice(&body);
+                return Ok(());
+            }
+        } else if is_opaque(name) {
+            return Err(Error::TypeUnknown);
+        }
+        self.encode(value, json, out)
+    }
+
+    fn encode_variant(
+        &mut self,
+        variants: &'s [Variant],
+        json: &Value,
+        out: &mut Vec<u8>,
+    ) -> Result<()> {
+        let (name, contents) = match json {
+            Value::String(name) => (name, None),
+            Value::Object(object) if object.len() == 1 => {
+                let (name, contents) =
+                    object.iter().next().ok_or_else(|| self.mismatch())?;
+                (name, Some(contents))
+            },
+            _ => return Err(self.mismatch()),
+        };
+        let variant = variants
+            .iter()
+            .find(|variant| variant.name == *name)
+            .ok_or_else(|| self.mismatch())?;
+        to_bytes_into(&variant.index, out)?;
+        self.path.push(format!(".{name}"));
+        let result = match (&variant.shape, contents) {
+            (VariantShape::Unit, None | Some(Value::Null)) => Ok(()),
+            (VariantShape::Newtype(schema), Some(contents)) => {
+                self.encode(schema, contents, out)
+            },
+            (VariantShape::Tuple(schemas), Some(Value::Array(elements)))
+                if schemas.len() == elements.len() =>
+            {
+                self.encode_elements(schemas.iter(), elements, out)
+            },
+            (VariantShape::Struct(fields), Some(Value::Object(object))) => {
+                self.encode_fields(fields, object, false, out)
+            },
+            _ => Err(self.mismatch()),
+        };
+        self.path.pop();
+        result
+    }
+
+    fn encode_bytes(
+        &self,
+        elements: &[Value],
+        out: &mut Vec<u8>,
+    ) -> Result<()> {
+        let bytes = elements
+            .iter()
+            .map(|element| {
+                element
+                    .as_u64()
+                    .and_then(|byte| u8::try_from(byte).ok())
+                    .ok_or_else(|| self.mismatch())
+            })
+            .collect::<Result<Vec<u8>>>()?;
+        to_bytes_into(&bytes.len(), out)?;
+        out.extend_from_slice(&bytes);
+        Ok(())
+    }
+
+    fn encode_elements<I>(
+        &mut self,
+        schemas: I,
+        elements: &[Value],
+        out: &mut Vec<u8>,
+    ) -> Result<()>
+    where
+        I: Iterator<Item = &'s Schema>,
+    {
+        for (index, (schema, element)) in schemas.zip(elements).enumerate() {
+            self.path.push(format!("[{index}]"));
+            self.encode(schema, element, out)?;
+            self.path.pop();
+        }
+        Ok(())
+    }
+
+    // Encode the fields of a struct from the given JSON object.  The fields
+    // of an appendable struct are preceded by their number, and may stop
+    // at the first one missing from the object.
+    fn encode_fields(
+        &mut self,
+        fields: &'s [Field],
+        object: &Map<String, Value>,
+        appendable: bool,
+        out: &mut Vec<u8>,
+    ) -> Result<()> {
+        let count = fields
+            .iter()
+            .take_while(|field| object.contains_key(&field.name))
+            .count();
+        if let Some(name) = object
+            .keys()
+            .find(|name| !fields.iter().any(|field| field.name == **name))
+        {
+            self.path.push(format!(".{name}"));
+            return Err(self.mismatch());
+        }
+        if count < fields.len() && (!appendable || object.len() > count) {
+            self.path.push(format!(".{}", fields[count].name));
+            return Err(self.mismatch());
+        }
+        if appendable {
+            to_bytes_into(&count, out)?;
+        }
+        for field in &fields[..count] {
+            self.path.push(format!(".{}", field.name));
+            self.encode(&field.schema, &object[&field.name], out)?;
+            self.path.pop();
+        }
+        Ok(())
+    }
+
+    fn encode_integer<T>(
+        &self,
+        json: &Value,
+        out: &mut Vec<u8>,
+    ) -> Result<()>
+    where
+        T: serde::Serialize + TryFrom<i64> + TryFrom<u64>,
+    {
+        let value = match json {
+            Value::Number(number) => number
+                .as_i64()
+                .and_then(|value| T::try_from(value).ok())
+                .or_else(|| {
+                    number.as_u64().and_then(|value| T::try_from(value).ok())
+                }),
+            _ => None,
+        };
+        to_bytes_into(&value.ok_or_else(|| self.mismatch())?, out)
+    }
+
+    fn encode_map(
+        &mut self,
+        key: &'s Schema,
+        value: &'s Schema,
+        json: &Value,
+        out: &mut Vec<u8>,
+    ) -> Result<()> {
+        match (self.key_kind(key), json) {
+            (KeyKind::Text, Value::Object(object)) => {
+                to_bytes_into(&object.len(), out)?;
+                for (name, element) in object {
+                    self.path.push(format!(".{name}"));
+                    self.encode(key, &Value::from(name.as_str()), out)?;
+                    self.encode(value, element, out)?;
+                    self.path.pop();
+                }
+                Ok(())
+            },
+            (KeyKind::Integer, Value::Object(object)) => {
+                to_bytes_into(&object.len(), out)?;
+                for (name, element) in object {
+                    self.path.push(format!(".{name}"));
+                    let number = name
+                        .parse::<i64>()
+                        .map(Value::from)
+                        .or_else(|_| name.parse::<u64>().map(Value::from))
+                        .map_err(|_| self.mismatch())?;
+                    self.encode(key, &number, out)?;
+                    self.encode(value, element, out)?;
+                    self.path.pop();
+                }
+                Ok(())
+            },
+            (KeyKind::Other, Value::Array(pairs)) => {
+                to_bytes_into(&pairs.len(), out)?;
+                for (index, pair) in pairs.iter().enumerate() {
+                    self.path.push(format!("[{index}]"));
+                    match pair.as_array().map(Vec::as_slice) {
+                        Some([element_key, element]) => {
+                            self.encode(key, element_key, out)?;
+                            self.encode(value, element, out)?;
+                        },
+                        _ => return Err(self.mismatch()),
+                    }
+                    self.path.pop();
+                }
+                Ok(())
+            },
+            _ => Err(self.mismatch()),
+        }
+    }
+
+    // Follow the given schema if it is a reference, and note it as an
+    // enclosing type until the matching `pop` of `enclosing`.
+    fn enter(
+        &mut self,
+        schema: &'s Schema,
+    ) -> Result<&'s Schema> {
+        let schema = self.resolve(schema).ok_or(Error::TypeUnknown)?;
+        self.enclosing.push(schema);
+        Ok(schema)
+    }
+
+    fn key_kind(
+        &self,
+        schema: &'s Schema,
+    ) -> KeyKind {
+        match self.resolve(schema) {
+            Some(Schema::Str | Schema::Char) => KeyKind::Text,
+            Some(
+                Schema::I8
+                | Schema::I16
+                | Schema::I32
+                | Schema::I64
+                | Schema::U8
+                | Schema::U16
+                | Schema::U32
+                | Schema::U64,
+            ) => KeyKind::Integer,
+            Some(Schema::NewtypeStruct {
+                name,
+                value,
+            }) if !is_opaque(name) => self.key_kind(value),
+            _ => KeyKind::Other,
+        }
+    }
+
+    fn mismatch(&self) -> Error {
+        Error::JsonMismatch(format!("${}", self.path.concat()))
+    }
+
+    fn resolve(
+        &self,
+        schema: &'s Schema,
+    ) -> Option<&'s Schema> {
+        match schema {
+            Schema::Ref {
+                name,
+            } => {
+                self.enclosing.iter().rev().copied().find(|enclosing| {
+                    type_name(enclosing) == Some(name.as_str())
+                })
+            },
+            _ => Some(schema),
+        }
+    }
+
+    // Return the fields of the given schema, following references, if it
+    // is that of a struct.
+    fn struct_fields(
+        &self,
+        schema: &'s Schema,
+    ) -> Option<&'s [Field]> {
+        match self.resolve(schema)? {
+            Schema::Struct {
+                fields,
+                ..
+            } => Some(fields),
+            _ => None,
+        }
+    }
+}
+
+// Return whether the newtype struct with the given name has a layout of its
+// own which cannot be transcoded without the type of its contents.
+fn is_opaque(name: &str) -> bool {
+    name == shared::NAME || borrowed_slice::alignment(name).is_some()
+}
+
+fn object_to_json(object: &Object) -> Value {
+    match object {
+        Object::Empty => Value::Null,
+        Object::Boolean(value) => Value::from(*value),
+        Object::Integer(value) => Value::from(*value),
+        Object::UnsignedInteger(value) => Value::from(*value),
+        Object::Decimal(value) => Value::from(*value),
+        Object::String(value) => Value::from(value.as_str()),
+        Object::Vector(value) => value.iter().map(object_to_json).collect(),
+        Object::Collection(value) => Value::Object(
+            value
+                .iter()
+                .map(|(name, object)| {
+                    (String::from(name), object_to_json(object))
+                })
+                .collect(),
+        ),
+        Object::IpAddress(value) => Value::from(value.to_string()),
+        Object::IntegerVector(value) => Value::from(value.as_slice()),
+        Object::UnsignedIntegerVector(value) => Value::from(value.as_slice()),
+    }
+}
+
+fn object_from_json(json: &Value) -> Result<Object> {
+    Ok(match json {
+        Value::Null => Object::Empty,
+        Value::Bool(value) => Object::Boolean(*value),
+        Value::Number(number) => {
+            if let Some(value) =
+                number.as_i64().and_then(|value| i32::try_from(value).ok())
+            {
+                Object::Integer(value)
+            } else if let Some(value) = number.as_u64() {
+                Object::UnsignedInteger(value)
+            } else {
+                Object::Decimal(number.as_f64().unwrap_or_default())
+            }
+        },
+        Value::String(value) => Object::String(value.clone()),
+        Value::Array(elements) => Object::Vector(
+            elements.iter().map(object_from_json).collect::<Result<_>>()?,
+        ),
+        Value::Object(object) => {
+            let mut collection = Collection::new();
+            for (name, element) in object {
+                collection
+                    .set_object(name.as_str(), object_from_json(element)?);
+            }
+            Object::Collection(collection)
+        },
+    })
+}
+
+/// This function is used to transcode the encoding of a value to JSON,
+/// interpreting it according to the given layout, without needing the
+/// type of the value.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate serialization;
+/// # extern crate serde;
+/// # extern crate serde_json;
+/// use serialization::JsonLayout;
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct Point {
+///     x: i32,
+///     label: Option<String>,
+/// }
+///
+/// let encoding = serialization::to_bytes(&Point {
+///     x: -3,
+///     label: Some(String::from("origin")),
+/// })
+/// .unwrap();
+/// let schema = serialization::schema_of::<Point>().unwrap();
+/// let json =
+///     serialization::to_json(&encoding, JsonLayout::Schema(&schema)).unwrap();
+/// assert_eq!(serde_json::json!({"x": -3, "label": "origin"}), json);
+/// assert_eq!(
+///     encoding,
+///     serialization::from_json(&json, JsonLayout::Schema(&schema)).unwrap()
+/// );
+/// ```
+///
+/// # Errors
+///
+/// [`Error::TypeUnknown`] is returned if the schema refers to a type which
+/// does not enclose the reference, or has a [`Shared`] value or
+/// [`BorrowedSlice`], which cannot be transcoded.  Otherwise this function
+/// returns the same errors as [`from_bytes`].
+///
+/// [`from_bytes`]: fn.from_bytes.html
+/// [`Shared`]: struct.Shared.html
+/// [`BorrowedSlice`]: struct.BorrowedSlice.html
+/// [`Error::TypeUnknown`]: enum.Error.html#variant.TypeUnknown
+pub fn to_json(
+    bytes: &[u8],
+    layout: JsonLayout,
+) -> Result<Value> {
+    match layout {
+        JsonLayout::Object => Ok(object_to_json(&from_bytes(bytes)?)),
+        JsonLayout::Schema(schema) => {
+            Transcoder::new().decode(schema, &mut Deserializer::new(bytes))
+        },
+    }
+}
+
+/// This function is used to encode a JSON value, interpreting it according
+/// to the given layout, without needing the type of the value.  The result
+/// is the same as encoding the value decoded from the JSON by `serde_json`
+/// with [`to_bytes`].
+///
+/// # Errors
+///
+/// [`Error::JsonMismatch`] is returned if the JSON value does not fit the
+/// schema.  [`Error::TypeUnknown`] is returned for the same schemas as for
+/// [`to_json`].
+///
+/// [`to_bytes`]: fn.to_bytes.html
+/// [`to_json`]: fn.to_json.html
+/// [`Error::JsonMismatch`]: enum.Error.html#variant.JsonMismatch
+/// [`Error::TypeUnknown`]: enum.Error.html#variant.TypeUnknown
+pub fn from_json(
+    json: &Value,
+    layout: JsonLayout,
+) -> Result<Vec<u8>> {
+    match layout {
+        JsonLayout::Object => to_bytes(&object_from_json(json)?),
+        JsonLayout::Schema(schema) => {
+            let mut out = Vec::new();
+            Transcoder::new().encode(schema, json, &mut out)?;
+            Ok(out)
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        schema_of,
+        Appendable,
+    };
+    use serde_json::json;
+    use std::{
+        collections::{
+            BTreeMap,
+            HashMap,
+        },
+        net::Ipv4Addr,
+    };
+
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    enum Shape {
+        Empty,
+        Circle(f64),
+        Line(i16, i16),
+        Box {
+            width: u8,
+            height: u8,
+        },
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    struct Id(String);
+
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    struct Drawing {
+        id: Id,
+        initial: char,
+        shapes: Vec<Shape>,
+        layers: BTreeMap<u32, String>,
+        origins: Vec<((i8, i8), bool)>,
+        thumbnail: Option<serde_bytes_like::Bytes>,
+        children: Vec<Drawing>,
+    }
+
+    // This stands in for `serde_bytes`, so that a field is encoded as
+    // bytes rather than as a sequence of numbers.
+    mod serde_bytes_like {
+        #[derive(PartialEq, Debug)]
+        pub struct Bytes(pub Vec<u8>);
+
+        impl serde::Serialize for Bytes {
+            fn serialize<S>(
+                &self,
+                serializer: S,
+            ) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                serializer.serialize_bytes(&self.0)
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for Bytes {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                <&[u8]>::deserialize(deserializer)
+                    .map(|bytes| Self(bytes.to_vec()))
+            }
+        }
+    }
+
+    fn drawing() -> Drawing {
+        Drawing {
+            id: Id(String::from("d1")),
+            initial: 'é',
+            shapes: vec![
+                Shape::Empty,
+                Shape::Circle(1.5),
+                Shape::Line(-1, 300),
+                Shape::Box {
+                    width: 2,
+                    height: 3,
+                },
+            ],
+            layers: vec![
+                (1, String::from("base")),
+                (70000, String::from("top")),
+            ]
+            .into_iter()
+            .collect(),
+            origins: vec![((1, -1), true)],
+            thumbnail: Some(serde_bytes_like::Bytes(vec![0, 255])),
+            children: vec![Drawing {
+                id: Id(String::from("d2")),
+                initial: 'x',
+                shapes: Vec::new(),
+                layers: BTreeMap::new(),
+                origins: Vec::new(),
+                thumbnail: None,
+                children: Vec::new(),
+            }],
+        }
+    }
+
+    fn drawing_json() -> Value {
+        json!({
+            "id": "d1",
+            "initial": "é",
+            "shapes": ["Empty", {"Circle": 1.5}, {"Line": [-1, 300]}, {"Box": {"width": 2, "height": 3}}],
+            "layers": {"1": "base", "70000": "top"},
+            "origins": [[[1, -1], true]],
+            "thumbnail": [0, 255],
+            "children": [{
+                "id": "d2",
+                "initial": "x",
+                "shapes": [],
+                "layers": {},
+                "origins": [],
+                "thumbnail": null,
+                "children": [],
+            }],
+        })
+    }
+
+    #[test]
+    fn schema_to_json() {
+        let schema = schema_of::<Drawing>().unwrap();
+        let encoding = to_bytes(&drawing()).unwrap();
+        let json = to_json(&encoding, JsonLayout::Schema(&schema));
+        assert!(json.is_ok());
+        let json = json.unwrap();
+        assert_eq!(drawing_json(), json);
+    }
+
+    #[test]
+    fn schema_from_json() {
+        let schema = schema_of::<Drawing>().unwrap();
+        let encoding = from_json(&drawing_json(), JsonLayout::Schema(&schema));
+        assert!(encoding.is_ok());
+        let encoding = encoding.unwrap();
+        assert_eq!(to_bytes(&drawing()).unwrap(), encoding);
+    }
+
+    #[test]
+    fn schema_maps_with_other_keys() {
+        let value: HashMap<(u8, u8), bool> =
+            std::iter::once(((1, 2), true)).collect();
+        let schema = schema_of::<HashMap<(u8, u8), bool>>().unwrap();
+        let encoding = to_bytes(&value).unwrap();
+        let json = to_json(&encoding, JsonLayout::Schema(&schema)).unwrap();
+        assert_eq!(json!([[[1, 2], true]]), json);
+        assert_eq!(
+            encoding,
+            from_json(&json, JsonLayout::Schema(&schema)).unwrap()
+        );
+    }
+
+    #[test]
+    fn schema_appendable_fields() {
+        #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+        struct Settings {
+            name: String,
+            #[serde(default)]
+            level: u8,
+        }
+        let schema = schema_of::<Appendable<Settings>>().unwrap();
+        let value = Appendable(Settings {
+            name: String::from("a"),
+            level: 4,
+        });
+        let encoding = to_bytes(&value).unwrap();
+        let json = to_json(&encoding, JsonLayout::Schema(&schema)).unwrap();
+        assert_eq!(json!({"name": "a", "level": 4}), json);
+        assert_eq!(
+            encoding,
+            from_json(&json, JsonLayout::Schema(&schema)).unwrap()
+        );
+        let encoding =
+            from_json(&json!({"name": "a"}), JsonLayout::Schema(&schema))
+                .unwrap();
+        assert_eq!(&[3, 1, 1, b'a'][..], encoding);
+        assert_eq!(
+            json!({"name": "a"}),
+            to_json(&encoding, JsonLayout::Schema(&schema)).unwrap()
+        );
+    }
+
+    #[test]
+    fn schema_mismatch() {
+        let schema = schema_of::<Drawing>().unwrap();
+        let mut json = drawing_json();
+        json["shapes"][2]["Line"][1] = json!(70000);
+        assert!(matches!(
+            from_json(&json, JsonLayout::Schema(&schema)),
+            Err(Error::JsonMismatch(path)) if path == "$.shapes[2].Line[1]"
+        ));
+        let mut json = drawing_json();
+        json["children"][0]["colour"] = json!("red");
+        assert!(matches!(
+            from_json(&json, JsonLayout::Schema(&schema)),
+            Err(Error::JsonMismatch(path)) if path == "$.children[0].colour"
+        ));
+        let mut json = drawing_json();
+        json.as_object_mut().unwrap().remove("origins");
+        assert!(matches!(
+            from_json(&json, JsonLayout::Schema(&schema)),
+            Err(Error::JsonMismatch(path)) if path == "$.origins"
+        ));
+    }
+
+    #[test]
+    fn object_round_trip() {
+        let mut collection = Collection::new();
+        collection.set_object("count", Object::Integer(-7));
+        collection.set_object("big", Object::UnsignedInteger(1 << 40));
+        collection.set_object("ratio", Object::Decimal(0.25));
+        collection.set_object(
+            "items",
+            Object::Vector(vec![
+                Object::from("a"),
+                Object::Empty,
+                Object::Boolean(true),
+            ]),
+        );
+        let encoding = to_bytes(&Object::Collection(collection)).unwrap();
+        let json = to_json(&encoding, JsonLayout::Object);
+        assert!(json.is_ok());
+        let json = json.unwrap();
+        assert_eq!(
+            json!({
+                "count": -7,
+                "big": 1_u64 << 40,
+                "ratio": 0.25,
+                "items": ["a", null, true],
+            }),
+            json
+        );
+        assert_eq!(encoding, from_json(&json, JsonLayout::Object).unwrap());
+    }
+
+    #[test]
+    fn object_lossy_types() {
+        let object = Object::Vector(vec![
+            Object::IpAddress(Ipv4Addr::new(10, 0, 0, 1)),
+            Object::UnsignedIntegerVector(vec![1, 2]),
+        ]);
+        let json =
+            to_json(&to_bytes(&object).unwrap(), JsonLayout::Object).unwrap();
+        assert_eq!(json!(["10.0.0.1", [1, 2]]), json);
+        let object: Object =
+            from_bytes(&from_json(&json, JsonLayout::Object).unwrap()).unwrap();
+        assert_eq!(
+            Object::Vector(vec![
+                Object::from("10.0.0.1"),
+                Object::Vector(vec![Object::Integer(1), Object::Integer(2)]),
+            ]),
+            object
+        );
+    }
+}
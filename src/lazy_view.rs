@@ -0,0 +1,442 @@
+use super::{
+    appendable,
+    borrowed_slice,
+    schema::type_name,
+    shared,
+    Deserializer,
+    Error,
+    Result,
+    Schema,
+};
+use serde::Deserialize as _;
+
+/// This is a value encoded by [`to_bytes_indexed`], viewed in place, so that
+/// the fields of its structs and the elements of its sequences can be found
+/// without decoding any of the others.
+///
+/// A view is made from the [`Schema`] of the encoded type, which gives the
+/// names and layouts of its fields.  Each step through a struct or sequence
+/// reads only its table of offsets, and the value finally reached can be
+/// decoded, borrowing strings and bytes from the encoding.
+///
+/// An [`Appendable`] struct is viewed as the struct inside it, and
+/// newtype structs as the values inside them.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate serialization;
+/// # extern crate serde;
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct Item {
+///     id: u32,
+///     name: String,
+/// }
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct Record {
+///     items: Vec<Item>,
+/// }
+///
+/// let record = Record {
+///     items: (0..2000)
+///         .map(|id| Item {
+///             id,
+///             name: format!("item {}", id),
+///         })
+///         .collect(),
+/// };
+/// let encoding = serialization::to_bytes_indexed(&record).unwrap();
+/// let schema = serialization::schema_of::<Record>().unwrap();
+/// let view = serialization::LazyView::new(&schema, &encoding).unwrap();
+/// let name: &str = view
+///     .field("items")
+///     .and_then(|items| items.element(1000))
+///     .and_then(|item| item.field("name"))
+///     .and_then(|name| name.decode())
+///     .unwrap();
+/// assert_eq!("item 1000", name);
+/// ```
+///
+/// [`to_bytes_indexed`]: fn.to_bytes_indexed.html
+/// [`Schema`]: enum.Schema.html
+/// [`Appendable`]: struct.Appendable.html
+#[derive(Clone, Debug)]
+pub struct LazyView<'a> {
+    schema: &'a Schema,
+    bytes: &'a [u8],
+    enclosing: Vec<&'a Schema>,
+}
+
+impl<'a> LazyView<'a> {
+    /// Return a view of the value at the start of the given bytes, which
+    /// was encoded by [`to_bytes_indexed`] from a value of the type with
+    /// the given schema.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::ValueTruncated`] is returned if the value is an
+    /// [`Appendable`] struct whose length runs past the end of the bytes.
+    ///
+    /// [`to_bytes_indexed`]: fn.to_bytes_indexed.html
+    /// [`Appendable`]: struct.Appendable.html
+    /// [`Error::ValueTruncated`]: enum.Error.html#variant.ValueTruncated
+    pub fn new(
+        schema: &'a Schema,
+        bytes: &'a [u8],
+    ) -> Result<Self> {
+        Self::resolve(schema, bytes, Vec::new())
+    }
+
+    /// Decode the viewed value as the given type, which should be the one
+    /// whose schema describes it.
+    ///
+    /// # Errors
+    ///
+    /// This function returns the same errors as [`from_bytes_indexed`].
+    ///
+    /// [`from_bytes_indexed`]: fn.from_bytes_indexed.html
+    pub fn decode<T>(&self) -> Result<T>
+    where
+        T: serde::Deserialize<'a>,
+    {
+        T::deserialize(&mut Deserializer::new_indexed(self.bytes))
+    }
+
+    /// Return a view of the field at the given position in the viewed
+    /// struct, or of the element at the given position in the viewed
+    /// sequence.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::ElementsUnknown`] is returned if the viewed value is not a
+    /// struct or sequence, and [`Error::IndexOutOfRange`] if it has no
+    /// field or element at the given position.
+    ///
+    /// [`Error::ElementsUnknown`]: enum.Error.html#variant.ElementsUnknown
+    /// [`Error::IndexOutOfRange`]: enum.Error.html#variant.IndexOutOfRange
+    pub fn element(
+        &self,
+        index: usize,
+    ) -> Result<Self> {
+        let schema = match self.schema {
+            Schema::Seq(element) => element,
+            Schema::Struct {
+                fields,
+                ..
+            } => {
+                &fields.get(index).ok_or(Error::IndexOutOfRange(index))?.schema
+            },
+            _ => return Err(Error::ElementsUnknown),
+        };
+        self.nth(index, schema)
+    }
+
+    /// Return a view of the field with the given name in the viewed struct.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::FieldUnknown`] is returned if the viewed value is not a
+    /// struct with a field of the given name, or if it was encoded
+    /// without that field.
+    ///
+    /// [`Error::FieldUnknown`]: enum.Error.html#variant.FieldUnknown
+    pub fn field(
+        &self,
+        name: &str,
+    ) -> Result<Self> {
+        let unknown = || Error::FieldUnknown(String::from(name));
+        let Schema::Struct {
+            fields,
+            ..
+        } = self.schema
+        else {
+            return Err(unknown());
+        };
+        let index = fields
+            .iter()
+            .position(|field| field.name == name)
+            .ok_or_else(unknown)?;
+        self.nth(index, &fields[index].schema).map_err(|error| match error {
+            Error::IndexOutOfRange(_) => unknown(),
+            error => error,
+        })
+    }
+
+    /// Return `true` if the viewed struct or sequence has no fields or
+    /// elements.
+    ///
+    /// # Errors
+    ///
+    /// This function returns the same errors as [`len`].
+    ///
+    /// [`len`]: #method.len
+    pub fn is_empty(&self) -> Result<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Return the number of fields in the viewed struct, or of elements in
+    /// the viewed sequence.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::ElementsUnknown`] is returned if the viewed value is not a
+    /// struct or sequence.
+    ///
+    /// [`Error::ElementsUnknown`]: enum.Error.html#variant.ElementsUnknown
+    pub fn len(&self) -> Result<usize> {
+        Ok(self.table()?.0.len() / 4)
+    }
+
+    /// Return the schema of the viewed value.
+    #[must_use]
+    pub fn schema(&self) -> &'a Schema {
+        self.schema
+    }
+
+    // Return a view of the element at the given position in the viewed
+    // struct or sequence, which has the given schema.  Its encoding ends
+    // at the offset in the table for its position and starts at the offset
+    // for the position before it.
+    fn nth(
+        &self,
+        index: usize,
+        schema: &'a Schema,
+    ) -> Result<Self> {
+        let (table, data) = self.table()?;
+        if index >= table.len() / 4 {
+            return Err(Error::IndexOutOfRange(index));
+        }
+        let offset = |index: usize| {
+            let mut offset = [0; 4];
+            offset.copy_from_slice(&table[index * 4..index * 4 + 4]);
+            u32::from_be_bytes(offset) as usize
+        };
+        let start = index.checked_sub(1).map_or(0, offset);
+        let bytes =
+            data.get(start..offset(index)).ok_or(Error::ValueTruncated)?;
+        Self::resolve(schema, bytes, self.enclosing.clone())
+    }
+
+    // Follow the given schema through references and newtype structs to
+    // the layout of the value encoded at the start of the given bytes,
+    // noting the types passed through as enclosing the value.  An
+    // appendable struct is viewed from the start of its fields.
+    fn resolve(
+        mut schema: &'a Schema,
+        mut bytes: &'a [u8],
+        mut enclosing: Vec<&'a Schema>,
+    ) -> Result<Self> {
+        let mut appendable = false;
+        loop {
+            enclosing.push(schema);
+            match schema {
+                Schema::Ref {
+                    name,
+                } => {
+                    schema = enclosing
+                        .iter()
+                        .rev()
+                        .copied()
+                        .find(|enclosing| {
+                            type_name(enclosing) == Some(name.as_str())
+                        })
+                        .ok_or(Error::TypeUnknown)?;
+                },
+                Schema::NewtypeStruct {
+                    name,
+                    value,
+                } if name != shared::NAME
+                    && borrowed_slice::alignment(name).is_none() =>
+                {
+                    appendable |= name == appendable::NAME;
+                    schema = value;
+                },
+                Schema::Struct {
+                    ..
+                } if appendable => {
+                    let mut deserializer = Deserializer::new(bytes);
+                    bytes = <&[u8]>::deserialize(&mut deserializer)?;
+                    break;
+                },
+                _ => break,
+            }
+        }
+        Ok(Self {
+            schema,
+            bytes,
+            enclosing,
+        })
+    }
+
+    // Return the table of offsets of the viewed struct or sequence, and
+    // the encoding of its elements which follows.
+    fn table(&self) -> Result<(&'a [u8], &'a [u8])> {
+        if !matches!(self.schema, Schema::Seq(_) | Schema::Struct { .. }) {
+            return Err(Error::ElementsUnknown);
+        }
+        let mut deserializer = Deserializer::new(self.bytes);
+        let count = usize::deserialize(&mut deserializer)?;
+        let rest = &self.bytes[deserializer.offset()..];
+        let len = count.checked_mul(4).ok_or(Error::ValueTruncated)?;
+        if len > rest.len() {
+            return Err(Error::ValueTruncated);
+        }
+        Ok(rest.split_at(len))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        schema_of,
+        to_bytes_indexed,
+        Appendable,
+    };
+    use std::collections::BTreeMap;
+
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    struct Item {
+        id: u32,
+        name: String,
+        tags: Vec<String>,
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    struct Record {
+        title: String,
+        items: Vec<Item>,
+        extra: Appendable<Extra>,
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    struct Extra {
+        counts: BTreeMap<String, u64>,
+        note: Option<String>,
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    struct Tree {
+        label: u8,
+        children: Vec<Tree>,
+    }
+
+    fn record() -> Record {
+        Record {
+            title: String::from("inventory"),
+            items: (0..2000)
+                .map(|id| Item {
+                    id,
+                    name: format!("item {id}"),
+                    tags: vec![String::from("a"); (id % 3) as usize],
+                })
+                .collect(),
+            extra: Appendable(Extra {
+                counts: std::iter::once((String::from("x"), 5)).collect(),
+                note: Some(String::from("fresh")),
+            }),
+        }
+    }
+
+    #[test]
+    fn view_nested_fields() {
+        let encoding = to_bytes_indexed(&record()).unwrap();
+        let schema = schema_of::<Record>().unwrap();
+        let view = LazyView::new(&schema, &encoding);
+        assert!(view.is_ok());
+        let view = view.unwrap();
+        assert_eq!(3, view.len().unwrap());
+        let items = view.field("items").unwrap();
+        assert_eq!(2000, items.len().unwrap());
+        let item = items.element(1000).unwrap();
+        let name: &str = item.field("name").unwrap().decode().unwrap();
+        assert_eq!("item 1000", name);
+        assert!(encoding.as_ptr_range().contains(&name.as_ptr()));
+        assert_eq!(
+            Item {
+                id: 1999,
+                name: String::from("item 1999"),
+                tags: vec![String::from("a")],
+            },
+            items.element(1999).unwrap().decode().unwrap()
+        );
+        let tag: &str = item
+            .field("tags")
+            .and_then(|tags| tags.element(0))
+            .and_then(|tag| tag.decode())
+            .unwrap();
+        assert_eq!("a", tag);
+        let title: String = view.element(0).unwrap().decode().unwrap();
+        assert_eq!("inventory", title);
+    }
+
+    #[test]
+    fn view_appendable_struct() {
+        let encoding = to_bytes_indexed(&record()).unwrap();
+        let schema = schema_of::<Record>().unwrap();
+        let view = LazyView::new(&schema, &encoding).unwrap();
+        let extra = view.field("extra").unwrap();
+        let note: Option<&str> = extra.field("note").unwrap().decode().unwrap();
+        assert_eq!(Some("fresh"), note);
+        let counts: BTreeMap<&str, u64> =
+            extra.field("counts").unwrap().decode().unwrap();
+        assert_eq!(Some(&5), counts.get("x"));
+    }
+
+    #[test]
+    fn view_recursive_type() {
+        let tree = Tree {
+            label: 0,
+            children: vec![
+                Tree {
+                    label: 1,
+                    children: Vec::new(),
+                },
+                Tree {
+                    label: 2,
+                    children: vec![Tree {
+                        label: 3,
+                        children: Vec::new(),
+                    }],
+                },
+            ],
+        };
+        let encoding = to_bytes_indexed(&tree).unwrap();
+        let schema = schema_of::<Tree>().unwrap();
+        let view = LazyView::new(&schema, &encoding).unwrap();
+        let label: u8 = view
+            .field("children")
+            .and_then(|children| children.element(1))
+            .and_then(|child| child.field("children"))
+            .and_then(|children| children.element(0))
+            .and_then(|child| child.field("label"))
+            .and_then(|label| label.decode())
+            .unwrap();
+        assert_eq!(3, label);
+    }
+
+    #[test]
+    fn view_missing_elements() {
+        let encoding = to_bytes_indexed(&record()).unwrap();
+        let schema = schema_of::<Record>().unwrap();
+        let view = LazyView::new(&schema, &encoding).unwrap();
+        assert!(matches!(
+            view.field("missing"),
+            Err(Error::FieldUnknown(name)) if name == "missing"
+        ));
+        assert!(matches!(
+            view.field("items").and_then(|items| items.element(2000)),
+            Err(Error::IndexOutOfRange(2000))
+        ));
+        assert!(matches!(
+            view.field("title").and_then(|title| title.element(0)),
+            Err(Error::ElementsUnknown)
+        ));
+        assert!(matches!(
+            LazyView::new(&schema, &encoding[..encoding.len() / 2])
+                .and_then(|view| view.field("extra")),
+            Err(Error::ValueTruncated)
+        ));
+    }
+}
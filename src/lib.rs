@@ -22,19 +22,125 @@
 #![warn(clippy::pedantic)]
 #![warn(missing_docs)]
 
+// This lets code generated by the derive macros refer to this crate by name
+// when it is used within this crate.
+extern crate self as serialization;
+
+mod appendable;
+mod borrowed_slice;
+#[cfg(feature = "capi")]
+pub mod capi;
+mod checksum;
+mod collection;
+mod compatibility;
 mod de;
+mod encoded_set;
 mod error;
+mod int_seq;
+pub mod int_vec;
+pub mod ip_address;
+#[cfg(feature = "json")]
+mod json;
+mod lazy_view;
+mod object;
+mod packed;
+mod patch;
+#[cfg(feature = "mmap")]
+mod record_file;
+mod record_log;
+mod schema;
 mod ser;
+mod shared;
+mod tagged;
+pub mod uint_vec;
 
+pub use appendable::Appendable;
+pub use borrowed_slice::BorrowedSlice;
+pub use bytemuck::Pod;
+pub use checksum::{
+    from_bytes_checked,
+    from_bytes_checked_with,
+    to_bytes_checked,
+    to_bytes_checked_with,
+    Checksum,
+    Crc32c,
+};
+pub use collection::Collection;
+pub use compatibility::{
+    check_compatibility,
+    Change,
+    ChangeKind,
+    Compatibility,
+};
 pub use de::{
     from_bytes,
+    from_bytes_indexed,
+    from_bytes_interned,
+    from_bytes_into,
     Deserializer,
 };
+pub use encoded_set::EncodedSet;
 pub use error::{
     Error,
     Result,
 };
+#[cfg(feature = "json")]
+pub use json::{
+    from_json,
+    to_json,
+    JsonLayout,
+};
+pub use lazy_view::LazyView;
+pub use object::Object;
+pub use packed::{
+    Packed,
+    PackedElement,
+};
+pub use patch::{
+    apply,
+    diff,
+    Patch,
+};
+#[cfg(feature = "mmap")]
+pub use record_file::{
+    RecordFile,
+    Records,
+};
+pub use record_log::{
+    LogRecords,
+    RecordLog,
+    SyncPolicy,
+};
+pub use schema::{
+    schema_of,
+    Field,
+    Schema,
+    Variant,
+    VariantShape,
+};
 pub use ser::{
     to_bytes,
+    to_bytes_indexed,
+    to_bytes_interned,
+    to_bytes_into,
     Serializer,
 };
+#[cfg(feature = "derive")]
+pub use serialization_derive::{
+    Decode,
+    Encode,
+};
+pub use shared::Shared;
+
+// These are used by code generated by the derive macros, and are not part
+// of the public interface of this crate.
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::tagged::{
+        PositionalVisitor,
+        RawField,
+        TaggedField,
+        VariantId,
+    };
+    pub use serde;
+}
@@ -0,0 +1,442 @@
+use super::{
+    ip_address,
+    Collection,
+    Deserializer,
+    Error,
+    Result,
+    Serializer,
+};
+use serde::{
+    Deserialize as _,
+    Serialize as _,
+};
+use std::{
+    fmt,
+    net::Ipv4Addr,
+};
+
+// The following are identifier strings for supported object types.
+//
+// IMPORTANT: Changing these will break compatibility with the C++
+//            implementation and with previously encoded data!
+const TYPE_ID_EMPTY: &str = "e";
+const TYPE_ID_BOOLEAN: &str = "b";
+const TYPE_ID_INTEGER: &str = "n";
+const TYPE_ID_INTEGER_VECTOR: &str = "nv";
+const TYPE_ID_IP_ADDRESS: &str = "ip";
+const TYPE_ID_UNSIGNED_INTEGER: &str = "i";
+const TYPE_ID_UNSIGNED_INTEGER_VECTOR: &str = "iv";
+const TYPE_ID_STRING: &str = "s";
+const TYPE_ID_DECIMAL: &str = "d";
+const TYPE_ID_VECTOR: &str = "v";
+const TYPE_ID_COLLECTION: &str = "c";
+
+/// This is a value of any of the types which the C++ `SerializedObject`
+/// class can hold, encoded exactly as that class encodes it: the length of
+/// the rest of the encoding, followed by a string identifying the type of
+/// the value, followed by the value itself.
+///
+/// Because each value carries its own type, a vector or [`Collection`] of
+/// objects can be decoded without knowing in advance what it contains.
+///
+/// [`Collection`]: struct.Collection.html
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Object {
+    /// There is no value.
+    #[default]
+    Empty,
+
+    /// This holds a C++ `SerializedBoolean`.
+    Boolean(bool),
+
+    /// This holds a C++ `SerializedInteger`.
+    Integer(i32),
+
+    /// This holds a C++ `SerializedUnsignedInteger`.
+    UnsignedInteger(u64),
+
+    /// This holds a C++ `SerializedDecimal`.
+    Decimal(f64),
+
+    /// This holds a C++ `SerializedString`.
+    String(String),
+
+    /// This holds a C++ `SerializedVector`.
+    Vector(Vec<Object>),
+
+    /// This holds a C++ `SerializedCollection`.
+    Collection(Collection),
+
+    /// This holds a C++ `SerializedIpAddress`.
+    IpAddress(Ipv4Addr),
+
+    /// This holds a C++ `SerializedIntegerVector`.
+    IntegerVector(Vec<i32>),
+
+    /// This holds a C++ `SerializedUnsignedIntegerVector`.
+    UnsignedIntegerVector(Vec<u32>),
+}
+
+impl Object {
+    /// Return the value held by the object, if it holds a boolean.
+    #[must_use]
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Object::Boolean(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Return the value held by the object, if it holds a collection.
+    #[must_use]
+    pub fn as_collection(&self) -> Option<&Collection> {
+        match self {
+            Object::Collection(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Return the value held by the object, if it holds a decimal.
+    #[must_use]
+    pub fn as_decimal(&self) -> Option<f64> {
+        match self {
+            Object::Decimal(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Return the value held by the object, if it holds a signed integer.
+    #[must_use]
+    pub fn as_integer(&self) -> Option<i32> {
+        match self {
+            Object::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Return the value held by the object, if it holds a vector of signed
+    /// integers.
+    #[must_use]
+    pub fn as_integer_vector(&self) -> Option<&[i32]> {
+        match self {
+            Object::IntegerVector(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Return the value held by the object, if it holds an IP address.
+    #[must_use]
+    pub fn as_ip_address(&self) -> Option<Ipv4Addr> {
+        match self {
+            Object::IpAddress(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Return the value held by the object, if it holds a string.
+    #[must_use]
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Object::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Return the value held by the object, if it holds an unsigned
+    /// integer.
+    #[must_use]
+    pub fn as_unsigned_integer(&self) -> Option<u64> {
+        match self {
+            Object::UnsignedInteger(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Return the value held by the object, if it holds a vector of
+    /// unsigned integers.
+    #[must_use]
+    pub fn as_unsigned_integer_vector(&self) -> Option<&[u32]> {
+        match self {
+            Object::UnsignedIntegerVector(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Return the value held by the object, if it holds a vector of
+    /// objects.
+    #[must_use]
+    pub fn as_vector(&self) -> Option<&[Object]> {
+        match self {
+            Object::Vector(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn decode_contents(contents: &[u8]) -> Result<Self> {
+        let mut deserializer = Deserializer::new(contents);
+        let de = &mut deserializer;
+        let type_id = <&str>::deserialize(&mut *de)?;
+        let object = match type_id {
+            TYPE_ID_EMPTY => Object::Empty,
+            TYPE_ID_BOOLEAN => Object::Boolean(bool::deserialize(de)?),
+            TYPE_ID_INTEGER => Object::Integer(i32::deserialize(de)?),
+            TYPE_ID_UNSIGNED_INTEGER => {
+                Object::UnsignedInteger(u64::deserialize(de)?)
+            },
+            TYPE_ID_DECIMAL => {
+                Object::Decimal(f64::from_le_bytes(<[u8; 8]>::deserialize(de)?))
+            },
+            TYPE_ID_STRING => Object::String(String::deserialize(de)?),
+            TYPE_ID_VECTOR => Object::Vector(Vec::deserialize(de)?),
+            TYPE_ID_COLLECTION => {
+                Object::Collection(Collection::deserialize(de)?)
+            },
+            TYPE_ID_IP_ADDRESS => {
+                Object::IpAddress(ip_address::deserialize(de)?)
+            },
+            TYPE_ID_INTEGER_VECTOR => {
+                Object::IntegerVector(Vec::deserialize(de)?)
+            },
+            TYPE_ID_UNSIGNED_INTEGER_VECTOR => {
+                Object::UnsignedIntegerVector(Vec::deserialize(de)?)
+            },
+            _ => {
+                return Err(Error::Message(format!(
+                    "unknown object type {type_id:?}"
+                )))
+            },
+        };
+        if deserializer.offset() == contents.len() {
+            Ok(object)
+        } else {
+            Err(Error::Message(format!(
+                "object of type {:?} has {} unused bytes",
+                type_id,
+                contents.len() - deserializer.offset()
+            )))
+        }
+    }
+
+    fn encode_contents(
+        &self,
+        buffer: &mut Vec<u8>,
+    ) -> Result<()> {
+        let mut serializer = Serializer::new(buffer);
+        let ser = &mut serializer;
+        self.type_id().serialize(&mut *ser)?;
+        match self {
+            Object::Empty => Ok(()),
+            Object::Boolean(value) => value.serialize(ser),
+            Object::Integer(value) => value.serialize(ser),
+            Object::UnsignedInteger(value) => value.serialize(ser),
+            Object::Decimal(value) => value.to_le_bytes().serialize(ser),
+            Object::String(value) => value.serialize(ser),
+            Object::Vector(value) => value.serialize(ser),
+            Object::Collection(value) => value.serialize(ser),
+            Object::IpAddress(value) => ip_address::serialize(value, ser),
+            Object::IntegerVector(value) => value.serialize(ser),
+            Object::UnsignedIntegerVector(value) => value.serialize(ser),
+        }
+    }
+
+    /// Return the string which identifies the type of the object's value in
+    /// its encoding.
+    #[must_use]
+    pub fn type_id(&self) -> &'static str {
+        match self {
+            Object::Empty => TYPE_ID_EMPTY,
+            Object::Boolean(_) => TYPE_ID_BOOLEAN,
+            Object::Integer(_) => TYPE_ID_INTEGER,
+            Object::UnsignedInteger(_) => TYPE_ID_UNSIGNED_INTEGER,
+            Object::Decimal(_) => TYPE_ID_DECIMAL,
+            Object::String(_) => TYPE_ID_STRING,
+            Object::Vector(_) => TYPE_ID_VECTOR,
+            Object::Collection(_) => TYPE_ID_COLLECTION,
+            Object::IpAddress(_) => TYPE_ID_IP_ADDRESS,
+            Object::IntegerVector(_) => TYPE_ID_INTEGER_VECTOR,
+            Object::UnsignedIntegerVector(_) => TYPE_ID_UNSIGNED_INTEGER_VECTOR,
+        }
+    }
+}
+
+impl From<bool> for Object {
+    fn from(value: bool) -> Self {
+        Object::Boolean(value)
+    }
+}
+
+impl From<i32> for Object {
+    fn from(value: i32) -> Self {
+        Object::Integer(value)
+    }
+}
+
+impl From<u64> for Object {
+    fn from(value: u64) -> Self {
+        Object::UnsignedInteger(value)
+    }
+}
+
+impl From<f64> for Object {
+    fn from(value: f64) -> Self {
+        Object::Decimal(value)
+    }
+}
+
+impl From<String> for Object {
+    fn from(value: String) -> Self {
+        Object::String(value)
+    }
+}
+
+impl From<&str> for Object {
+    fn from(value: &str) -> Self {
+        Object::String(value.to_string())
+    }
+}
+
+impl From<Vec<Object>> for Object {
+    fn from(value: Vec<Object>) -> Self {
+        Object::Vector(value)
+    }
+}
+
+impl From<Collection> for Object {
+    fn from(value: Collection) -> Self {
+        Object::Collection(value)
+    }
+}
+
+impl From<Ipv4Addr> for Object {
+    fn from(value: Ipv4Addr) -> Self {
+        Object::IpAddress(value)
+    }
+}
+
+impl From<Vec<i32>> for Object {
+    fn from(value: Vec<i32>) -> Self {
+        Object::IntegerVector(value)
+    }
+}
+
+impl From<Vec<u32>> for Object {
+    fn from(value: Vec<u32>) -> Self {
+        Object::UnsignedIntegerVector(value)
+    }
+}
+
+impl serde::Serialize for Object {
+    fn serialize<S>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut contents = Vec::new();
+        self.encode_contents(&mut contents)
+            .map_err(serde::ser::Error::custom)?;
+        serializer.serialize_bytes(&contents)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Object {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl serde::de::Visitor<'_> for Visitor {
+            type Value = Object;
+
+            fn expecting(
+                &self,
+                f: &mut fmt::Formatter,
+            ) -> fmt::Result {
+                f.write_str("an encoded object")
+            }
+
+            fn visit_bytes<E>(
+                self,
+                v: &[u8],
+            ) -> std::result::Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Object::decode_contents(v).map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_bytes(Visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        from_bytes,
+        to_bytes,
+    };
+
+    fn examples() -> Vec<(Object, &'static [u8])> {
+        vec![
+            (Object::Empty, &[2, 1, 0x65]),
+            (Object::Boolean(true), &[3, 1, 0x62, 1]),
+            (Object::Integer(-42), &[3, 1, 0x6E, 0x6A]),
+            (Object::UnsignedInteger(255), &[4, 1, 0x69, 0x81, 0x7F]),
+            (Object::Decimal(-10.0), &[
+                10, 1, 0x64, 0, 0, 0, 0, 0, 0, 0x24, 0xC0,
+            ]),
+            (Object::from("hi"), &[5, 1, 0x73, 2, 0x68, 0x69]),
+            (Object::Vector(vec![Object::Boolean(true)]), &[
+                7, 1, 0x76, 1, 3, 1, 0x62, 1,
+            ]),
+            (Object::IpAddress(Ipv4Addr::new(192, 168, 1, 2)), &[
+                7, 2, 0x69, 0x70, 2, 1, 168, 192,
+            ]),
+            (Object::IntegerVector(vec![1, -1]), &[
+                6, 2, 0x6E, 0x76, 2, 1, 0x41,
+            ]),
+            (Object::UnsignedIntegerVector(vec![1, 200]), &[
+                7, 2, 0x69, 0x76, 2, 1, 0x81, 0x48,
+            ]),
+        ]
+    }
+
+    #[test]
+    fn serialize_object() {
+        for (value, expected) in examples() {
+            let serialization = to_bytes(&value);
+            assert!(serialization.is_ok());
+            let serialization = serialization.unwrap();
+            assert_eq!(expected, serialization);
+        }
+    }
+
+    #[test]
+    fn deserialize_object() {
+        for (expected, value) in examples() {
+            let deserialization: Result<Object> = from_bytes(value);
+            assert!(deserialization.is_ok());
+            let deserialization = deserialization.unwrap();
+            assert_eq!(expected, deserialization);
+        }
+    }
+
+    #[test]
+    fn deserialize_object_unknown_type() {
+        let deserialization: Result<Object> = from_bytes(&[3, 1, 0x78, 0][..]);
+        assert!(matches!(deserialization, Err(Error::Message(_))));
+    }
+
+    #[test]
+    fn deserialize_object_wrong_length() {
+        let deserialization: Result<Object> =
+            from_bytes(&[4, 1, 0x62, 1, 0][..]);
+        assert!(matches!(deserialization, Err(Error::Message(_))));
+        let deserialization: Result<Object> = from_bytes(&[2, 1, 0x62][..]);
+        assert!(matches!(deserialization, Err(Error::Message(_))));
+    }
+}
@@ -0,0 +1,270 @@
+use std::{
+    fmt,
+    ops::{
+        Deref,
+        DerefMut,
+    },
+};
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// This is implemented by the fixed-width numeric types which can be the
+/// elements of a [`Packed`] vector: `u16`, `u32`, `u64`, `i16`, `i32`,
+/// `i64`, `f32` and `f64`.
+///
+/// [`Packed`]: struct.Packed.html
+pub trait PackedElement: Copy + Default + sealed::Sealed {
+    /// This is the number of bytes in the encoding of each element.
+    const WIDTH: usize;
+
+    /// Decode an element from exactly [`WIDTH`] bytes, most significant
+    /// byte first.
+    ///
+    /// [`WIDTH`]: #associatedconstant.WIDTH
+    fn from_be_slice(bytes: &[u8]) -> Self;
+
+    /// Encode the element into exactly [`WIDTH`] bytes, most significant
+    /// byte first.
+    ///
+    /// [`WIDTH`]: #associatedconstant.WIDTH
+    fn write_be_slice(
+        self,
+        bytes: &mut [u8],
+    );
+}
+
+macro_rules! packed_element {
+    ($($t:ty),*) => {$(
+        impl sealed::Sealed for $t {}
+
+        impl PackedElement for $t {
+            const WIDTH: usize = std::mem::size_of::<$t>();
+
+            #[inline]
+            fn from_be_slice(bytes: &[u8]) -> Self {
+                let mut array = [0; std::mem::size_of::<$t>()];
+                array.copy_from_slice(bytes);
+                <$t>::from_be_bytes(array)
+            }
+
+            #[inline]
+            fn write_be_slice(
+                self,
+                bytes: &mut [u8],
+            ) {
+                bytes.copy_from_slice(&self.to_be_bytes());
+            }
+        }
+    )*};
+}
+
+packed_element!(u16, u32, u64, i16, i32, i64, f32, f64);
+
+/// This wraps a vector of fixed-width numbers so that it is encoded as a
+/// single block: the length of the block in bytes, followed by every
+/// element at its full width, most significant byte first.  Each element
+/// has the same encoding as a lone `f32` or `f64` would, but integers are
+/// not given variable-length encodings.
+///
+/// A plain `Vec<f32>` is encoded and decoded one element at a time, which
+/// is slow for vectors of millions of samples.  A packed vector is
+/// written and read in one pass over contiguous memory instead.  The
+/// layout is not understood by the C++ implementation.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate serialization;
+/// # extern crate serde;
+/// use serialization::Packed;
+///
+/// #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+/// struct Frame {
+///     samples: Packed<Vec<f32>>,
+/// }
+///
+/// let frame = Frame {
+///     samples: Packed(vec![1.0, -2.5]),
+/// };
+/// let encoding = serialization::to_bytes(&frame).unwrap();
+/// assert_eq!(
+///     &[8, 0x3F, 0x80, 0x00, 0x00, 0xC0, 0x20, 0x00, 0x00][..],
+///     encoding
+/// );
+/// assert_eq!(frame, serialization::from_bytes(&encoding).unwrap());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Packed<T>(pub T);
+
+impl<T> Packed<T> {
+    /// Return the wrapped value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Packed<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Packed<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T> From<T> for Packed<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl<T> serde::Serialize for Packed<Vec<T>>
+where
+    T: PackedElement,
+{
+    fn serialize<S>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut bytes = vec![0; self.0.len() * T::WIDTH];
+        for (chunk, element) in bytes.chunks_exact_mut(T::WIDTH).zip(&self.0) {
+            element.write_be_slice(chunk);
+        }
+        serializer.serialize_bytes(&bytes)
+    }
+}
+
+struct Visitor<'a, T> {
+    place: &'a mut Vec<T>,
+}
+
+impl<T> serde::de::Visitor<'_> for Visitor<'_, T>
+where
+    T: PackedElement,
+{
+    type Value = ();
+
+    fn expecting(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        write!(f, "a block of {}-byte numbers", T::WIDTH)
+    }
+
+    fn visit_bytes<E>(
+        self,
+        bytes: &[u8],
+    ) -> Result<(), E>
+    where
+        E: serde::de::Error,
+    {
+        if bytes.len() % T::WIDTH != 0 {
+            return Err(E::invalid_length(bytes.len(), &self));
+        }
+        // Filling a vector of the final length, rather than pushing each
+        // element, lets the conversion compile to a few wide copies.
+        self.place.clear();
+        self.place.resize(bytes.len() / T::WIDTH, T::default());
+        for (element, chunk) in
+            self.place.iter_mut().zip(bytes.chunks_exact(T::WIDTH))
+        {
+            *element = T::from_be_slice(chunk);
+        }
+        Ok(())
+    }
+}
+
+impl<'de, T> serde::Deserialize<'de> for Packed<Vec<T>>
+where
+    T: PackedElement,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let mut values = Vec::new();
+        deserializer.deserialize_bytes(Visitor {
+            place: &mut values,
+        })?;
+        Ok(Self(values))
+    }
+
+    fn deserialize_in_place<D>(
+        deserializer: D,
+        place: &mut Self,
+    ) -> Result<(), D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_bytes(Visitor {
+            place: &mut place.0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        from_bytes,
+        from_bytes_into,
+        to_bytes,
+        Error,
+    };
+
+    #[test]
+    fn serialize_integers() {
+        let serialization = to_bytes(&Packed(vec![1_u16, 0x1234]));
+        assert!(serialization.is_ok());
+        let serialization = serialization.unwrap();
+        assert_eq!(&[4, 0x00, 0x01, 0x12, 0x34][..], serialization);
+        let serialization = to_bytes(&Packed(vec![-2_i32]));
+        assert!(serialization.is_ok());
+        let serialization = serialization.unwrap();
+        assert_eq!(&[4, 0xFF, 0xFF, 0xFF, 0xFE][..], serialization);
+    }
+
+    #[test]
+    fn floats_match_unpacked_elements() {
+        let values = vec![0.5_f64, -1.0e300, f64::INFINITY];
+        let packed = to_bytes(&Packed(values.clone())).unwrap();
+        let unpacked = to_bytes(&values).unwrap();
+        assert_eq!(24, packed[0]);
+        assert_eq!(3, unpacked[0]);
+        assert_eq!(packed[1..], unpacked[1..]);
+        let deserialization: Packed<Vec<f64>> = from_bytes(&packed).unwrap();
+        assert_eq!(values, deserialization.into_inner());
+    }
+
+    #[test]
+    fn deserialize_misaligned() {
+        assert!(matches!(
+            from_bytes::<Packed<Vec<u32>>>(&[3, 0, 0, 1][..]),
+            Err(Error::Message(_))
+        ));
+        assert!(matches!(
+            from_bytes::<Packed<Vec<u32>>>(&[4, 0, 0, 1][..]),
+            Err(Error::ValueTruncated)
+        ));
+    }
+
+    #[test]
+    fn deserialize_into_reuses_vec() {
+        let mut value = Packed(Vec::with_capacity(16));
+        value.push(9_u64);
+        assert!(from_bytes_into(&mut value, &[8, 0, 0, 0, 0, 0, 0, 1, 0][..])
+            .is_ok());
+        assert_eq!(&[256][..], &value[..]);
+        assert_eq!(16, value.capacity());
+    }
+}
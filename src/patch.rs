@@ -0,0 +1,898 @@
+use super::{
+    appendable,
+    borrowed_slice,
+    from_bytes,
+    schema::type_name,
+    schema_of,
+    shared,
+    to_bytes,
+    to_bytes_into,
+    Deserializer,
+    Error,
+    Result,
+    Schema,
+    VariantShape,
+};
+use serde::Deserialize as _;
+use std::{
+    collections::{
+        HashMap,
+        HashSet,
+    },
+    convert::TryFrom,
+};
+
+/// This is the difference between two values of the same type, as found by
+/// [`diff`], which [`apply`] can make to the first value to turn it into the
+/// second.
+///
+/// Only the fields, elements and map entries which changed are held, each
+/// as the encoding of its new value.  A patch may itself be encoded with
+/// [`to_bytes`], and is only meaningful for the type it was made from.
+///
+/// [`diff`]: fn.diff.html
+/// [`apply`]: fn.apply.html
+/// [`to_bytes`]: fn.to_bytes.html
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Patch {
+    edit: Edit,
+}
+
+impl Patch {
+    /// Return `true` if the patch makes no change.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.edit == Edit::Keep
+    }
+}
+
+// This is a change to an encoded value.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+enum Edit {
+    // The value is unchanged.
+    Keep,
+
+    // The value is replaced by the one with the given encoding.
+    Replace(Vec<u8>),
+
+    // The value keeps its option flag or variant index, and the values
+    // which follow it at the given positions are changed.
+    Fields(Vec<(u32, Edit)>),
+
+    // The sequence is given a new length, the elements at the given
+    // positions are changed, and the elements past its old length, if it
+    // is longer, are given by their encodings one after another.
+    Seq {
+        len: u64,
+        edits: Vec<(u64, Edit)>,
+        appended: Vec<u8>,
+    },
+
+    // The entries with the given encoded keys are removed, and those with
+    // the given encoded keys are changed or, if they are new, added.
+    Map {
+        removed: Vec<Vec<u8>>,
+        changed: Vec<(Vec<u8>, Edit)>,
+    },
+}
+
+// This is an encoded value divided into the parts which may be changed
+// separately.
+enum Parts<'a, 's> {
+    // The value may only be replaced as a whole.
+    Whole,
+
+    // The value is a header, such as an option flag or variant index,
+    // followed by a fixed number of other values.
+    Fields(&'a [u8], Vec<(&'s Schema, &'a [u8])>),
+
+    // The value is a sequence of elements with the given layout.
+    Seq(&'s Schema, Vec<&'a [u8]>),
+
+    // The value is a map, whose values have the given layout.
+    Map(&'s Schema, Vec<(&'a [u8], &'a [u8])>),
+}
+
+// Return `true` if values of the given layout may hold shared values.
+// These are encoded as references to values encoded earlier, so their
+// encodings cannot be changed piecemeal.
+fn has_shared(schema: &Schema) -> bool {
+    match schema {
+        Schema::Option(schema) | Schema::Seq(schema) => has_shared(schema),
+        Schema::Map {
+            key,
+            value,
+        } => has_shared(key) || has_shared(value),
+        Schema::Tuple(schemas)
+        | Schema::TupleStruct {
+            fields: schemas,
+            ..
+        } => schemas.iter().any(has_shared),
+        Schema::Struct {
+            fields,
+            ..
+        } => fields.iter().any(|field| has_shared(&field.schema)),
+        Schema::NewtypeStruct {
+            name,
+            value,
+        } => name == shared::NAME || has_shared(value),
+        Schema::Enum {
+            variants,
+            ..
+        } => variants.iter().any(|variant| match &variant.shape {
+            VariantShape::Unit => false,
+            VariantShape::Newtype(schema) => has_shared(schema),
+            VariantShape::Tuple(schemas) => schemas.iter().any(has_shared),
+            VariantShape::Struct(fields) => {
+                fields.iter().any(|field| has_shared(&field.schema))
+            },
+        }),
+        _ => false,
+    }
+}
+
+// Return the number of bytes at the start of the given ones which encode a
+// value of the given type.
+fn length_of<'de, T>(bytes: &'de [u8]) -> Result<usize>
+where
+    T: serde::Deserialize<'de>,
+{
+    let mut deserializer = Deserializer::new(bytes);
+    T::deserialize(&mut deserializer)?;
+    Ok(deserializer.offset())
+}
+
+// Decode the variable-length unsigned integer at the start of the given
+// bytes, returning it along with the number of bytes it occupies.
+fn varint(bytes: &[u8]) -> Result<(usize, usize)> {
+    let mut deserializer = Deserializer::new(bytes);
+    let value = usize::deserialize(&mut deserializer)?;
+    Ok((value, deserializer.offset()))
+}
+
+// This walks encoded values along with their schema, keeping track of the
+// named types which enclose the current position, so that references to
+// them can be followed.
+struct Walker<'s> {
+    enclosing: Vec<&'s Schema>,
+}
+
+impl<'s> Walker<'s> {
+    fn apply(
+        &mut self,
+        schema: &'s Schema,
+        old: &[u8],
+        edit: &Edit,
+        out: &mut Vec<u8>,
+    ) -> Result<()> {
+        match edit {
+            Edit::Keep => out.extend_from_slice(old),
+            Edit::Replace(new) => out.extend_from_slice(new),
+            _ => {
+                let schema = self.enter(schema)?;
+                let result = self.apply_parts(schema, old, edit, out);
+                self.enclosing.pop();
+                result?;
+            },
+        }
+        Ok(())
+    }
+
+    fn apply_parts(
+        &mut self,
+        schema: &'s Schema,
+        old: &[u8],
+        edit: &Edit,
+        out: &mut Vec<u8>,
+    ) -> Result<()> {
+        match (self.split(schema, old)?.0, edit) {
+            (Parts::Fields(header, fields), Edit::Fields(edits)) => {
+                out.extend_from_slice(header);
+                let mut edits = edits.iter().peekable();
+                for (position, (schema, old)) in fields.into_iter().enumerate()
+                {
+                    match edits.next_if(|(edited, _)| {
+                        usize::try_from(*edited).ok() == Some(position)
+                    }) {
+                        Some((_, edit)) => {
+                            self.apply(schema, old, edit, out)?;
+                        },
+                        None => out.extend_from_slice(old),
+                    }
+                }
+                if edits.next().is_some() {
+                    return Err(Error::PatchMismatch);
+                }
+            },
+            (
+                Parts::Seq(element, elements),
+                Edit::Seq {
+                    len,
+                    edits,
+                    appended,
+                },
+            ) => {
+                let len =
+                    usize::try_from(*len).map_err(|_| Error::PatchMismatch)?;
+                self.check_appended(
+                    element,
+                    appended,
+                    len.saturating_sub(elements.len()),
+                )?;
+                to_bytes_into(&len, out)?;
+                let mut edits = edits.iter().peekable();
+                for (position, old) in
+                    elements.into_iter().take(len).enumerate()
+                {
+                    match edits.next_if(|(edited, _)| {
+                        usize::try_from(*edited).ok() == Some(position)
+                    }) {
+                        Some((_, edit)) => {
+                            self.apply(element, old, edit, out)?;
+                        },
+                        None => out.extend_from_slice(old),
+                    }
+                }
+                if edits.next().is_some() {
+                    return Err(Error::PatchMismatch);
+                }
+                out.extend_from_slice(appended);
+            },
+            (
+                Parts::Map(value, entries),
+                Edit::Map {
+                    removed,
+                    changed,
+                },
+            ) => {
+                let removed =
+                    removed.iter().map(Vec::as_slice).collect::<HashSet<_>>();
+                let mut pending = changed
+                    .iter()
+                    .map(|(key, edit)| (key.as_slice(), edit))
+                    .collect::<HashMap<_, _>>();
+                let mut body = Vec::new();
+                let mut count = 0_usize;
+                for (key, old) in entries {
+                    if removed.contains(key) {
+                        continue;
+                    }
+                    body.extend_from_slice(key);
+                    match pending.remove(key) {
+                        Some(edit) => {
+                            self.apply(value, old, edit, &mut body)?;
+                        },
+                        None => body.extend_from_slice(old),
+                    }
+                    count += 1;
+                }
+                for (key, edit) in changed {
+                    if !pending.contains_key(key.as_slice()) {
+                        continue;
+                    }
+                    let Edit::Replace(new) = edit else {
+                        return Err(Error::PatchMismatch);
+                    };
+                    body.extend_from_slice(key);
+                    body.extend_from_slice(new);
+                    count += 1;
+                }
+                to_bytes_into(&count, out)?;
+                out.extend_from_slice(&body);
+            },
+            _ => return Err(Error::PatchMismatch),
+        }
+        Ok(())
+    }
+
+    fn diff(
+        &mut self,
+        schema: &'s Schema,
+        old: &[u8],
+        new: &[u8],
+    ) -> Result<Edit> {
+        if old == new {
+            return Ok(Edit::Keep);
+        }
+        let schema = self.enter(schema)?;
+        let result = self.diff_parts(schema, old, new);
+        self.enclosing.pop();
+        result
+    }
+
+    fn diff_parts(
+        &mut self,
+        schema: &'s Schema,
+        old: &[u8],
+        new: &[u8],
+    ) -> Result<Edit> {
+        match (self.split(schema, old)?.0, self.split(schema, new)?.0) {
+            (
+                Parts::Fields(old_header, old_fields),
+                Parts::Fields(new_header, new_fields),
+            ) if old_header == new_header
+                && old_fields.len() == new_fields.len() =>
+            {
+                let mut edits = Vec::new();
+                for (position, ((schema, old), (_, new))) in
+                    old_fields.into_iter().zip(new_fields).enumerate()
+                {
+                    let edit = self.diff(schema, old, new)?;
+                    if edit != Edit::Keep {
+                        edits.push((
+                            u32::try_from(position).unwrap_or(u32::MAX),
+                            edit,
+                        ));
+                    }
+                }
+                Ok(Edit::Fields(edits))
+            },
+            (
+                Parts::Seq(element, old_elements),
+                Parts::Seq(_, new_elements),
+            ) => {
+                let mut edits = Vec::new();
+                for (position, (old, new)) in
+                    old_elements.iter().zip(&new_elements).enumerate()
+                {
+                    let edit = self.diff(element, old, new)?;
+                    if edit != Edit::Keep {
+                        edits.push((position as u64, edit));
+                    }
+                }
+                Ok(Edit::Seq {
+                    len: new_elements.len() as u64,
+                    edits,
+                    appended: new_elements
+                        .get(old_elements.len()..)
+                        .unwrap_or_default()
+                        .concat(),
+                })
+            },
+            (Parts::Map(value, old_entries), Parts::Map(_, new_entries)) => {
+                let old_values =
+                    old_entries.iter().copied().collect::<HashMap<_, _>>();
+                let new_keys = new_entries
+                    .iter()
+                    .map(|(key, _)| *key)
+                    .collect::<HashSet<_>>();
+                let removed = old_entries
+                    .iter()
+                    .filter(|(key, _)| !new_keys.contains(key))
+                    .map(|(key, _)| key.to_vec())
+                    .collect();
+                let mut changed = Vec::new();
+                for (key, new) in new_entries {
+                    let edit = match old_values.get(key) {
+                        Some(old) => self.diff(value, old, new)?,
+                        None => Edit::Replace(new.to_vec()),
+                    };
+                    if edit != Edit::Keep {
+                        changed.push((key.to_vec(), edit));
+                    }
+                }
+                Ok(Edit::Map {
+                    removed,
+                    changed,
+                })
+            },
+            _ => Ok(Edit::Replace(new.to_vec())),
+        }
+    }
+
+    // Follow the given schema if it is a reference, and note it as an
+    // enclosing type until the matching `pop` of `enclosing`.
+    fn enter(
+        &mut self,
+        schema: &'s Schema,
+    ) -> Result<&'s Schema> {
+        let schema = match schema {
+            Schema::Ref {
+                name,
+            } => self
+                .enclosing
+                .iter()
+                .rev()
+                .copied()
+                .find(|enclosing| type_name(enclosing) == Some(name.as_str()))
+                .ok_or(Error::PatchMismatch)?,
+            _ => schema,
+        };
+        self.enclosing.push(schema);
+        Ok(schema)
+    }
+
+    // Check that the given bytes encode exactly the given number of values
+    // with the given layout, as the elements appended to a sequence by a
+    // patch must.
+    fn check_appended(
+        &mut self,
+        schema: &'s Schema,
+        appended: &[u8],
+        count: usize,
+    ) -> Result<()> {
+        let mut offset = 0;
+        for _ in 0..count {
+            let element = self
+                .next(appended, &mut offset, schema)
+                .map_err(|_| Error::PatchMismatch)?;
+            // A layout which encodes one value in no bytes does so for
+            // every value, so the rest need not be counted one by one.
+            if element.is_empty() {
+                break;
+            }
+        }
+        if offset == appended.len() {
+            Ok(())
+        } else {
+            Err(Error::PatchMismatch)
+        }
+    }
+
+    // Return the number of bytes at the start of the given ones which
+    // encode a value with the given layout.
+    fn measure(
+        &mut self,
+        schema: &'s Schema,
+        bytes: &[u8],
+    ) -> Result<usize> {
+        let schema = self.enter(schema)?;
+        let result = self.measure_entered(schema, bytes);
+        self.enclosing.pop();
+        result
+    }
+
+    fn measure_entered(
+        &mut self,
+        schema: &'s Schema,
+        bytes: &[u8],
+    ) -> Result<usize> {
+        let len = match schema {
+            Schema::Unit
+            | Schema::UnitStruct {
+                ..
+            } => 0,
+            Schema::Bool | Schema::I8 | Schema::U8 => 1,
+            Schema::I16 | Schema::I32 | Schema::I64 => length_of::<i64>(bytes)?,
+            Schema::U16 | Schema::U32 | Schema::U64 => length_of::<u64>(bytes)?,
+            Schema::F32 => 4,
+            Schema::F64 => 8,
+            Schema::Char => length_of::<char>(bytes)?,
+            Schema::Str | Schema::Bytes => length_of::<&[u8]>(bytes)?,
+            Schema::NewtypeStruct {
+                name,
+                ..
+            } if name == appendable::NAME
+                || borrowed_slice::alignment(name).is_some() =>
+            {
+                length_of::<&[u8]>(bytes)?
+            },
+            Schema::NewtypeStruct {
+                name,
+                ..
+            } if name == shared::NAME => return Err(Error::PatchMismatch),
+            _ => return self.split(schema, bytes).map(|(_, len)| len),
+        };
+        if len > bytes.len() {
+            return Err(Error::ValueTruncated);
+        }
+        Ok(len)
+    }
+
+    // Return the encoded value with the given layout which starts at the
+    // given offset into the given bytes, and move the offset past it.
+    fn next<'a>(
+        &mut self,
+        bytes: &'a [u8],
+        offset: &mut usize,
+        schema: &'s Schema,
+    ) -> Result<&'a [u8]> {
+        let rest = bytes.get(*offset..).ok_or(Error::ValueTruncated)?;
+        let len = self.measure(schema, rest)?;
+        *offset += len;
+        Ok(&rest[..len])
+    }
+
+    // Divide the encoded value at the start of the given bytes into parts,
+    // according to the given layout, which must have been entered, and
+    // return them along with the number of bytes the value occupies.
+    fn split<'a>(
+        &mut self,
+        schema: &'s Schema,
+        bytes: &'a [u8],
+    ) -> Result<(Parts<'a, 's>, usize)> {
+        let mut offset = 0;
+        let parts = match schema {
+            Schema::Option(value) => {
+                let flag = *bytes.first().ok_or(Error::ValueTruncated)?;
+                offset = 1;
+                let value = if flag == 0 {
+                    None
+                } else {
+                    Some(&**value)
+                };
+                let fields = self.fields(bytes, &mut offset, value)?;
+                Parts::Fields(&bytes[..1], fields)
+            },
+            Schema::Seq(element) => {
+                let (count, header) = varint(bytes)?;
+                offset = header;
+                let mut elements = Vec::with_capacity(count.min(bytes.len()));
+                for _ in 0..count {
+                    elements.push(self.next(bytes, &mut offset, element)?);
+                }
+                Parts::Seq(element, elements)
+            },
+            Schema::Map {
+                key,
+                value,
+            } => {
+                let (count, header) = varint(bytes)?;
+                offset = header;
+                let mut entries = Vec::with_capacity(count.min(bytes.len()));
+                for _ in 0..count {
+                    let key = self.next(bytes, &mut offset, key)?;
+                    entries.push((key, self.next(bytes, &mut offset, value)?));
+                }
+                Parts::Map(value, entries)
+            },
+            Schema::Tuple(schemas)
+            | Schema::TupleStruct {
+                fields: schemas,
+                ..
+            } => Parts::Fields(&[], self.fields(bytes, &mut offset, schemas)?),
+            Schema::Struct {
+                fields,
+                ..
+            } => Parts::Fields(
+                &[],
+                self.fields(
+                    bytes,
+                    &mut offset,
+                    fields.iter().map(|field| &field.schema),
+                )?,
+            ),
+            Schema::NewtypeStruct {
+                name,
+                value,
+            } if name != appendable::NAME
+                && name != shared::NAME
+                && borrowed_slice::alignment(name).is_none() =>
+            {
+                Parts::Fields(
+                    &[],
+                    self.fields(bytes, &mut offset, Some(&**value))?,
+                )
+            },
+            Schema::Enum {
+                variants,
+                ..
+            } => {
+                let (index, header) = varint(bytes)?;
+                offset = header;
+                let variant = variants
+                    .iter()
+                    .find(|variant| usize::try_from(variant.index) == Ok(index))
+                    .ok_or(Error::UnknownVariant(index as u64))?;
+                let fields = match &variant.shape {
+                    VariantShape::Unit => Vec::new(),
+                    VariantShape::Newtype(schema) => {
+                        self.fields(bytes, &mut offset, Some(&**schema))?
+                    },
+                    VariantShape::Tuple(schemas) => {
+                        self.fields(bytes, &mut offset, schemas)?
+                    },
+                    VariantShape::Struct(fields) => self.fields(
+                        bytes,
+                        &mut offset,
+                        fields.iter().map(|field| &field.schema),
+                    )?,
+                };
+                Parts::Fields(&bytes[..header], fields)
+            },
+            _ => Parts::Whole,
+        };
+        Ok((parts, offset))
+    }
+
+    // Return the encoded values with the given layouts which follow one
+    // another from the given offset into the given bytes, each with its
+    // layout, and move the offset past them.
+    fn fields<'a, I>(
+        &mut self,
+        bytes: &'a [u8],
+        offset: &mut usize,
+        schemas: I,
+    ) -> Result<Vec<(&'s Schema, &'a [u8])>>
+    where
+        I: IntoIterator<Item = &'s Schema>,
+    {
+        schemas
+            .into_iter()
+            .map(|schema| Ok((schema, self.next(bytes, offset, schema)?)))
+            .collect()
+    }
+}
+
+/// This function is used to find the difference between two values of the
+/// same type, as a [`Patch`] which [`apply`] can make to the first value
+/// to turn it into the second.
+///
+/// Both values are encoded, and their encodings are compared according to
+/// the [`schema_of`] their type.  Values which contain any [`Shared`]
+/// values are replaced as a whole.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate serialization;
+/// # extern crate serde;
+/// use std::collections::BTreeMap;
+///
+/// #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+/// struct State {
+///     tick: u64,
+///     scores: BTreeMap<String, u32>,
+///     log: Vec<String>,
+/// }
+///
+/// let mut state = State {
+///     tick: 1,
+///     scores: BTreeMap::new(),
+///     log: vec![String::from("start")],
+/// };
+/// let mut next = serialization::from_bytes::<State>(
+///     &serialization::to_bytes(&state).unwrap(),
+/// )
+/// .unwrap();
+/// next.tick = 2;
+/// next.scores.insert(String::from("a"), 10);
+/// next.log.push(String::from("scored"));
+///
+/// let patch = serialization::diff(&state, &next).unwrap();
+/// serialization::apply(&mut state, &patch).unwrap();
+/// assert_eq!(next, state);
+/// ```
+///
+/// # Errors
+///
+/// This function returns the same errors as [`to_bytes`] and
+/// [`schema_of`], and [`Error::PatchMismatch`] if an encoding does not
+/// match the schema.
+///
+/// [`Patch`]: struct.Patch.html
+/// [`apply`]: fn.apply.html
+/// [`schema_of`]: fn.schema_of.html
+/// [`Shared`]: struct.Shared.html
+/// [`to_bytes`]: fn.to_bytes.html
+/// [`Error::PatchMismatch`]: enum.Error.html#variant.PatchMismatch
+pub fn diff<T>(
+    old: &T,
+    new: &T,
+) -> Result<Patch>
+where
+    T: serde::Serialize + serde::Deserialize<'static>,
+{
+    let old = to_bytes(old)?;
+    let new = to_bytes(new)?;
+    let schema = schema_of::<T>()?;
+    let edit = if has_shared(&schema) {
+        if old == new {
+            Edit::Keep
+        } else {
+            Edit::Replace(new)
+        }
+    } else {
+        Walker {
+            enclosing: Vec::new(),
+        }
+        .diff(&schema, &old, &new)?
+    };
+    Ok(Patch {
+        edit,
+    })
+}
+
+/// This function is used to make the changes of a [`Patch`] found by
+/// [`diff`] to a value.
+///
+/// # Errors
+///
+/// [`Error::PatchMismatch`] is returned if the patch does not fit the
+/// value, such as when it was made from a different value than the one it
+/// is applied to.  Otherwise this function returns the same errors as
+/// [`to_bytes`], [`from_bytes`] and [`schema_of`].  If it returns an error,
+/// the value is unchanged.
+///
+/// [`Patch`]: struct.Patch.html
+/// [`diff`]: fn.diff.html
+/// [`to_bytes`]: fn.to_bytes.html
+/// [`from_bytes`]: fn.from_bytes.html
+/// [`schema_of`]: fn.schema_of.html
+/// [`Error::PatchMismatch`]: enum.Error.html#variant.PatchMismatch
+pub fn apply<T>(
+    value: &mut T,
+    patch: &Patch,
+) -> Result<()>
+where
+    T: serde::Serialize + serde::de::DeserializeOwned,
+{
+    let new = match &patch.edit {
+        Edit::Keep => return Ok(()),
+        Edit::Replace(new) => from_bytes(new)?,
+        edit => {
+            let old = to_bytes(value)?;
+            let schema = schema_of::<T>()?;
+            let mut new = Vec::with_capacity(old.len());
+            Walker {
+                enclosing: Vec::new(),
+            }
+            .apply(&schema, &old, edit, &mut new)?;
+            from_bytes(&new)?
+        },
+    };
+    *value = new;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Shared;
+    use std::{
+        collections::BTreeMap,
+        rc::Rc,
+    };
+
+    #[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq, Debug)]
+    enum Status {
+        Idle,
+        Busy(u32),
+        Failed {
+            code: i32,
+            reason: String,
+        },
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq, Debug)]
+    struct Node {
+        name: String,
+        status: Status,
+        load: Option<f64>,
+        children: Vec<Node>,
+        labels: BTreeMap<String, String>,
+    }
+
+    fn node(name: &str) -> Node {
+        Node {
+            name: String::from(name),
+            status: Status::Idle,
+            load: None,
+            children: Vec::new(),
+            labels: BTreeMap::new(),
+        }
+    }
+
+    fn tree() -> Node {
+        let mut root = node("root");
+        for index in 0..20 {
+            let mut child = node(&format!("child {index}"));
+            child.status = Status::Busy(index);
+            child.labels.insert(String::from("zone"), String::from("a"));
+            root.children.push(child);
+        }
+        root
+    }
+
+    fn round_trip(
+        old: &Node,
+        new: &Node,
+    ) -> Patch {
+        let patch = diff(old, new);
+        assert!(patch.is_ok());
+        let patch = patch.unwrap();
+        let encoding = to_bytes(&patch).unwrap();
+        let patch: Patch = from_bytes(&encoding).unwrap();
+        let mut value = old.clone();
+        assert!(apply(&mut value, &patch).is_ok());
+        assert_eq!(*new, value);
+        patch
+    }
+
+    #[test]
+    fn unchanged_is_empty() {
+        let patch = round_trip(&tree(), &tree());
+        assert!(patch.is_empty());
+        assert_eq!(1, to_bytes(&patch).unwrap().len());
+    }
+
+    #[test]
+    fn changed_fields_only() {
+        let old = tree();
+        let mut new = old.clone();
+        new.children[7].load = Some(0.5);
+        new.children[12].status = Status::Failed {
+            code: -3,
+            reason: String::from("disk"),
+        };
+        new.children[12].name.push('!');
+        let patch = round_trip(&old, &new);
+        assert!(
+            to_bytes(&patch).unwrap().len() * 4 < to_bytes(&new).unwrap().len()
+        );
+    }
+
+    #[test]
+    fn sequence_grows_and_shrinks() {
+        let old = tree();
+        let mut new = old.clone();
+        new.children.push(node("extra"));
+        new.children[0].children.push(node("grandchild"));
+        round_trip(&old, &new);
+        round_trip(&new, &old);
+        let mut new = old.clone();
+        new.children.clear();
+        round_trip(&old, &new);
+    }
+
+    #[test]
+    fn sequence_of_empty_values() {
+        #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+        struct Marker;
+
+        let old = vec![(), ()];
+        let new = vec![(), (), ()];
+        for (old, new) in &[(&old, &new), (&new, &old)] {
+            let patch = diff(*old, *new).unwrap();
+            let mut value = (*old).clone();
+            assert!(apply(&mut value, &patch).is_ok());
+            assert_eq!(**new, value);
+        }
+        let patch = diff(&vec![Marker], &vec![Marker, Marker]).unwrap();
+        let mut value = vec![Marker];
+        assert!(apply(&mut value, &patch).is_ok());
+        assert_eq!(vec![Marker, Marker], value);
+    }
+
+    #[test]
+    fn map_entries_change() {
+        let old = tree();
+        let mut new = old.clone();
+        new.children[3].labels.remove("zone");
+        new.children[4].labels.insert(String::from("zone"), String::from("b"));
+        new.children[5].labels.insert(String::from("rack"), String::from("9"));
+        round_trip(&old, &new);
+    }
+
+    #[test]
+    fn enum_variant_changes() {
+        let old = tree();
+        let mut new = old.clone();
+        new.status = Status::Busy(1);
+        new.children[1].status = Status::Busy(100);
+        new.children[2].status = Status::Idle;
+        round_trip(&old, &new);
+    }
+
+    #[test]
+    fn shared_values_replace_whole() {
+        let old = (Shared(Rc::new(1_u8)), 2_u8);
+        let new = (Shared(Rc::new(1_u8)), 3_u8);
+        let patch = diff(&old, &new).unwrap();
+        assert_eq!(Edit::Replace(to_bytes(&new).unwrap()), patch.edit);
+        let mut value = old;
+        assert!(apply(&mut value, &patch).is_ok());
+        assert_eq!(3, value.1);
+    }
+
+    #[test]
+    fn apply_mismatch() {
+        let old = tree();
+        let mut new = old.clone();
+        new.children[19].name.clear();
+        let patch = diff(&old, &new).unwrap();
+        let mut value = node("other");
+        assert!(matches!(apply(&mut value, &patch), Err(Error::PatchMismatch)));
+        assert_eq!(node("other"), value);
+    }
+}
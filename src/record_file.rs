@@ -0,0 +1,422 @@
+use super::{
+    Deserializer,
+    Error,
+    Result,
+};
+use memmap2::Mmap;
+use std::{
+    convert::TryFrom,
+    fs::File,
+    marker::PhantomData,
+    path::Path,
+};
+
+// This is the number of bytes in each entry of a sidecar index, which is
+// the big-endian offset of a record from the start of the file.
+const INDEX_ENTRY_LENGTH: usize = 8;
+
+// Map the whole of the file at the given path into memory.
+fn map(path: &Path) -> Result<Mmap> {
+    let file = File::open(path).map_err(Error::Io)?;
+    // SAFETY: The mapping is only ever read, and record files are only
+    // appended to, so the bytes mapped are not changed while they are in
+    // use.  A record file truncated by another process while mapped is
+    // not supported.
+    unsafe { Mmap::map(&file) }.map_err(Error::Io)
+}
+
+/// This is a file of records, each encoded after the one before it as by
+/// [`to_bytes_into`], which is read through a memory map rather than loaded
+/// into memory.
+///
+/// Records are decoded in order with [`iter`], borrowing from the mapped
+/// file.  A sidecar index, made by [`write_index`], holds the offset of each
+/// record, so that reading may start at any record with [`seek`] without
+/// decoding those before it.
+///
+/// A file may end with part of a record, if a write to it was torn short.
+/// Iterating stops with an error at such a record, and [`last_good_offset`]
+/// gives the length of the file up to it, to which the file may be
+/// truncated before more records are appended.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate serialization;
+/// let path = std::env::temp_dir().join("record-file-example.bin");
+/// let mut bytes = Vec::new();
+/// for value in &[1_u32, 300, 70000] {
+///     serialization::to_bytes_into(value, &mut bytes).unwrap();
+/// }
+/// bytes.push(0x81);
+/// std::fs::write(&path, &bytes).unwrap();
+///
+/// let file = serialization::RecordFile::open(&path).unwrap();
+/// let values = file.iter::<u32>().take(3).collect::<Result<Vec<_>, _>>();
+/// assert_eq!(vec![1, 300, 70000], values.unwrap());
+/// assert_eq!(bytes.len() - 1, file.last_good_offset::<u32>());
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+///
+/// [`to_bytes_into`]: fn.to_bytes_into.html
+/// [`iter`]: #method.iter
+/// [`write_index`]: #method.write_index
+/// [`seek`]: #method.seek
+/// [`last_good_offset`]: #method.last_good_offset
+pub struct RecordFile {
+    data: Mmap,
+    index: Option<Mmap>,
+}
+
+impl RecordFile {
+    /// Return the bytes of the file.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Return an iterator which decodes the records of the file in order,
+    /// as values of type `T`.
+    #[must_use]
+    pub fn iter<'a, T>(&'a self) -> Records<'a, T>
+    where
+        T: serde::Deserialize<'a>,
+    {
+        Records::new(&self.data, 0)
+    }
+
+    /// Return the number of bytes at the start of the file which hold
+    /// whole records of type `T`, which is the offset of the first record
+    /// which cannot be decoded, or the length of the file if there is none.
+    #[must_use]
+    pub fn last_good_offset<'a, T>(&'a self) -> usize
+    where
+        T: serde::Deserialize<'a>,
+    {
+        let mut records = self.iter::<T>();
+        records.by_ref().for_each(drop);
+        records.offset()
+    }
+
+    /// Return the number of records in the sidecar index of the file, if
+    /// it has one.
+    #[must_use]
+    pub fn indexed_len(&self) -> Option<usize> {
+        self.index.as_ref().map(|index| index.len() / INDEX_ENTRY_LENGTH)
+    }
+
+    /// Open the record file at the given path.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::Io`] is returned if the file cannot be opened or mapped.
+    ///
+    /// [`Error::Io`]: enum.Error.html#variant.Io
+    pub fn open<P>(path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(Self {
+            data: map(path.as_ref())?,
+            index: None,
+        })
+    }
+
+    /// Open the record file at the given path, along with its sidecar
+    /// index at the other given path, which was made by [`write_index`].
+    /// An entry cut short at the end of the index is ignored.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::Io`] is returned if either file cannot be opened or mapped.
+    ///
+    /// [`write_index`]: #method.write_index
+    /// [`Error::Io`]: enum.Error.html#variant.Io
+    pub fn open_indexed<P, Q>(
+        path: P,
+        index_path: Q,
+    ) -> Result<Self>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        Ok(Self {
+            data: map(path.as_ref())?,
+            index: Some(map(index_path.as_ref())?),
+        })
+    }
+
+    /// Return an iterator which decodes the records of the file in order
+    /// as values of type `T`, starting with the one at the given position.
+    /// The record is found through the sidecar index if the file has one,
+    /// or by decoding the records before it otherwise.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::IndexOutOfRange`] is returned if the file has no record at
+    /// the given position, or its sidecar index has no entry for it.
+    /// [`Error::ValueTruncated`] is returned if the entry is past the end
+    /// of the file.  Otherwise, without a sidecar index, the first error in
+    /// decoding the records before the given one is returned.
+    ///
+    /// [`Error::IndexOutOfRange`]: enum.Error.html#variant.IndexOutOfRange
+    /// [`Error::ValueTruncated`]: enum.Error.html#variant.ValueTruncated
+    pub fn seek<'a, T>(
+        &'a self,
+        position: usize,
+    ) -> Result<Records<'a, T>>
+    where
+        T: serde::Deserialize<'a>,
+    {
+        let offset = if let Some(index) = &self.index {
+            let entry = position
+                .checked_mul(INDEX_ENTRY_LENGTH)
+                .and_then(|start| index.get(start..start + INDEX_ENTRY_LENGTH))
+                .ok_or(Error::IndexOutOfRange(position))?;
+            let mut offset = [0; INDEX_ENTRY_LENGTH];
+            offset.copy_from_slice(entry);
+            usize::try_from(u64::from_be_bytes(offset))
+                .ok()
+                .filter(|&offset| offset <= self.data.len())
+                .ok_or(Error::ValueTruncated)?
+        } else {
+            let mut records = self.iter::<T>();
+            for _ in 0..position {
+                records.next().ok_or(Error::IndexOutOfRange(position))??;
+            }
+            records.offset()
+        };
+        Ok(Records::new(&self.data, offset))
+    }
+
+    /// Write a sidecar index for the file to the given path, with an entry
+    /// for each of its records of type `T`, up to the first which cannot be
+    /// decoded.  Return the number of entries written.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::Io`] is returned if the index cannot be written.
+    ///
+    /// [`Error::Io`]: enum.Error.html#variant.Io
+    pub fn write_index<'a, T, P>(
+        &'a self,
+        path: P,
+    ) -> Result<usize>
+    where
+        T: serde::Deserialize<'a>,
+        P: AsRef<Path>,
+    {
+        let mut index = Vec::new();
+        let mut records = self.iter::<T>();
+        loop {
+            let offset = records.offset();
+            if !matches!(records.next(), Some(Ok(_))) {
+                break;
+            }
+            index.extend_from_slice(&(offset as u64).to_be_bytes());
+        }
+        std::fs::write(path, &index).map_err(Error::Io)?;
+        Ok(index.len() / INDEX_ENTRY_LENGTH)
+    }
+}
+
+/// This is an iterator over the records of a [`RecordFile`], which decodes
+/// each as a value of type `T`.  After a record fails to decode, the error
+/// is returned and iteration ends.
+///
+/// [`RecordFile`]: struct.RecordFile.html
+pub struct Records<'a, T> {
+    bytes: &'a [u8],
+    offset: usize,
+    done: bool,
+    output: PhantomData<T>,
+}
+
+impl<'a, T> Records<'a, T> {
+    fn new(
+        bytes: &'a [u8],
+        offset: usize,
+    ) -> Self {
+        Self {
+            bytes,
+            offset,
+            done: false,
+            output: PhantomData,
+        }
+    }
+
+    /// Return the offset in the file of the next record to decode, which
+    /// is the end of the last record successfully decoded.
+    #[must_use]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl<'a, T> Iterator for Records<'a, T>
+where
+    T: serde::Deserialize<'a>,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = &self.bytes[self.offset..];
+        if self.done || rest.is_empty() {
+            return None;
+        }
+        let mut deserializer = Deserializer::new(rest);
+        let record = T::deserialize(&mut deserializer);
+        match record {
+            Ok(_) => self.offset += deserializer.offset(),
+            Err(_) => self.done = true,
+        }
+        Some(record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::to_bytes_into;
+    use std::path::PathBuf;
+
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    struct Entry<'a> {
+        id: u32,
+        text: &'a str,
+    }
+
+    // This is a file in the temporary directory, named for the test which
+    // uses it, which is removed when dropped.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            Self(std::env::temp_dir().join(format!(
+                "serialization-{}-{}",
+                std::process::id(),
+                name
+            )))
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    fn write_records(
+        file: &TempFile,
+        count: u32,
+        torn: bool,
+    ) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for id in 0..count {
+            let text = format!("entry {id}");
+            to_bytes_into(
+                &Entry {
+                    id,
+                    text: &text,
+                },
+                &mut bytes,
+            )
+            .unwrap();
+        }
+        if torn {
+            bytes.extend_from_slice(&[0x81, 0x00, 5, b'e']);
+        }
+        std::fs::write(&file.0, &bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn iterate_records() {
+        let data = TempFile::new("iterate_records");
+        write_records(&data, 100, false);
+        let file = RecordFile::open(&data.0);
+        assert!(file.is_ok());
+        let file = file.unwrap();
+        let mut count = 0;
+        for (id, entry) in (0..).zip(file.iter::<Entry>()) {
+            assert!(entry.is_ok());
+            let entry = entry.unwrap();
+            assert_eq!(id, entry.id);
+            assert_eq!(format!("entry {id}"), entry.text);
+            assert!(file
+                .as_bytes()
+                .as_ptr_range()
+                .contains(&entry.text.as_ptr()));
+            count += 1;
+        }
+        assert_eq!(100, count);
+        assert_eq!(file.as_bytes().len(), file.last_good_offset::<Entry>());
+    }
+
+    #[test]
+    fn torn_write() {
+        let data = TempFile::new("torn_write");
+        let bytes = write_records(&data, 10, true);
+        let file = RecordFile::open(&data.0).unwrap();
+        let mut records = file.iter::<Entry>();
+        assert_eq!(9, records.by_ref().take(10).flatten().last().unwrap().id);
+        assert!(matches!(records.next(), Some(Err(Error::ValueTruncated))));
+        assert!(records.next().is_none());
+        assert_eq!(bytes.len() - 4, records.offset());
+        assert_eq!(bytes.len() - 4, file.last_good_offset::<Entry>());
+    }
+
+    #[test]
+    fn seek_with_index() {
+        let data = TempFile::new("seek_with_index");
+        let index = TempFile::new("seek_with_index.idx");
+        write_records(&data, 1000, true);
+        let file = RecordFile::open(&data.0).unwrap();
+        assert_eq!(None, file.indexed_len());
+        let written = file.write_index::<Entry, _>(&index.0);
+        assert!(written.is_ok());
+        assert_eq!(1000, written.unwrap());
+        let file = RecordFile::open_indexed(&data.0, &index.0).unwrap();
+        assert_eq!(Some(1000), file.indexed_len());
+        let mut records = file.seek::<Entry>(700).unwrap();
+        assert_eq!(700, records.next().unwrap().unwrap().id);
+        assert_eq!(701, records.next().unwrap().unwrap().id);
+        assert_eq!(
+            999,
+            file.seek::<Entry>(999).unwrap().next().unwrap().unwrap().id
+        );
+        assert!(matches!(
+            file.seek::<Entry>(1000).map(|_| ()),
+            Err(Error::IndexOutOfRange(1000))
+        ));
+    }
+
+    #[test]
+    fn seek_without_index() {
+        let data = TempFile::new("seek_without_index");
+        write_records(&data, 20, false);
+        let file = RecordFile::open(&data.0).unwrap();
+        let mut records = file.seek::<Entry>(15).unwrap();
+        assert_eq!(15, records.next().unwrap().unwrap().id);
+        assert_eq!(0, file.seek::<Entry>(20).unwrap().count());
+        assert!(matches!(
+            file.seek::<Entry>(21).map(|_| ()),
+            Err(Error::IndexOutOfRange(21))
+        ));
+    }
+
+    #[test]
+    fn open_empty() {
+        let data = TempFile::new("open_empty");
+        write_records(&data, 0, false);
+        let file = RecordFile::open(&data.0).unwrap();
+        assert!(file.iter::<Entry>().next().is_none());
+        assert_eq!(0, file.last_good_offset::<Entry>());
+    }
+
+    #[test]
+    fn open_missing() {
+        let data = TempFile::new("open_missing");
+        assert!(matches!(RecordFile::open(&data.0), Err(Error::Io(_))));
+    }
+}
@@ -0,0 +1,566 @@
+use super::{
+    from_bytes,
+    to_bytes_checked,
+    to_bytes_into,
+    Checksum as _,
+    Crc32c,
+    Deserializer,
+    Error,
+    Result,
+};
+use serde::Deserialize as _;
+use std::{
+    fs::{
+        File,
+        OpenOptions,
+    },
+    io::{
+        ErrorKind,
+        Read as _,
+        Seek as _,
+        SeekFrom,
+        Write as _,
+    },
+    marker::PhantomData,
+    path::Path,
+};
+
+// Check the header of the frame at the given offset in the given bytes
+// against its checksum, and return the offsets of the start of the payload
+// of the frame and of the end of the frame.  If the bytes end part way
+// through the frame, whose length can only be trusted once its header has
+// been checked, the frame is where a write was torn short.
+fn parse_header(
+    bytes: &[u8],
+    offset: usize,
+) -> Result<(usize, usize)> {
+    let rest = &bytes[offset..];
+    let mut deserializer = Deserializer::new(rest);
+    let len = usize::deserialize(&mut deserializer)?;
+    let (header, rest) = rest.split_at(deserializer.offset());
+    let checksum = rest.get(..Crc32c.length()).ok_or(Error::ValueTruncated)?;
+    if Crc32c.checksum(header) != checksum {
+        return Err(Error::ChecksumMismatch(offset as u64));
+    }
+    let start = offset + header.len() + Crc32c.length();
+    let end = len
+        .checked_add(start + Crc32c.length())
+        .filter(|&end| end <= bytes.len())
+        .ok_or(Error::ValueTruncated)?;
+    Ok((start, end))
+}
+
+// Return the payload of the frame at the given offset in the given bytes,
+// after checking its header and the payload itself against their
+// checksums, along with the offset of the end of the frame.
+fn parse_frame(
+    bytes: &[u8],
+    offset: usize,
+) -> Result<(&[u8], usize)> {
+    let (start, end) = parse_header(bytes, offset)?;
+    let (payload, checksum) =
+        bytes[start..end].split_at(end - start - Crc32c.length());
+    if Crc32c.checksum(payload) != checksum {
+        return Err(Error::ChecksumMismatch(offset as u64));
+    }
+    Ok((payload, end))
+}
+
+// Flush the entry of a newly created file in its directory through to the
+// storage device, so that the file itself survives a crash.  Windows does
+// not allow directories to be opened as files, and does not need this.
+#[cfg(unix)]
+fn sync_parent(path: &Path) -> Result<()> {
+    let parent = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    File::open(parent).and_then(|dir| dir.sync_all()).map_err(Error::Io)
+}
+
+#[cfg(not(unix))]
+fn sync_parent(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+/// This selects when a [`RecordLog`] flushes appended records through to
+/// the storage device.
+///
+/// [`RecordLog`]: struct.RecordLog.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyncPolicy {
+    /// The log is synchronized after every record appended, so that no
+    /// record is lost once it has been appended.
+    Always,
+
+    /// The log is synchronized after every given number of records
+    /// appended, so that at most that many may be lost in a crash.
+    EveryRecords(usize),
+
+    /// The log is only synchronized when [`sync`] is called.
+    ///
+    /// [`sync`]: struct.RecordLog.html#method.sync
+    Manual,
+}
+
+/// This appends records to a file, in a way which survives the program or
+/// machine crashing part way through writing one.
+///
+/// Each record is encoded as a frame, which holds a header and then the
+/// value encoded by [`to_bytes`] followed by its CRC-32C, as by
+/// [`to_bytes_checked`].  The header holds the length of the encoded value,
+/// as a variable-length integer, followed by its own CRC-32C, so that a
+/// damaged length is never trusted.  Records are flushed to the storage
+/// device according to a [`SyncPolicy`].
+///
+/// When a log is opened, its frames are checked from the start.  If the
+/// file ends part way through the last one, or the last one is complete
+/// but its value does not match its checksum, it is where a write was torn
+/// short, and the file is truncated to remove it.  Any other frame which
+/// does not match a checksum is corruption rather than a torn write, and
+/// is reported as an error without changing the file.  Records in a log
+/// are read with a [`LogRecords`] iterator.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate serialization;
+/// use serialization::{
+///     LogRecords,
+///     RecordLog,
+///     SyncPolicy,
+/// };
+///
+/// let path = std::env::temp_dir().join("record-log-example.log");
+/// # let _ = std::fs::remove_file(&path);
+/// let mut log = RecordLog::open(&path, SyncPolicy::Always).unwrap();
+/// log.append(&(1_u8, "one")).unwrap();
+/// log.append(&(2_u8, "two")).unwrap();
+/// drop(log);
+///
+/// let bytes = std::fs::read(&path).unwrap();
+/// let records = LogRecords::<(u8, &str)>::new(&bytes)
+///     .collect::<Result<Vec<_>, _>>()
+///     .unwrap();
+/// assert_eq!(vec![(1, "one"), (2, "two")], records);
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+///
+/// [`to_bytes`]: fn.to_bytes.html
+/// [`to_bytes_checked`]: fn.to_bytes_checked.html
+/// [`SyncPolicy`]: enum.SyncPolicy.html
+/// [`LogRecords`]: struct.LogRecords.html
+pub struct RecordLog {
+    file: File,
+    len: u64,
+    truncated: u64,
+    unsynced: usize,
+    policy: SyncPolicy,
+}
+
+impl RecordLog {
+    /// Encode the given value and append it to the log as a record,
+    /// returning the offset in the file at which the record starts.
+    ///
+    /// If the record cannot be written in full, the file is truncated back
+    /// to the end of the record before it, so that later records are not
+    /// appended after a torn one.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::Io`] is returned if the record cannot be written, or if it
+    /// cannot be synchronized to the storage device when the policy calls
+    /// for it.  Otherwise this function returns the same errors as
+    /// [`to_bytes`].
+    ///
+    /// [`to_bytes`]: fn.to_bytes.html
+    /// [`Error::Io`]: enum.Error.html#variant.Io
+    pub fn append<T>(
+        &mut self,
+        value: &T,
+    ) -> Result<u64>
+    where
+        T: ?Sized + serde::Serialize,
+    {
+        let record = to_bytes_checked(value)?;
+        let mut frame = Vec::with_capacity(record.len() + 14);
+        to_bytes_into(&(record.len() - Crc32c.length()), &mut frame)?;
+        let header = Crc32c.checksum(&frame);
+        frame.extend(header);
+        frame.extend_from_slice(&record);
+        let start = self.len;
+        if let Err(error) = self.file.write_all(&frame) {
+            // Failing to remove the torn record here is not reported, as it
+            // would be removed when the log is next opened anyway.
+            if self.file.set_len(start).is_ok() {
+                let _ = self.file.seek(SeekFrom::Start(start));
+            }
+            return Err(Error::Io(error));
+        }
+        self.len += frame.len() as u64;
+        self.unsynced += 1;
+        match self.policy {
+            SyncPolicy::Always => self.sync()?,
+            SyncPolicy::EveryRecords(count) if self.unsynced >= count => {
+                self.sync()?;
+            },
+            _ => {},
+        }
+        Ok(start)
+    }
+
+    /// Return `true` if the log holds no records.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Return the number of bytes in the log.
+    #[must_use]
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Open the log at the given path, creating it if it does not exist,
+    /// and truncating it after the last record which was written in full.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::ChecksumMismatch`] is returned, with the offset of the
+    /// record, if the header of any record, or the value of a record before
+    /// the last one, does not match its checksum.  [`Error::Io`] is returned if
+    /// the file cannot be created, opened, read, or truncated.
+    ///
+    /// [`Error::ChecksumMismatch`]: enum.Error.html#variant.ChecksumMismatch
+    /// [`Error::Io`]: enum.Error.html#variant.Io
+    pub fn open<P>(
+        path: P,
+        policy: SyncPolicy,
+    ) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let mut options = OpenOptions::new();
+        options.read(true).write(true);
+        let mut file = match options.clone().create_new(true).open(path) {
+            Ok(file) => {
+                sync_parent(path)?;
+                file
+            },
+            Err(error) if error.kind() == ErrorKind::AlreadyExists => {
+                options.open(path).map_err(Error::Io)?
+            },
+            Err(error) => return Err(Error::Io(error)),
+        };
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes).map_err(Error::Io)?;
+        let mut len = 0;
+        while len < bytes.len() {
+            match parse_frame(&bytes, len) {
+                Ok((_, end)) => len = end,
+                Err(Error::ValueTruncated) => break,
+                Err(Error::ChecksumMismatch(_))
+                    if matches!(
+                        parse_header(&bytes, len),
+                        Ok((_, end)) if end == bytes.len()
+                    ) =>
+                {
+                    break;
+                },
+                Err(error) => return Err(error),
+            }
+        }
+        let truncated = (bytes.len() - len) as u64;
+        let len = len as u64;
+        if truncated > 0 {
+            file.set_len(len).map_err(Error::Io)?;
+            file.sync_all().map_err(Error::Io)?;
+        }
+        file.seek(SeekFrom::Start(len)).map_err(Error::Io)?;
+        Ok(Self {
+            file,
+            len,
+            truncated,
+            unsynced: 0,
+            policy,
+        })
+    }
+
+    /// Flush all records appended to the log through to the storage device.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::Io`] is returned if the file cannot be synchronized.
+    ///
+    /// [`Error::Io`]: enum.Error.html#variant.Io
+    pub fn sync(&mut self) -> Result<()> {
+        self.file.sync_data().map_err(Error::Io)?;
+        self.unsynced = 0;
+        Ok(())
+    }
+
+    /// Return the number of bytes of a torn record which were removed from
+    /// the end of the log when it was opened.
+    #[must_use]
+    pub fn truncated(&self) -> u64 {
+        self.truncated
+    }
+}
+
+/// This is an iterator over the records of a [`RecordLog`], which checks
+/// and decodes each as a value of type `T`.  After a record fails to
+/// decode, the error is returned and iteration ends.
+///
+/// [`RecordLog`]: struct.RecordLog.html
+pub struct LogRecords<'a, T> {
+    bytes: &'a [u8],
+    offset: usize,
+    done: bool,
+    output: PhantomData<T>,
+}
+
+impl<'a, T> LogRecords<'a, T> {
+    /// Return an iterator over the records held in the given bytes of a
+    /// log.
+    #[must_use]
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes,
+            offset: 0,
+            done: false,
+            output: PhantomData,
+        }
+    }
+
+    /// Return the offset in the log of the next record to decode, which
+    /// is the end of the last record successfully decoded.
+    #[must_use]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl<'a, T> Iterator for LogRecords<'a, T>
+where
+    T: serde::Deserialize<'a>,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || self.offset == self.bytes.len() {
+            return None;
+        }
+        let record =
+            parse_frame(self.bytes, self.offset).and_then(|(payload, end)| {
+                let record = from_bytes(payload)?;
+                self.offset = end;
+                Ok(record)
+            });
+        self.done = record.is_err();
+        Some(record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        convert::TryFrom,
+        path::PathBuf,
+    };
+
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    struct Event {
+        id: u32,
+        text: String,
+    }
+
+    fn event(id: u32) -> Event {
+        Event {
+            id,
+            text: format!("event {id}"),
+        }
+    }
+
+    // This is a directory in the temporary directory, named for the test
+    // which uses it, which is removed along with its contents when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "serialization-{}-{}",
+                std::process::id(),
+                name
+            ));
+            std::fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+
+        fn log(&self) -> PathBuf {
+            self.0.join("records.log")
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn read_ids(path: &Path) -> Vec<u32> {
+        let bytes = std::fs::read(path).unwrap();
+        LogRecords::<Event>::new(&bytes)
+            .map(|event| event.unwrap().id)
+            .collect()
+    }
+
+    #[test]
+    fn append_and_read() {
+        let dir = TempDir::new("append_and_read");
+        let log = RecordLog::open(dir.log(), SyncPolicy::EveryRecords(3));
+        assert!(log.is_ok());
+        let mut log = log.unwrap();
+        assert!(log.is_empty());
+        let mut offsets = Vec::new();
+        for id in 0..10 {
+            let offset = log.append(&event(id));
+            assert!(offset.is_ok());
+            offsets.push(offset.unwrap());
+        }
+        assert!(log.sync().is_ok());
+        let len = log.len();
+        drop(log);
+        let bytes = std::fs::read(dir.log()).unwrap();
+        assert_eq!(len, bytes.len() as u64);
+        for (id, offset) in (0..).zip(offsets) {
+            let mut records = LogRecords::<Event>::new(
+                &bytes[usize::try_from(offset).unwrap()..],
+            );
+            assert_eq!(event(id), records.next().unwrap().unwrap());
+        }
+        let log = RecordLog::open(dir.log(), SyncPolicy::Manual).unwrap();
+        assert_eq!(0, log.truncated());
+        assert_eq!(len, log.len());
+        assert_eq!((0..10).collect::<Vec<_>>(), read_ids(&dir.log()));
+    }
+
+    #[test]
+    fn frame_layout() {
+        let dir = TempDir::new("frame_layout");
+        let mut log = RecordLog::open(dir.log(), SyncPolicy::Always).unwrap();
+        assert!(log.append(&300_u16).is_ok());
+        drop(log);
+        let mut expected = vec![2];
+        expected.extend_from_slice(&Crc32c::compute(&[2]).to_be_bytes());
+        expected.extend_from_slice(&[0x82, 0x2C]);
+        expected
+            .extend_from_slice(&Crc32c::compute(&[0x82, 0x2C]).to_be_bytes());
+        assert_eq!(expected, std::fs::read(dir.log()).unwrap());
+    }
+
+    #[test]
+    fn recover_torn_tail() {
+        let dir = TempDir::new("recover_torn_tail");
+        let mut log = RecordLog::open(dir.log(), SyncPolicy::Always).unwrap();
+        for id in 0..5 {
+            log.append(&event(id)).unwrap();
+        }
+        let good = log.len();
+        log.append(&event(5)).unwrap();
+        drop(log);
+        let whole = std::fs::read(dir.log()).unwrap();
+        for cut in usize::try_from(good).unwrap() + 1..whole.len() {
+            // Simulate the last write being torn short at each possible
+            // byte, then recover and carry on appending.
+            std::fs::write(dir.log(), &whole[..cut]).unwrap();
+            let mut log =
+                RecordLog::open(dir.log(), SyncPolicy::Always).unwrap();
+            assert_eq!(good, log.len());
+            assert_eq!(cut as u64 - good, log.truncated());
+            assert_eq!(good, log.append(&event(6)).unwrap());
+            drop(log);
+            assert_eq!(vec![0, 1, 2, 3, 4, 6], read_ids(&dir.log()));
+        }
+    }
+
+    #[test]
+    fn recover_corrupt_tail() {
+        let dir = TempDir::new("recover_corrupt_tail");
+        let mut log = RecordLog::open(dir.log(), SyncPolicy::Manual).unwrap();
+        for id in 0..3 {
+            log.append(&event(id)).unwrap();
+        }
+        let good = log.len();
+        log.append(&event(3)).unwrap();
+        log.sync().unwrap();
+        drop(log);
+        let mut bytes = std::fs::read(dir.log()).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0x01;
+        std::fs::write(dir.log(), &bytes).unwrap();
+        let mut records = LogRecords::<Event>::new(&bytes);
+        assert_eq!(3, records.by_ref().take(3).count());
+        assert!(matches!(
+            records.next(),
+            Some(Err(Error::ChecksumMismatch(offset))) if offset == good
+        ));
+        assert!(records.next().is_none());
+        assert_eq!(good, records.offset() as u64);
+        let log = RecordLog::open(dir.log(), SyncPolicy::Manual).unwrap();
+        assert_eq!(good, log.len());
+        assert_eq!(vec![0, 1, 2], read_ids(&dir.log()));
+    }
+
+    #[test]
+    fn reject_corrupt_middle() {
+        let dir = TempDir::new("reject_corrupt_middle");
+        let mut log = RecordLog::open(dir.log(), SyncPolicy::Always).unwrap();
+        let mut offsets = Vec::new();
+        for id in 0..5 {
+            offsets.push(log.append(&event(id)).unwrap());
+        }
+        drop(log);
+        let whole = std::fs::read(dir.log()).unwrap();
+        for (record, &offset) in offsets.iter().enumerate().take(4) {
+            // Flip a byte in the checksum of a record which is followed
+            // by others.
+            let end = usize::try_from(offsets[record + 1]).unwrap();
+            let mut bytes = whole.clone();
+            bytes[end - 1] ^= 0x01;
+            std::fs::write(dir.log(), &bytes).unwrap();
+            let log = RecordLog::open(dir.log(), SyncPolicy::Always);
+            assert!(matches!(
+                log,
+                Err(Error::ChecksumMismatch(corrupt)) if corrupt == offset
+            ));
+            assert_eq!(bytes, std::fs::read(dir.log()).unwrap());
+        }
+    }
+
+    #[test]
+    fn reject_corrupt_length() {
+        let dir = TempDir::new("reject_corrupt_length");
+        let mut log = RecordLog::open(dir.log(), SyncPolicy::Always).unwrap();
+        let mut offsets = Vec::new();
+        for id in 0..5 {
+            offsets.push(log.append(&event(id)).unwrap());
+        }
+        drop(log);
+        let whole = std::fs::read(dir.log()).unwrap();
+        for &offset in &offsets {
+            // Make the length of a record point past the end of the log,
+            // as it would if the record were torn short.
+            let mut bytes = whole.clone();
+            bytes[usize::try_from(offset).unwrap()] = 0x7F;
+            std::fs::write(dir.log(), &bytes).unwrap();
+            let log = RecordLog::open(dir.log(), SyncPolicy::Always);
+            assert!(matches!(
+                log,
+                Err(Error::ChecksumMismatch(corrupt)) if corrupt == offset
+            ));
+            assert_eq!(bytes, std::fs::read(dir.log()).unwrap());
+        }
+    }
+}
@@ -0,0 +1,1556 @@
+use super::{
+    Error,
+    Result,
+};
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+};
+
+// This is the greatest number of named containers which may be nested while
+// a schema is traced, before the type is assumed to be recursive without end.
+const MAX_DEPTH: usize = 128;
+
+/// This is a description of the layout in which a type is encoded, as
+/// determined by [`schema_of`].
+///
+/// Because the format is not self-describing, the layout of a type is
+/// implied entirely by the calls which its `Deserialize` implementation
+/// makes on the deserializer.  A schema records those calls as a tree, so
+/// that it can be stored alongside encoded data and compared later.
+/// Schemas may themselves be encoded with [`to_bytes`].
+///
+/// [`schema_of`]: fn.schema_of.html
+/// [`to_bytes`]: fn.to_bytes.html
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Schema {
+    /// This is a value which occupies no bytes.
+    Unit,
+
+    /// This is a `bool`, encoded as a single byte.
+    Bool,
+
+    /// This is an `i8`, encoded as a single byte.
+    I8,
+
+    /// This is an `i16`, encoded as a variable-length signed integer.
+    I16,
+
+    /// This is an `i32`, encoded as a variable-length signed integer.
+    I32,
+
+    /// This is an `i64`, encoded as a variable-length signed integer.
+    I64,
+
+    /// This is a `u8`, encoded as a single byte.
+    U8,
+
+    /// This is a `u16`, encoded as a variable-length unsigned integer.
+    U16,
+
+    /// This is a `u32`, encoded as a variable-length unsigned integer.
+    U32,
+
+    /// This is a `u64`, encoded as a variable-length unsigned integer.
+    U64,
+
+    /// This is an `f32`, encoded as four bytes.
+    F32,
+
+    /// This is an `f64`, encoded as eight bytes.
+    F64,
+
+    /// This is a `char`, encoded as UTF-8 text.
+    Char,
+
+    /// This is text, encoded as its length followed by its UTF-8 bytes.
+    Str,
+
+    /// This is a byte array, encoded as its length followed by its bytes.
+    Bytes,
+
+    /// This is a value which may be absent, encoded as a byte which is set
+    /// if the value is present, followed by the value if it is.
+    Option(Box<Schema>),
+
+    /// This is a sequence of values, encoded as the number of values
+    /// followed by each value.
+    Seq(Box<Schema>),
+
+    /// This is a map, encoded as the number of entries followed by the key
+    /// and value of each entry.
+    Map {
+        /// This is the layout of each key.
+        key: Box<Schema>,
+
+        /// This is the layout of each value.
+        value: Box<Schema>,
+    },
+
+    /// This is a fixed number of values, encoded one after another.
+    Tuple(Vec<Schema>),
+
+    /// This is a named value which occupies no bytes.
+    UnitStruct {
+        /// This is the name of the type.
+        name: String,
+    },
+
+    /// This is a named wrapper, encoded as the value it wraps.
+    NewtypeStruct {
+        /// This is the name of the type.
+        name: String,
+
+        /// This is the layout of the wrapped value.
+        value: Box<Schema>,
+    },
+
+    /// This is a named tuple, encoded as its fields one after another.
+    TupleStruct {
+        /// This is the name of the type.
+        name: String,
+
+        /// These are the layouts of the fields, in order.
+        fields: Vec<Schema>,
+    },
+
+    /// This is a struct, encoded as its fields one after another.
+    Struct {
+        /// This is the name of the type.
+        name: String,
+
+        /// These are the fields, in order.
+        fields: Vec<Field>,
+    },
+
+    /// This is an enum, encoded as the index of the variant followed by the
+    /// contents of the variant.
+    Enum {
+        /// This is the name of the type.
+        name: String,
+
+        /// These are the variants, in order of their indices.
+        variants: Vec<Variant>,
+    },
+
+    /// This stands for the nearest enclosing type with the given name, and
+    /// appears where a type contains itself.
+    Ref {
+        /// This is the name of the type.
+        name: String,
+    },
+}
+
+/// This is a field of a struct or struct variant within a [`Schema`].
+///
+/// [`Schema`]: enum.Schema.html
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Field {
+    /// This is the name of the field.
+    pub name: String,
+
+    /// This is the layout of the field.
+    pub schema: Schema,
+}
+
+/// This is a variant of an enum within a [`Schema`].
+///
+/// [`Schema`]: enum.Schema.html
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Variant {
+    /// This is the name of the variant.
+    pub name: String,
+
+    /// This is the index which is encoded to select the variant.
+    pub index: u32,
+
+    /// This is the layout of the contents of the variant.
+    pub shape: VariantShape,
+}
+
+/// This is the layout of the contents of an enum [`Variant`].
+///
+/// [`Variant`]: struct.Variant.html
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum VariantShape {
+    /// The variant has no contents.
+    Unit,
+
+    /// The variant wraps a single value.
+    Newtype(Box<Schema>),
+
+    /// The variant has unnamed fields, encoded one after another.
+    Tuple(Vec<Schema>),
+
+    /// The variant has named fields, encoded one after another.
+    Struct(Vec<Field>),
+}
+
+impl Schema {
+    fn has_ref(&self) -> bool {
+        match self {
+            Schema::Option(schema)
+            | Schema::Seq(schema)
+            | Schema::NewtypeStruct {
+                value: schema,
+                ..
+            } => schema.has_ref(),
+            Schema::Map {
+                key,
+                value,
+            } => key.has_ref() || value.has_ref(),
+            Schema::Tuple(schemas)
+            | Schema::TupleStruct {
+                fields: schemas,
+                ..
+            } => schemas.iter().any(Schema::has_ref),
+            Schema::Struct {
+                fields,
+                ..
+            } => fields.iter().any(|field| field.schema.has_ref()),
+            Schema::Enum {
+                variants,
+                ..
+            } => variants.iter().any(|variant| variant.shape.has_ref()),
+            Schema::Ref {
+                ..
+            } => true,
+            _ => false,
+        }
+    }
+
+    // Combine two traces of the same type, which differ only in the enum
+    // variants which were chosen while tracing them.
+    fn merge(
+        self,
+        other: Schema,
+    ) -> Schema {
+        match (self, other) {
+            (Schema::Option(schema), Schema::Option(other)) => {
+                Schema::Option(Box::new(schema.merge(*other)))
+            },
+            (Schema::Seq(schema), Schema::Seq(other)) => {
+                Schema::Seq(Box::new(schema.merge(*other)))
+            },
+            (
+                Schema::Map {
+                    key,
+                    value,
+                },
+                Schema::Map {
+                    key: other_key,
+                    value: other_value,
+                },
+            ) => Schema::Map {
+                key: Box::new(key.merge(*other_key)),
+                value: Box::new(value.merge(*other_value)),
+            },
+            (Schema::Tuple(schemas), Schema::Tuple(others)) => {
+                Schema::Tuple(merge_all(schemas, others))
+            },
+            (
+                Schema::NewtypeStruct {
+                    name,
+                    value,
+                },
+                Schema::NewtypeStruct {
+                    value: other,
+                    ..
+                },
+            ) => Schema::NewtypeStruct {
+                name,
+                value: Box::new(value.merge(*other)),
+            },
+            (
+                Schema::TupleStruct {
+                    name,
+                    fields,
+                },
+                Schema::TupleStruct {
+                    fields: others,
+                    ..
+                },
+            ) => Schema::TupleStruct {
+                name,
+                fields: merge_all(fields, others),
+            },
+            (
+                Schema::Struct {
+                    name,
+                    fields,
+                },
+                Schema::Struct {
+                    fields: others,
+                    ..
+                },
+            ) => Schema::Struct {
+                name,
+                fields: merge_fields(fields, others),
+            },
+            (
+                Schema::Enum {
+                    name,
+                    mut variants,
+                },
+                Schema::Enum {
+                    variants: others,
+                    ..
+                },
+            ) => {
+                for other in others {
+                    match variants
+                        .iter()
+                        .position(|variant| variant.index == other.index)
+                    {
+                        Some(position) => {
+                            let variant = variants.remove(position);
+                            variants.insert(position, Variant {
+                                shape: variant.shape.merge(other.shape),
+                                ..variant
+                            });
+                        },
+                        None => variants.push(other),
+                    }
+                }
+                variants.sort_by_key(|variant| variant.index);
+                Schema::Enum {
+                    name,
+                    variants,
+                }
+            },
+            (schema, _) => schema,
+        }
+    }
+}
+
+impl VariantShape {
+    fn has_ref(&self) -> bool {
+        match self {
+            VariantShape::Unit => false,
+            VariantShape::Newtype(schema) => schema.has_ref(),
+            VariantShape::Tuple(schemas) => schemas.iter().any(Schema::has_ref),
+            VariantShape::Struct(fields) => {
+                fields.iter().any(|field| field.schema.has_ref())
+            },
+        }
+    }
+
+    fn merge(
+        self,
+        other: VariantShape,
+    ) -> VariantShape {
+        match (self, other) {
+            (VariantShape::Newtype(schema), VariantShape::Newtype(other)) => {
+                VariantShape::Newtype(Box::new(schema.merge(*other)))
+            },
+            (VariantShape::Tuple(schemas), VariantShape::Tuple(others)) => {
+                VariantShape::Tuple(merge_all(schemas, others))
+            },
+            (VariantShape::Struct(fields), VariantShape::Struct(others)) => {
+                VariantShape::Struct(merge_fields(fields, others))
+            },
+            (shape, _) => shape,
+        }
+    }
+}
+
+fn merge_all(
+    schemas: Vec<Schema>,
+    others: Vec<Schema>,
+) -> Vec<Schema> {
+    schemas
+        .into_iter()
+        .zip(others)
+        .map(|(schema, other)| schema.merge(other))
+        .collect()
+}
+
+fn merge_fields(
+    fields: Vec<Field>,
+    others: Vec<Field>,
+) -> Vec<Field> {
+    fields
+        .into_iter()
+        .zip(others)
+        .map(|(field, other)| Field {
+            name: field.name,
+            schema: field.schema.merge(other.schema),
+        })
+        .collect()
+}
+
+fn named_fields(
+    names: &[&str],
+    schemas: Vec<Schema>,
+) -> Vec<Field> {
+    names
+        .iter()
+        .zip(schemas)
+        .map(|(name, schema)| Field {
+            name: (*name).to_string(),
+            schema,
+        })
+        .collect()
+}
+
+// This tracks which variants of each enum have been traced.  Enums are
+// identified by their position in the tree of the traced type, since the
+// same enum may appear in several places.  A variant is pending if other
+// enums within it may still have variants which have not been traced.
+struct Progress {
+    deferred: Vec<bool>,
+    explored: Vec<bool>,
+    pending: Vec<bool>,
+}
+
+// This is a choice of variant made for an enum while tracing.
+struct Choice {
+    path: Vec<usize>,
+    variant: usize,
+    new: bool,
+}
+
+#[derive(Default)]
+struct State {
+    choices: Vec<Choice>,
+    enums: HashMap<Vec<usize>, Progress>,
+    safe_variants: HashMap<&'static str, usize>,
+    stack: Vec<&'static str>,
+    too_deep: bool,
+    value_only: usize,
+}
+
+// This deserializer produces placeholder values while recording the calls
+// made on it.  When a type is found to contain itself, the inner occurrence
+// is recorded as a reference, and a value for it is made without recording
+// anything, choosing the simplest values which end the recursion.
+struct Tracer<'a> {
+    state: &'a mut State,
+    path: Vec<usize>,
+    schema: Option<Schema>,
+}
+
+impl Tracer<'_> {
+    fn child(
+        &mut self,
+        segment: usize,
+    ) -> Tracer<'_> {
+        let mut path = self.path.clone();
+        path.push(segment);
+        Tracer {
+            state: &mut *self.state,
+            path,
+            schema: None,
+        }
+    }
+
+    fn choose_variant(
+        &mut self,
+        name: &'static str,
+        variants: &'static [&'static str],
+    ) -> usize {
+        // Enums with pinned ids have unnamed gaps in their table of
+        // variants, which are never chosen.
+        let count = variants.len();
+        let named = |index: &usize| !variants[*index].is_empty();
+        let state = &mut *self.state;
+        let fallback = state
+            .safe_variants
+            .get(name)
+            .copied()
+            .or_else(|| (0..count).find(named))
+            .unwrap_or(0);
+        if state.value_only > 0 {
+            return fallback;
+        }
+        let progress =
+            state.enums.entry(self.path.clone()).or_insert_with(|| Progress {
+                deferred: vec![false; count],
+                explored: vec![false; count],
+                pending: vec![false; count],
+            });
+        let unexplored = (0..count).filter(named).find(|&index| {
+            !progress.explored[index] && !progress.deferred[index]
+        });
+        let pending = (0..count).filter(named).find(|&index| {
+            progress.pending[index] && !progress.deferred[index]
+        });
+        let variant = unexplored.or(pending).unwrap_or(fallback);
+        state.choices.push(Choice {
+            path: self.path.clone(),
+            variant,
+            new: unexplored.is_some(),
+        });
+        variant
+    }
+
+    fn enter<F, R>(
+        &mut self,
+        name: &'static str,
+        f: F,
+    ) -> Result<R>
+    where
+        F: FnOnce(&mut Self) -> Result<R>,
+    {
+        if self.state.stack.len() >= MAX_DEPTH {
+            self.state.too_deep = true;
+            return Err(Error::RecursionUnbounded);
+        }
+        let recursive =
+            self.state.value_only == 0 && self.state.stack.contains(&name);
+        if recursive {
+            self.schema = Some(Schema::Ref {
+                name: name.to_string(),
+            });
+            self.state.value_only += 1;
+        }
+        self.state.stack.push(name);
+        let result = f(self);
+        self.state.stack.pop();
+        if recursive {
+            self.state.value_only -= 1;
+        }
+        result
+    }
+
+    fn record(
+        &mut self,
+        schema: Schema,
+    ) {
+        if self.state.value_only == 0 {
+            self.schema = Some(schema);
+        }
+    }
+
+    fn trace<T>(
+        &mut self,
+        segment: usize,
+        seed: T,
+    ) -> Result<(T::Value, Schema)>
+    where
+        T: serde::de::DeserializeSeed<'static>,
+    {
+        let mut tracer = self.child(segment);
+        let value = seed.deserialize(&mut tracer)?;
+        Ok((value, tracer.schema.unwrap_or(Schema::Unit)))
+    }
+
+    fn trace_elements<V>(
+        &mut self,
+        len: usize,
+        visitor: V,
+    ) -> Result<(V::Value, Vec<Schema>)>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        struct Elements<'a, 'b> {
+            tracer: &'a mut Tracer<'b>,
+            len: usize,
+            schemas: Vec<Schema>,
+        }
+
+        impl serde::de::SeqAccess<'static> for Elements<'_, '_> {
+            type Error = Error;
+
+            fn next_element_seed<T>(
+                &mut self,
+                seed: T,
+            ) -> Result<Option<T::Value>>
+            where
+                T: serde::de::DeserializeSeed<'static>,
+            {
+                if self.schemas.len() == self.len {
+                    return Ok(None);
+                }
+                let (value, schema) =
+                    self.tracer.trace(self.schemas.len(), seed)?;
+                self.schemas.push(schema);
+                Ok(Some(value))
+            }
+
+            fn size_hint(&self) -> Option<usize> {
+                Some(self.len - self.schemas.len())
+            }
+        }
+
+        let mut elements = Elements {
+            tracer: self,
+            len,
+            schemas: Vec::new(),
+        };
+        let value = visitor.visit_seq(&mut elements)?;
+        Ok((value, elements.schemas))
+    }
+}
+
+// This gives the visitor of an enum the variant chosen by the tracer, and
+// records the contents of that variant.
+struct Enum<'a, 'b> {
+    tracer: &'a mut Tracer<'b>,
+    variant: usize,
+    shape: &'a mut Option<VariantShape>,
+}
+
+impl serde::de::EnumAccess<'static> for Enum<'_, '_> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant)>
+    where
+        V: serde::de::DeserializeSeed<'static>,
+    {
+        Ok((
+            seed.deserialize(serde::de::IntoDeserializer::into_deserializer(
+                self.variant,
+            ))?,
+            self,
+        ))
+    }
+}
+
+impl serde::de::VariantAccess<'static> for Enum<'_, '_> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        *self.shape = Some(VariantShape::Unit);
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(
+        self,
+        seed: T,
+    ) -> Result<T::Value>
+    where
+        T: serde::de::DeserializeSeed<'static>,
+    {
+        let (value, schema) = self.tracer.trace(self.variant, seed)?;
+        *self.shape = Some(VariantShape::Newtype(Box::new(schema)));
+        Ok(value)
+    }
+
+    fn tuple_variant<V>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        let (value, schemas) =
+            self.tracer.child(self.variant).trace_elements(len, visitor)?;
+        *self.shape = Some(VariantShape::Tuple(schemas));
+        Ok(value)
+    }
+
+    fn struct_variant<V>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        let (value, schemas) = self
+            .tracer
+            .child(self.variant)
+            .trace_elements(fields.len(), visitor)?;
+        *self.shape = Some(VariantShape::Struct(named_fields(fields, schemas)));
+        Ok(value)
+    }
+}
+
+#[allow(clippy::missing_errors_doc)]
+impl serde::Deserializer<'static> for &mut Tracer<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V>(
+        self,
+        _visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        Err(Error::TypeUnknown)
+    }
+
+    fn deserialize_bool<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        self.record(Schema::Bool);
+        visitor.visit_bool(false)
+    }
+
+    fn deserialize_i8<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        self.record(Schema::I8);
+        visitor.visit_i8(0)
+    }
+
+    fn deserialize_i16<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        self.record(Schema::I16);
+        visitor.visit_i16(0)
+    }
+
+    fn deserialize_i32<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        self.record(Schema::I32);
+        visitor.visit_i32(0)
+    }
+
+    fn deserialize_i64<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        self.record(Schema::I64);
+        visitor.visit_i64(0)
+    }
+
+    fn deserialize_u8<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        self.record(Schema::U8);
+        visitor.visit_u8(0)
+    }
+
+    fn deserialize_u16<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        self.record(Schema::U16);
+        visitor.visit_u16(0)
+    }
+
+    fn deserialize_u32<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        self.record(Schema::U32);
+        visitor.visit_u32(0)
+    }
+
+    fn deserialize_u64<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        self.record(Schema::U64);
+        visitor.visit_u64(0)
+    }
+
+    fn deserialize_f32<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        self.record(Schema::F32);
+        visitor.visit_f32(0.0)
+    }
+
+    fn deserialize_f64<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        self.record(Schema::F64);
+        visitor.visit_f64(0.0)
+    }
+
+    fn deserialize_char<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        self.record(Schema::Char);
+        visitor.visit_char('\0')
+    }
+
+    fn deserialize_str<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        self.record(Schema::Str);
+        visitor.visit_borrowed_str("")
+    }
+
+    fn deserialize_string<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        // An empty slice is given at the widest alignment which a
+        // `BorrowedSlice` may require, so that it can be borrowed as one.
+        #[repr(align(16))]
+        struct Aligned([u8; 0]);
+        static EMPTY: Aligned = Aligned([]);
+        self.record(Schema::Bytes);
+        visitor.visit_borrowed_bytes(&EMPTY.0)
+    }
+
+    fn deserialize_byte_buf<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        if self.state.value_only > 0 {
+            return visitor.visit_none();
+        }
+        let mut tracer = self.child(0);
+        let value = visitor.visit_some(&mut tracer)?;
+        let schema = tracer.schema.unwrap_or(Schema::Unit);
+        self.record(Schema::Option(Box::new(schema)));
+        Ok(value)
+    }
+
+    fn deserialize_unit<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        self.record(Schema::Unit);
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        self.record(Schema::UnitStruct {
+            name: name.to_string(),
+        });
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        self.enter(name, |tracer| {
+            let mut inner = tracer.child(0);
+            let value = visitor.visit_newtype_struct(&mut inner)?;
+            let schema = inner.schema.unwrap_or(Schema::Unit);
+            tracer.record(Schema::NewtypeStruct {
+                name: name.to_string(),
+                value: Box::new(schema),
+            });
+            Ok(value)
+        })
+    }
+
+    fn deserialize_seq<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        let len = usize::from(self.state.value_only == 0);
+        let (value, mut schemas) = self.trace_elements(len, visitor)?;
+        self.record(Schema::Seq(Box::new(
+            schemas.pop().unwrap_or(Schema::Unit),
+        )));
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        let (value, schemas) = self.trace_elements(len, visitor)?;
+        self.record(Schema::Tuple(schemas));
+        Ok(value)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        self.enter(name, |tracer| {
+            let (value, schemas) = tracer.trace_elements(len, visitor)?;
+            tracer.record(Schema::TupleStruct {
+                name: name.to_string(),
+                fields: schemas,
+            });
+            Ok(value)
+        })
+    }
+
+    fn deserialize_map<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        struct Entries<'a, 'b> {
+            tracer: &'a mut Tracer<'b>,
+            len: usize,
+            key: Option<Schema>,
+            value: Option<Schema>,
+        }
+
+        impl serde::de::MapAccess<'static> for Entries<'_, '_> {
+            type Error = Error;
+
+            fn next_key_seed<K>(
+                &mut self,
+                seed: K,
+            ) -> Result<Option<K::Value>>
+            where
+                K: serde::de::DeserializeSeed<'static>,
+            {
+                self.len
+                    .checked_sub(1)
+                    .map(|_| {
+                        let (key, schema) = self.tracer.trace(0, seed)?;
+                        self.key = Some(schema);
+                        Ok(key)
+                    })
+                    .transpose()
+            }
+
+            fn next_value_seed<V>(
+                &mut self,
+                seed: V,
+            ) -> Result<V::Value>
+            where
+                V: serde::de::DeserializeSeed<'static>,
+            {
+                self.len -= 1;
+                let (value, schema) = self.tracer.trace(1, seed)?;
+                self.value = Some(schema);
+                Ok(value)
+            }
+
+            fn size_hint(&self) -> Option<usize> {
+                Some(self.len)
+            }
+        }
+
+        let mut entries = Entries {
+            len: usize::from(self.state.value_only == 0),
+            tracer: &mut *self,
+            key: None,
+            value: None,
+        };
+        let value = visitor.visit_map(&mut entries)?;
+        let key = entries.key.take().unwrap_or(Schema::Unit);
+        let schema = entries.value.take().unwrap_or(Schema::Unit);
+        self.record(Schema::Map {
+            key: Box::new(key),
+            value: Box::new(schema),
+        });
+        Ok(value)
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        self.enter(name, |tracer| {
+            let (value, schemas) =
+                tracer.trace_elements(fields.len(), visitor)?;
+            tracer.record(Schema::Struct {
+                name: name.to_string(),
+                fields: named_fields(fields, schemas),
+            });
+            Ok(value)
+        })
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        self.enter(name, |tracer| {
+            let variant = tracer.choose_variant(name, variants);
+            let mut shape = None;
+            let value = visitor.visit_enum(Enum {
+                tracer: &mut *tracer,
+                variant,
+                shape: &mut shape,
+            })?;
+            if tracer.state.value_only > 0 {
+                return Ok(value);
+            }
+            let variants = shape
+                .map(|shape| {
+                    if !shape.has_ref() {
+                        tracer
+                            .state
+                            .safe_variants
+                            .entry(name)
+                            .or_insert(variant);
+                    }
+                    Variant {
+                        name: variants
+                            .get(variant)
+                            .copied()
+                            .unwrap_or_default()
+                            .to_string(),
+                        index: u32::try_from(variant).unwrap_or(u32::MAX),
+                        shape,
+                    }
+                })
+                .into_iter()
+                .collect();
+            tracer.record(Schema::Enum {
+                name: name.to_string(),
+                variants,
+            });
+            Ok(value)
+        })
+    }
+
+    fn deserialize_identifier<V>(
+        self,
+        _visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        Err(Error::IdentifierUnknown)
+    }
+
+    fn deserialize_ignored_any<V>(
+        self,
+        _visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        Err(Error::TypeUnknown)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/// This function is used to determine the layout in which values of a type
+/// are encoded, by driving the type's `Deserialize` implementation with a
+/// deserializer which records each call made on it.
+///
+/// Placeholder values are supplied while tracing: zero for numbers, empty
+/// text, present optional values, and sequences and maps with one element.
+/// The type is traced as many times as it takes to visit every variant of
+/// every enum it contains.
+///
+/// # Errors
+///
+/// [`Error::RecursionUnbounded`] is returned if the type contains itself
+/// in a way which cannot end, such as a struct with a boxed field of its
+/// own type.  Any error returned by the type's `Deserialize` implementation
+/// in response to a placeholder value, or to a call which this format does
+/// not support, is passed through unchanged.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate serialization;
+/// # extern crate serde;
+/// use serialization::{
+///     schema_of,
+///     Field,
+///     Schema,
+/// };
+///
+/// #[derive(serde::Deserialize)]
+/// struct Point {
+///     x: i32,
+///     label: Option<String>,
+/// }
+///
+/// assert_eq!(
+///     Schema::Struct {
+///         name: String::from("Point"),
+///         fields: vec![
+///             Field {
+///                 name: String::from("x"),
+///                 schema: Schema::I32,
+///             },
+///             Field {
+///                 name: String::from("label"),
+///                 schema: Schema::Option(Box::new(Schema::Str)),
+///             },
+///         ],
+///     },
+///     schema_of::<Point>().unwrap()
+/// );
+/// ```
+///
+/// [`Error::RecursionUnbounded`]: enum.Error.html#variant.RecursionUnbounded
+pub fn schema_of<T>() -> Result<Schema>
+where
+    T: serde::Deserialize<'static>,
+{
+    let mut state = State::default();
+    let mut schema: Option<Schema> = None;
+    loop {
+        state.too_deep = false;
+        let mut tracer = Tracer {
+            state: &mut state,
+            path: Vec::new(),
+            schema: None,
+        };
+        let result = T::deserialize(&mut tracer);
+        let run_schema = tracer.schema.unwrap_or(Schema::Unit);
+        let choices = std::mem::take(&mut state.choices);
+        let discovered = choices.iter().any(|choice| choice.new);
+        if let Err(error) = result {
+            // A run which recursed without end is retried with different
+            // variants, since variants traced later may provide the way to
+            // end the recursion.
+            if !state.too_deep || !discovered {
+                return Err(error);
+            }
+            for choice in choices.iter().filter(|choice| choice.new) {
+                if let Some(progress) = state.enums.get_mut(&choice.path) {
+                    progress.deferred[choice.variant] = true;
+                }
+            }
+            continue;
+        }
+        let merged = match schema {
+            Some(schema) => schema.merge(run_schema),
+            None => run_schema,
+        };
+        for choice in &choices {
+            let mut prefix = choice.path.clone();
+            prefix.push(choice.variant);
+            let pending = choices
+                .iter()
+                .any(|other| other.new && other.path.starts_with(&prefix));
+            if let Some(progress) = state.enums.get_mut(&choice.path) {
+                progress.explored[choice.variant] = true;
+                progress.pending[choice.variant] = pending;
+            }
+        }
+        if discovered {
+            // Any variant found while tracing this run may provide the way
+            // to end recursion in a variant which was deferred.
+            for progress in state.enums.values_mut() {
+                progress.deferred.iter_mut().for_each(|flag| *flag = false);
+            }
+        } else if !state
+            .enums
+            .values()
+            .any(|progress| progress.pending.contains(&true))
+        {
+            let deferred = state
+                .enums
+                .values()
+                .any(|progress| progress.deferred.contains(&true));
+            return if deferred {
+                Err(Error::RecursionUnbounded)
+            } else {
+                Ok(merged)
+            };
+        }
+        schema = Some(merged);
+    }
+}
+
+// Return the name of the type with the given schema, if it is a named type,
+// which is the name by which references to it are made.
+pub(crate) fn type_name(schema: &Schema) -> Option<&str> {
+    match schema {
+        Schema::UnitStruct {
+            name,
+        }
+        | Schema::NewtypeStruct {
+            name,
+            ..
+        }
+        | Schema::TupleStruct {
+            name,
+            ..
+        }
+        | Schema::Struct {
+            name,
+            ..
+        }
+        | Schema::Enum {
+            name,
+            ..
+        } => Some(name),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        from_bytes,
+        to_bytes,
+    };
+    use std::collections::HashMap;
+
+    fn field(
+        name: &str,
+        schema: Schema,
+    ) -> Field {
+        Field {
+            name: name.to_string(),
+            schema,
+        }
+    }
+
+    fn variant(
+        name: &str,
+        index: u32,
+        shape: VariantShape,
+    ) -> Variant {
+        Variant {
+            name: name.to_string(),
+            index,
+            shape,
+        }
+    }
+
+    #[test]
+    fn primitives() {
+        assert_eq!(Schema::U16, schema_of::<u16>().unwrap());
+        assert_eq!(Schema::Str, schema_of::<String>().unwrap());
+        assert_eq!(Schema::Str, schema_of::<&str>().unwrap());
+        assert_eq!(Schema::Bytes, schema_of::<&[u8]>().unwrap());
+        assert_eq!(
+            Schema::Tuple(vec![Schema::Bool, Schema::F64, Schema::Char]),
+            schema_of::<(bool, f64, char)>().unwrap()
+        );
+        assert_eq!(
+            Schema::Tuple(vec![Schema::U8; 4]),
+            schema_of::<std::net::Ipv4Addr>().unwrap()
+        );
+    }
+
+    #[test]
+    fn containers() {
+        #[derive(serde::Deserialize)]
+        #[allow(dead_code)]
+        struct Record {
+            tags: Option<Vec<u8>>,
+            counts: HashMap<String, i64>,
+            id: Id,
+            pair: Pair,
+            marker: Marker,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[allow(dead_code)]
+        struct Id(u32);
+
+        #[derive(serde::Deserialize)]
+        #[allow(dead_code)]
+        struct Pair(i8, i16);
+
+        #[derive(serde::Deserialize)]
+        struct Marker;
+
+        assert_eq!(
+            Schema::Struct {
+                name: String::from("Record"),
+                fields: vec![
+                    field(
+                        "tags",
+                        Schema::Option(Box::new(Schema::Seq(Box::new(
+                            Schema::U8
+                        ))))
+                    ),
+                    field("counts", Schema::Map {
+                        key: Box::new(Schema::Str),
+                        value: Box::new(Schema::I64),
+                    }),
+                    field("id", Schema::NewtypeStruct {
+                        name: String::from("Id"),
+                        value: Box::new(Schema::U32),
+                    }),
+                    field("pair", Schema::TupleStruct {
+                        name: String::from("Pair"),
+                        fields: vec![Schema::I8, Schema::I16],
+                    }),
+                    field("marker", Schema::UnitStruct {
+                        name: String::from("Marker"),
+                    }),
+                ],
+            },
+            schema_of::<Record>().unwrap()
+        );
+    }
+
+    #[test]
+    fn enums() {
+        #[derive(serde::Deserialize)]
+        #[allow(dead_code)]
+        enum Shape {
+            Empty,
+            Circle(f32),
+            Line(Point, Point),
+            Labeled {
+                label: String,
+                inner: Option<Box<Inner>>,
+            },
+        }
+
+        #[derive(serde::Deserialize)]
+        #[allow(dead_code)]
+        struct Point(u8, u8);
+
+        #[derive(serde::Deserialize)]
+        #[allow(dead_code)]
+        enum Inner {
+            A,
+            B(bool),
+        }
+
+        let point = Schema::TupleStruct {
+            name: String::from("Point"),
+            fields: vec![Schema::U8, Schema::U8],
+        };
+        assert_eq!(
+            Schema::Enum {
+                name: String::from("Shape"),
+                variants: vec![
+                    variant("Empty", 0, VariantShape::Unit),
+                    variant(
+                        "Circle",
+                        1,
+                        VariantShape::Newtype(Box::new(Schema::F32))
+                    ),
+                    variant(
+                        "Line",
+                        2,
+                        VariantShape::Tuple(vec![point.clone(), point])
+                    ),
+                    variant(
+                        "Labeled",
+                        3,
+                        VariantShape::Struct(vec![
+                            field("label", Schema::Str),
+                            field(
+                                "inner",
+                                Schema::Option(Box::new(Schema::Enum {
+                                    name: String::from("Inner"),
+                                    variants: vec![
+                                        variant("A", 0, VariantShape::Unit),
+                                        variant(
+                                            "B",
+                                            1,
+                                            VariantShape::Newtype(Box::new(
+                                                Schema::Bool
+                                            ))
+                                        ),
+                                    ],
+                                }))
+                            ),
+                        ])
+                    ),
+                ],
+            },
+            schema_of::<Shape>().unwrap()
+        );
+    }
+
+    #[test]
+    fn recursive_struct() {
+        #[derive(serde::Deserialize)]
+        #[allow(dead_code)]
+        struct Node {
+            value: i32,
+            next: Option<Box<Node>>,
+            children: Vec<Node>,
+        }
+
+        assert_eq!(
+            Schema::Struct {
+                name: String::from("Node"),
+                fields: vec![
+                    field("value", Schema::I32),
+                    field(
+                        "next",
+                        Schema::Option(Box::new(Schema::Ref {
+                            name: String::from("Node"),
+                        }))
+                    ),
+                    field(
+                        "children",
+                        Schema::Seq(Box::new(Schema::Ref {
+                            name: String::from("Node"),
+                        }))
+                    ),
+                ],
+            },
+            schema_of::<Node>().unwrap()
+        );
+    }
+
+    #[test]
+    fn recursive_enum() {
+        #[derive(serde::Deserialize)]
+        #[allow(dead_code)]
+        enum Expr {
+            Add(Box<Expr>, Box<Expr>),
+            Literal(i64),
+        }
+
+        let expr = Schema::Ref {
+            name: String::from("Expr"),
+        };
+        assert_eq!(
+            Schema::Enum {
+                name: String::from("Expr"),
+                variants: vec![
+                    variant(
+                        "Add",
+                        0,
+                        VariantShape::Tuple(vec![expr.clone(), expr])
+                    ),
+                    variant(
+                        "Literal",
+                        1,
+                        VariantShape::Newtype(Box::new(Schema::I64))
+                    ),
+                ],
+            },
+            schema_of::<Expr>().unwrap()
+        );
+    }
+
+    #[test]
+    fn recursion_without_end() {
+        #[derive(serde::Deserialize)]
+        #[allow(dead_code)]
+        struct Loop {
+            next: Box<Loop>,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[allow(dead_code)]
+        enum Spiral {
+            Left(Box<Spiral>),
+            Right(Box<Spiral>),
+        }
+
+        assert!(matches!(schema_of::<Loop>(), Err(Error::RecursionUnbounded)));
+        assert!(matches!(
+            schema_of::<Spiral>(),
+            Err(Error::RecursionUnbounded)
+        ));
+    }
+
+    #[test]
+    fn unsupported() {
+        assert!(matches!(schema_of::<i128>(), Err(Error::Message(_))));
+    }
+
+    #[test]
+    fn schema_round_trip() {
+        let schema = schema_of::<(Option<u64>, Vec<String>)>().unwrap();
+        let serialization = to_bytes(&schema);
+        assert!(serialization.is_ok());
+        let serialization = serialization.unwrap();
+        let deserialization: Result<Schema> = from_bytes(&serialization);
+        assert!(deserialization.is_ok());
+        assert_eq!(schema, deserialization.unwrap());
+    }
+}
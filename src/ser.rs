@@ -1,7 +1,59 @@
 use super::{
+    appendable,
+    borrowed_slice,
+    shared::{
+        self,
+        Table,
+    },
     Error,
     Result,
 };
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    sync::Arc,
+};
+
+// This is the most bytes needed to encode a 64-bit integer, at seven bits
+// per byte.
+const MAX_VARINT_LENGTH: usize = 10;
+
+// Return the number of bytes in the variable-length encoding of the given
+// unsigned value.
+fn varint_length(v: usize) -> usize {
+    let bits = usize::BITS - v.leading_zeros();
+    (bits.max(1) as usize).div_ceil(7)
+}
+
+// This is the table of strings encoded so far in the interned layout.  The
+// entries are kept in order as well as by text, so that those made within
+// an appendable struct can be dropped at its end, since a decoder skipping
+// fields of the struct would not see them.
+#[derive(Default)]
+struct Strings {
+    entries: Vec<Arc<str>>,
+    indices: HashMap<Arc<str>, usize>,
+}
+
+impl Strings {
+    fn truncate(
+        &mut self,
+        len: usize,
+    ) {
+        for entry in self.entries.drain(len.min(self.entries.len())..) {
+            self.indices.remove(&entry);
+        }
+    }
+}
+
+// This is the table of offsets of a struct or sequence being encoded in the
+// indexed layout, which is filled in as each of its elements is encoded.
+struct Offsets {
+    table: usize,
+    data: usize,
+    len: usize,
+    next: usize,
+}
 
 /// This type implements [`serde::Serializer`] in order to encode data
 /// into a sequence of bytes.
@@ -10,42 +62,242 @@ use super::{
 /// https://docs.rs/serde/1.0/serde/trait.Serializer.html
 pub struct Serializer<'ser> {
     buffer: &'ser mut Vec<u8>,
+    alignment: Option<usize>,
+    appendable: Option<usize>,
+    strings: Option<Strings>,
+    shared: Option<Table>,
+    offsets: Option<Vec<Offsets>>,
 }
 
 impl<'ser> Serializer<'ser> {
-    fn new(buffer: &'ser mut Vec<u8>) -> Self {
+    /// Return the buffer into which this serializer has been encoding.
+    #[must_use]
+    pub fn into_inner(self) -> &'ser mut Vec<u8> {
+        self.buffer
+    }
+
+    /// Return `true` if the buffer of this serializer holds no bytes.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// Return the number of bytes in the buffer of this serializer,
+    /// including any that were there before it was constructed.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Return a new serializer that appends encoded values to the given
+    /// buffer, leaving any bytes already in it untouched.
+    pub fn new(buffer: &'ser mut Vec<u8>) -> Self {
         Self {
             buffer,
+            alignment: None,
+            appendable: None,
+            strings: None,
+            shared: None,
+            offsets: None,
         }
     }
 
+    /// Return a new serializer that appends encoded values to the given
+    /// buffer, as [`new`] does, but encodes structs and sequences in the
+    /// indexed layout described for [`to_bytes_indexed`].
+    ///
+    /// [`new`]: #method.new
+    /// [`to_bytes_indexed`]: fn.to_bytes_indexed.html
+    pub fn new_indexed(buffer: &'ser mut Vec<u8>) -> Self {
+        Self {
+            offsets: Some(Vec::new()),
+            ..Self::new(buffer)
+        }
+    }
+
+    /// Return a new serializer that appends encoded values to the given
+    /// buffer, as [`new`] does, but encodes strings in the interned layout
+    /// described for [`to_bytes_interned`].  Strings are shared between all
+    /// the values encoded by the serializer.
+    ///
+    /// [`new`]: #method.new
+    /// [`to_bytes_interned`]: fn.to_bytes_interned.html
+    pub fn new_interned(buffer: &'ser mut Vec<u8>) -> Self {
+        Self {
+            strings: Some(Strings::default()),
+            ..Self::new(buffer)
+        }
+    }
+
+    // Encode the given bytes in the aligned-array layout of
+    // `BorrowedSlice`, with padding so that the data starts at a multiple
+    // of the given alignment from the start of the buffer.  Adding padding
+    // can lengthen the length prefix, but only once, so a fitting amount
+    // of padding is always found below twice the alignment.
     #[allow(clippy::cast_possible_truncation)]
-    fn serialize_usize(
+    fn serialize_aligned(
         &mut self,
-        mut v: usize,
+        alignment: usize,
+        v: &[u8],
     ) {
-        let mut stack = Vec::new();
-        stack.reserve(8);
-        while v & !0x7F != 0 {
-            stack.push((v & 0x7F) as u8);
-            v >>= 7;
-            if v == 0 {
-                break;
-            }
+        let mut padding = 0;
+        while (self.buffer.len()
+            + varint_length(2 + padding + v.len())
+            + 2
+            + padding)
+            % alignment
+            != 0
+        {
+            padding += 1;
         }
-        let more = if stack.is_empty() {
-            0x00
+        self.serialize_usize(2 + padding + v.len());
+        self.buffer.push(borrowed_slice::NATIVE_BYTE_ORDER);
+        self.buffer.push(padding as u8);
+        self.buffer.resize(self.buffer.len() + padding, 0);
+        self.buffer.extend_from_slice(v);
+    }
+
+    // Encode the given value, using the appendable layout if it is a
+    // struct.  A struct found at the same position in the buffer is the
+    // wrapped value itself, rather than a struct within it.
+    fn serialize_appendable<T>(
+        &mut self,
+        value: &T,
+    ) -> Result<()>
+    where
+        T: ?Sized + serde::Serialize,
+    {
+        self.appendable = Some(self.buffer.len());
+        let result = value.serialize(&mut *self);
+        self.appendable = None;
+        result
+    }
+
+    // Encode the given string as a reference to an earlier copy of it, if
+    // there is one, or as a new entry in the table of strings otherwise.
+    // The lowest bit of the prefix tells which.
+    fn serialize_interned(
+        &mut self,
+        v: &str,
+    ) -> bool {
+        let Some(strings) = &mut self.strings else {
+            return false;
+        };
+        if let Some(&index) = strings.indices.get(v) {
+            self.serialize_usize((index << 1) | 1);
         } else {
-            0x80
+            let entry: Arc<str> = Arc::from(v);
+            strings.indices.insert(Arc::clone(&entry), strings.entries.len());
+            strings.entries.push(entry);
+            self.serialize_usize(v.len() << 1);
+            self.buffer.extend(v.as_bytes());
+        }
+        true
+    }
+
+    // Encode the given value in the shared layout, as a reference to an
+    // earlier encoding of it if there is one.  The value is identified by
+    // its address, and the pointer to it is handed over by its `Shared`
+    // wrapper, so that it is held until the serializer is dropped.
+    fn serialize_shared<T>(
+        &mut self,
+        value: &T,
+    ) -> Result<()>
+    where
+        T: ?Sized + serde::Serialize,
+    {
+        let pointer = shared::take();
+        let address = std::ptr::from_ref(value).cast::<u8>() as usize;
+        let table = self.shared.get_or_insert_with(Table::new);
+        if let Some(index) = table.find(address) {
+            self.serialize_usize(index + 1);
+            return Ok(());
+        }
+        table.push(address, pointer);
+        self.buffer.push(0x00);
+        value.serialize(&mut *self)
+    }
+
+    // Begin the table of offsets of a struct or sequence with the given
+    // number of elements, if encoding in the indexed layout.  The table is
+    // filled with zeros until the elements are encoded.
+    fn begin_offsets(
+        &mut self,
+        len: usize,
+    ) {
+        if let Some(offsets) = &mut self.offsets {
+            let table = self.buffer.len();
+            self.buffer.resize(table + 4 * len, 0);
+            offsets.push(Offsets {
+                table,
+                data: self.buffer.len(),
+                len,
+                next: 0,
+            });
+        }
+    }
+
+    // Note the end of the element of the current struct or sequence which
+    // was just encoded in its table of offsets, if encoding in the indexed
+    // layout.
+    fn end_element(&mut self) -> Result<()> {
+        let Some(offsets) =
+            self.offsets.as_mut().and_then(|offsets| offsets.last_mut())
+        else {
+            return Ok(());
         };
-        self.buffer.push((v as u8) | more);
-        while !stack.is_empty() {
-            let mut next = stack.pop().unwrap();
-            if !stack.is_empty() {
-                next |= 0x80;
-            }
-            self.buffer.push(next);
+        if offsets.next == offsets.len {
+            return Err(Error::LengthRequired);
+        }
+        let end = u32::try_from(self.buffer.len() - offsets.data)
+            .map_err(|_| Error::IndexedTooLarge)?;
+        let entry = offsets.table + 4 * offsets.next;
+        self.buffer[entry..entry + 4].copy_from_slice(&end.to_be_bytes());
+        offsets.next += 1;
+        Ok(())
+    }
+
+    // Finish the table of offsets of the current struct or sequence, if
+    // encoding in the indexed layout, checking that it had as many elements
+    // as it was begun with.
+    fn end_offsets(&mut self) -> Result<()> {
+        match self.offsets.as_mut().and_then(Vec::pop) {
+            Some(offsets) if offsets.next != offsets.len => {
+                Err(Error::LengthRequired)
+            },
+            _ => Ok(()),
+        }
+    }
+
+    fn serialize_usize(
+        &mut self,
+        v: usize,
+    ) {
+        self.serialize_varint(v as u64, 0x7F, 0x00);
+    }
+
+    // Encode the given value in groups of seven bits, most significant
+    // group first, with the high bit of each byte but the last set.  The
+    // first byte holds only the bits in `first_mask`, alongside `flags`.
+    #[allow(clippy::cast_possible_truncation)]
+    fn serialize_varint(
+        &mut self,
+        mut v: u64,
+        first_mask: u64,
+        flags: u8,
+    ) {
+        let mut scratch = [0_u8; MAX_VARINT_LENGTH];
+        let mut start = MAX_VARINT_LENGTH;
+        let mut more = 0x00;
+        while v & !first_mask != 0 {
+            start -= 1;
+            scratch[start] = ((v & 0x7F) as u8) | more;
+            more = 0x80;
+            v >>= 7;
         }
+        start -= 1;
+        scratch[start] = (v as u8) | flags | more;
+        self.buffer.extend_from_slice(&scratch[start..]);
     }
 }
 
@@ -54,7 +306,7 @@ impl<'a, 'ser> serde::Serializer for &'a mut Serializer<'ser> {
     type Ok = ();
     type SerializeMap = Self;
     type SerializeSeq = Self;
-    type SerializeStruct = Self;
+    type SerializeStruct = StructSerializer<'a, 'ser>;
     type SerializeStructVariant = Self;
     type SerializeTuple = Self;
     type SerializeTupleStruct = Self;
@@ -64,11 +316,7 @@ impl<'a, 'ser> serde::Serializer for &'a mut Serializer<'ser> {
         self,
         v: bool,
     ) -> Result<Self::Ok> {
-        self.buffer.push(if v {
-            1
-        } else {
-            0
-        });
+        self.buffer.push(u8::from(v));
         Ok(())
     }
 
@@ -95,39 +343,16 @@ impl<'a, 'ser> serde::Serializer for &'a mut Serializer<'ser> {
         self.serialize_i64(i64::from(v))
     }
 
-    #[allow(clippy::cast_possible_truncation)]
-    #[allow(clippy::cast_sign_loss)]
     fn serialize_i64(
         self,
         v: i64,
     ) -> Result<Self::Ok> {
-        let (sign, mut abs) = if v >= 0 {
-            (0x00_u8, v as u64)
-        } else {
-            (0x40_u8, (-v) as u64)
-        };
-        let mut stack = Vec::new();
-        stack.reserve(8);
-        while abs & !(0x3F_u64) != 0 {
-            stack.push((abs & 0x7F) as u8);
-            abs >>= 7;
-            if abs == 0 {
-                break;
-            }
-        }
-        let more = if stack.is_empty() {
-            0x00
+        let sign = if v >= 0 {
+            0x00_u8
         } else {
-            0x80
+            0x40_u8
         };
-        self.buffer.push((abs as u8) | sign | more);
-        while !stack.is_empty() {
-            let mut next = stack.pop().unwrap();
-            if !stack.is_empty() {
-                next |= 0x80;
-            }
-            self.buffer.push(next);
-        }
+        self.serialize_varint(v.unsigned_abs(), 0x3F, sign);
         Ok(())
     }
 
@@ -135,7 +360,7 @@ impl<'a, 'ser> serde::Serializer for &'a mut Serializer<'ser> {
         self,
         v: u8,
     ) -> Result<Self::Ok> {
-        self.buffer.push(v as u8);
+        self.buffer.push(v);
         Ok(())
     }
 
@@ -153,36 +378,27 @@ impl<'a, 'ser> serde::Serializer for &'a mut Serializer<'ser> {
         self.serialize_u64(u64::from(v))
     }
 
-    #[allow(clippy::cast_possible_truncation)]
     fn serialize_u64(
         self,
         v: u64,
     ) -> Result<Self::Ok> {
-        self.serialize_usize(v as usize);
+        self.serialize_varint(v, 0x7F, 0x00);
         Ok(())
     }
 
-    #[allow(clippy::cast_possible_truncation)]
     fn serialize_f32(
         self,
         v: f32,
     ) -> Result<Self::Ok> {
-        let v = unsafe { *(&v as *const f32).cast::<u32>() };
-        for i in (0..4).rev() {
-            self.buffer.push(((v >> (i * 8)) & 0xFF) as u8);
-        }
+        self.buffer.extend_from_slice(&v.to_be_bytes());
         Ok(())
     }
 
-    #[allow(clippy::cast_possible_truncation)]
     fn serialize_f64(
         self,
         v: f64,
     ) -> Result<Self::Ok> {
-        let v = unsafe { *(&v as *const f64).cast::<u64>() };
-        for i in (0..8).rev() {
-            self.buffer.push(((v >> (i * 8)) & 0xFF) as u8);
-        }
+        self.buffer.extend_from_slice(&v.to_be_bytes());
         Ok(())
     }
 
@@ -200,6 +416,9 @@ impl<'a, 'ser> serde::Serializer for &'a mut Serializer<'ser> {
         self,
         v: &str,
     ) -> Result<Self::Ok> {
+        if self.serialize_interned(v) {
+            return Ok(());
+        }
         let bytes = v.as_bytes();
         self.serialize_usize(v.len());
         self.buffer.extend(bytes);
@@ -210,6 +429,10 @@ impl<'a, 'ser> serde::Serializer for &'a mut Serializer<'ser> {
         self,
         v: &[u8],
     ) -> Result<Self::Ok> {
+        if let Some(alignment) = self.alignment.take() {
+            self.serialize_aligned(alignment, v);
+            return Ok(());
+        }
         self.serialize_usize(v.len());
         self.buffer.extend(v);
         Ok(())
@@ -220,12 +443,12 @@ impl<'a, 'ser> serde::Serializer for &'a mut Serializer<'ser> {
         Ok(())
     }
 
-    fn serialize_some<T: ?Sized>(
+    fn serialize_some<T>(
         self,
         value: &T,
     ) -> Result<Self::Ok>
     where
-        T: serde::Serialize,
+        T: ?Sized + serde::Serialize,
     {
         self.buffer.push(0x01);
         value.serialize(self)
@@ -251,18 +474,25 @@ impl<'a, 'ser> serde::Serializer for &'a mut Serializer<'ser> {
         <Self as serde::Serializer>::serialize_u32(self, variant_index)
     }
 
-    fn serialize_newtype_struct<T: ?Sized>(
+    fn serialize_newtype_struct<T>(
         self,
-        _name: &'static str,
+        name: &'static str,
         value: &T,
     ) -> Result<Self::Ok>
     where
-        T: serde::Serialize,
+        T: ?Sized + serde::Serialize,
     {
+        if name == appendable::NAME {
+            return self.serialize_appendable(value);
+        }
+        if name == shared::NAME {
+            return self.serialize_shared(value);
+        }
+        self.alignment = borrowed_slice::alignment(name);
         value.serialize(self)
     }
 
-    fn serialize_newtype_variant<T: ?Sized>(
+    fn serialize_newtype_variant<T>(
         self,
         _name: &'static str,
         variant_index: u32,
@@ -270,7 +500,7 @@ impl<'a, 'ser> serde::Serializer for &'a mut Serializer<'ser> {
         value: &T,
     ) -> Result<Self::Ok>
     where
-        T: serde::Serialize,
+        T: ?Sized + serde::Serialize,
     {
         self.serialize_usize(variant_index as usize);
         value.serialize(self)
@@ -281,7 +511,8 @@ impl<'a, 'ser> serde::Serializer for &'a mut Serializer<'ser> {
         len: Option<usize>,
     ) -> Result<Self::SerializeSeq> {
         len.map_or(Err(Error::LengthRequired), move |size| {
-            self.serialize_usize(size as usize);
+            self.serialize_usize(size);
+            self.begin_offsets(size);
             Ok(self)
         })
     }
@@ -290,6 +521,7 @@ impl<'a, 'ser> serde::Serializer for &'a mut Serializer<'ser> {
         self,
         _len: usize,
     ) -> Result<Self::SerializeTuple> {
+        self.appendable = None;
         Ok(self)
     }
 
@@ -298,6 +530,7 @@ impl<'a, 'ser> serde::Serializer for &'a mut Serializer<'ser> {
         _name: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleStruct> {
+        self.appendable = None;
         Ok(self)
     }
 
@@ -320,7 +553,7 @@ impl<'a, 'ser> serde::Serializer for &'a mut Serializer<'ser> {
         len: Option<usize>,
     ) -> Result<Self::SerializeMap> {
         len.map_or(Err(Error::LengthRequired), move |size| {
-            self.serialize_usize(size as usize);
+            self.serialize_usize(size);
             Ok(self)
         })
     }
@@ -328,9 +561,23 @@ impl<'a, 'ser> serde::Serializer for &'a mut Serializer<'ser> {
     fn serialize_struct(
         self,
         _name: &'static str,
-        _len: usize,
+        len: usize,
     ) -> Result<Self::SerializeStruct> {
-        Ok(self)
+        let start = self.buffer.len();
+        let appendable = self.appendable.take() == Some(start);
+        if appendable || self.offsets.is_some() {
+            self.serialize_usize(len);
+        }
+        self.begin_offsets(len);
+        let strings =
+            self.strings.as_ref().map_or(0, |strings| strings.entries.len());
+        let shared = self.shared.as_ref().map_or(0, Table::len);
+        Ok(StructSerializer {
+            ser: self,
+            start: appendable.then_some(start),
+            strings,
+            shared,
+        })
     }
 
     fn serialize_struct_variant(
@@ -346,28 +593,32 @@ impl<'a, 'ser> serde::Serializer for &'a mut Serializer<'ser> {
         )?;
         Ok(self)
     }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
 }
 
-impl<'a, 'ser> serde::ser::SerializeMap for &'a mut Serializer<'ser> {
+impl serde::ser::SerializeMap for &mut Serializer<'_> {
     type Error = Error;
     type Ok = ();
 
-    fn serialize_key<T: ?Sized>(
+    fn serialize_key<T>(
         &mut self,
         key: &T,
     ) -> Result<()>
     where
-        T: serde::Serialize,
+        T: ?Sized + serde::Serialize,
     {
         key.serialize(&mut **self)
     }
 
-    fn serialize_value<T: ?Sized>(
+    fn serialize_value<T>(
         &mut self,
         value: &T,
     ) -> Result<()>
     where
-        T: serde::Serialize,
+        T: ?Sized + serde::Serialize,
     {
         value.serialize(&mut **self)
     }
@@ -377,56 +628,82 @@ impl<'a, 'ser> serde::ser::SerializeMap for &'a mut Serializer<'ser> {
     }
 }
 
-impl<'a, 'ser> serde::ser::SerializeSeq for &'a mut Serializer<'ser> {
+impl serde::ser::SerializeSeq for &mut Serializer<'_> {
     type Error = Error;
     type Ok = ();
 
-    fn serialize_element<T: ?Sized>(
+    fn serialize_element<T>(
         &mut self,
         value: &T,
     ) -> Result<()>
     where
-        T: serde::Serialize,
+        T: ?Sized + serde::Serialize,
     {
-        value.serialize(&mut **self)
+        value.serialize(&mut **self)?;
+        self.end_element()
     }
 
     fn end(self) -> Result<Self::Ok> {
-        Ok(())
+        self.end_offsets()
     }
 }
 
-impl<'a, 'ser> serde::ser::SerializeStruct for &'a mut Serializer<'ser> {
+/// This type is used by [`Serializer`] to encode the fields of a struct.
+///
+/// [`Serializer`]: struct.Serializer.html
+pub struct StructSerializer<'a, 'ser> {
+    ser: &'a mut Serializer<'ser>,
+    start: Option<usize>,
+    strings: usize,
+    shared: usize,
+}
+
+impl serde::ser::SerializeStruct for StructSerializer<'_, '_> {
     type Error = Error;
     type Ok = ();
 
-    fn serialize_field<T: ?Sized>(
+    fn serialize_field<T>(
         &mut self,
         _key: &'static str,
         value: &T,
     ) -> Result<()>
     where
-        T: serde::Serialize,
+        T: ?Sized + serde::Serialize,
     {
-        value.serialize(&mut **self)
+        value.serialize(&mut *self.ser)?;
+        self.ser.end_element()
     }
 
     fn end(self) -> Result<Self::Ok> {
+        self.ser.end_offsets()?;
+        // The length of an appendable struct is only known once its fields
+        // are encoded, so it is inserted in front of them afterwards.
+        if let Some(start) = self.start {
+            let body = self.ser.buffer.split_off(start);
+            self.ser.serialize_usize(body.len());
+            self.ser.buffer.extend_from_slice(&body);
+            if let Some(strings) = &mut self.ser.strings {
+                strings.truncate(self.strings);
+            }
+            if let Some(shared) = &self.ser.shared {
+                shared.truncate(self.shared);
+            }
+        }
         Ok(())
     }
 }
 
-impl<'a, 'ser> serde::ser::SerializeStructVariant for &'a mut Serializer<'ser> {
+impl serde::ser::SerializeStructVariant for &mut Serializer<'_> {
     type Error = Error;
     type Ok = ();
 
-    fn serialize_field<T: ?Sized>(
+    fn serialize_field<T>(
         &mut self,
         _key: &'static str,
         value: &T,
     ) -> Result<()>
     where
-        T: serde::Serialize,
+        T: ?Sized + serde::Serialize,
     {
         value.serialize(&mut **self)
     }
@@ -436,16 +713,16 @@ impl<'a, 'ser> serde::ser::SerializeStructVariant for &'a mut Serializer<'ser> {
     }
 }
 
-impl<'a, 'ser> serde::ser::SerializeTuple for &'a mut Serializer<'ser> {
+impl serde::ser::SerializeTuple for &mut Serializer<'_> {
     type Error = Error;
     type Ok = ();
 
-    fn serialize_element<T: ?Sized>(
+    fn serialize_element<T>(
         &mut self,
         value: &T,
     ) -> Result<()>
     where
-        T: serde::Serialize,
+        T: ?Sized + serde::Serialize,
     {
         value.serialize(&mut **self)
     }
@@ -455,16 +732,16 @@ impl<'a, 'ser> serde::ser::SerializeTuple for &'a mut Serializer<'ser> {
     }
 }
 
-impl<'a, 'ser> serde::ser::SerializeTupleStruct for &'a mut Serializer<'ser> {
+impl serde::ser::SerializeTupleStruct for &mut Serializer<'_> {
     type Error = Error;
     type Ok = ();
 
-    fn serialize_field<T: ?Sized>(
+    fn serialize_field<T>(
         &mut self,
         value: &T,
     ) -> Result<()>
     where
-        T: serde::Serialize,
+        T: ?Sized + serde::Serialize,
     {
         value.serialize(&mut **self)
     }
@@ -474,16 +751,16 @@ impl<'a, 'ser> serde::ser::SerializeTupleStruct for &'a mut Serializer<'ser> {
     }
 }
 
-impl<'a, 'ser> serde::ser::SerializeTupleVariant for &'a mut Serializer<'ser> {
+impl serde::ser::SerializeTupleVariant for &mut Serializer<'_> {
     type Error = Error;
     type Ok = ();
 
-    fn serialize_field<T: ?Sized>(
+    fn serialize_field<T>(
         &mut self,
         value: &T,
     ) -> Result<()>
     where
-        T: serde::Serialize,
+        T: ?Sized + serde::Serialize,
     {
         value.serialize(&mut **self)
     }
@@ -503,13 +780,102 @@ impl<'a, 'ser> serde::ser::SerializeTupleVariant for &'a mut Serializer<'ser> {
 /// if it contains a mutex which is locked by a thread which has panicked.
 pub fn to_bytes<T>(value: &T) -> Result<Vec<u8>>
 where
-    T: serde::Serialize,
+    T: ?Sized + serde::Serialize,
 {
     let mut buffer = Vec::new();
     serde::Serialize::serialize(value, &mut Serializer::new(&mut buffer))?;
     Ok(buffer)
 }
 
+/// This function is used to encode a value onto the end of an existing
+/// sequence of bytes using the serializer implemented by this crate.
+/// Several values encoded one after another this way may be decoded in
+/// turn with [`Deserializer::iterate`].
+///
+/// [`Deserializer::iterate`]: crate::Deserializer::iterate
+///
+/// # Errors
+///
+/// This function may return the same kinds of errors as [`to_bytes`].
+/// If it does, `buffer` may hold part of the value's encoding.
+pub fn to_bytes_into<T>(
+    value: &T,
+    buffer: &mut Vec<u8>,
+) -> Result<()>
+where
+    T: ?Sized + serde::Serialize,
+{
+    serde::Serialize::serialize(value, &mut Serializer::new(buffer))
+}
+
+/// This function is used to encode a value into a sequence of bytes, as
+/// [`to_bytes`] does, except that each string which occurs more than once
+/// is only encoded in full the first time.
+///
+/// Every string is preceded by a variable-length integer.  If it is even,
+/// it is twice the length of the string, and the text follows as usual,
+/// becoming the next entry in a table of strings.  If it is odd, nothing
+/// follows, and it is one more than twice the index of an earlier entry in
+/// the table, which is repeated.  Entries made within an [`Appendable`]
+/// struct are removed from the table at its end.  The result must be
+/// decoded with [`from_bytes_interned`].
+///
+/// # Errors
+///
+/// This function returns the same errors as [`to_bytes`].
+///
+/// [`to_bytes`]: fn.to_bytes.html
+/// [`Appendable`]: struct.Appendable.html
+/// [`from_bytes_interned`]: fn.from_bytes_interned.html
+pub fn to_bytes_interned<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: ?Sized + serde::Serialize,
+{
+    let mut buffer = Vec::new();
+    serde::Serialize::serialize(
+        value,
+        &mut Serializer::new_interned(&mut buffer),
+    )?;
+    Ok(buffer)
+}
+
+/// This function is used to encode a value into a sequence of bytes, as
+/// [`to_bytes`] does, except that each struct and sequence is preceded by a
+/// table of offsets, so that any one of its elements can be found without
+/// decoding those before it, such as by a [`LazyView`].
+///
+/// A struct is encoded as the number of its fields, and a sequence as the
+/// number of its elements, as usual.  Then comes a table with a four-byte
+/// big-endian offset for each field or element, which is the number of
+/// bytes from the end of the table to the end of its encoding, and then
+/// the fields or elements themselves.  Sequences of bytes encoded by serde
+/// as sequences, such as `Vec<u8>`, get a table too, so they are better
+/// wrapped in `serde_bytes` or a [`BorrowedSlice`].  The result must be
+/// decoded with [`from_bytes_indexed`].
+///
+/// # Errors
+///
+/// [`Error::IndexedTooLarge`] is returned if a struct or sequence takes
+/// four gigabytes or more.  Otherwise this function returns the same errors
+/// as [`to_bytes`].
+///
+/// [`to_bytes`]: fn.to_bytes.html
+/// [`LazyView`]: struct.LazyView.html
+/// [`BorrowedSlice`]: struct.BorrowedSlice.html
+/// [`from_bytes_indexed`]: fn.from_bytes_indexed.html
+/// [`Error::IndexedTooLarge`]: enum.Error.html#variant.IndexedTooLarge
+pub fn to_bytes_indexed<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: ?Sized + serde::Serialize,
+{
+    let mut buffer = Vec::new();
+    serde::Serialize::serialize(
+        value,
+        &mut Serializer::new_indexed(&mut buffer),
+    )?;
+    Ok(buffer)
+}
+
 #[cfg(test)]
 #[allow(clippy::non_ascii_literal)]
 mod tests {
@@ -594,6 +960,10 @@ mod tests {
             (-2_000_000_000_i64, &[0xC7, 0xB9, 0xD6, 0xA8, 0x00][..]),
             (2_000_000_000_000_i64, &[0xBA, 0x9A, 0xCA, 0xA8, 0xC0, 0x00][..]),
             (-2_000_000_000_000_i64, &[0xFA, 0x9A, 0xCA, 0xA8, 0xC0, 0x00][..]),
+            (
+                i64::MIN,
+                &[0xC1, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x00][..],
+            ),
         ] {
             let serialization = to_bytes(value);
             assert!(serialization.is_ok());
@@ -898,7 +1268,7 @@ mod tests {
     }
 
     #[test]
-    #[allow(clippy::blacklisted_name)]
+    #[allow(clippy::disallowed_names)]
     fn serialize_struct_variant() {
         #[derive(serde::Serialize)]
         enum Foo {
@@ -916,4 +1286,108 @@ mod tests {
         let serialization = serialization.unwrap();
         assert_eq!(&[1, 16, 42][..], serialization);
     }
+
+    #[test]
+    fn serialize_ip_addr() {
+        use std::net::{
+            IpAddr,
+            Ipv4Addr,
+            Ipv6Addr,
+        };
+        let serialization = to_bytes(&Ipv4Addr::new(192, 168, 1, 2));
+        assert!(serialization.is_ok());
+        let serialization = serialization.unwrap();
+        assert_eq!(&[192, 168, 1, 2][..], serialization);
+        let serialization = to_bytes(&IpAddr::V6(Ipv6Addr::LOCALHOST));
+        assert!(serialization.is_ok());
+        let serialization = serialization.unwrap();
+        assert_eq!(
+            &[1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1][..],
+            serialization
+        );
+    }
+
+    #[test]
+    fn serialize_socket_addr() {
+        use std::net::{
+            Ipv4Addr,
+            SocketAddr,
+        };
+        let serialization =
+            to_bytes(&SocketAddr::from((Ipv4Addr::LOCALHOST, 8080)));
+        assert!(serialization.is_ok());
+        let serialization = serialization.unwrap();
+        assert_eq!(&[0, 127, 0, 0, 1, 0xBF, 0x10][..], serialization);
+    }
+
+    #[test]
+    fn serialize_into() {
+        let mut buffer = vec![0xFF];
+        assert!(to_bytes_into(&300_u32, &mut buffer).is_ok());
+        assert!(to_bytes_into("hi", &mut buffer).is_ok());
+        assert_eq!(&[0xFF, 0x82, 0x2C, 0x02, 0x68, 0x69][..], buffer);
+        let values: Vec<u32> = crate::Deserializer::new(&buffer[1..3])
+            .iterate()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(vec![300], values);
+    }
+
+    #[test]
+    fn serializer_accessors() {
+        let mut buffer = vec![0xFF];
+        let mut serializer = Serializer::new(&mut buffer);
+        assert!(!serializer.is_empty());
+        assert_eq!(1, serializer.len());
+        assert!(serde::Serialize::serialize(&true, &mut serializer).is_ok());
+        assert!(serde::Serialize::serialize(&-2_i8, &mut serializer).is_ok());
+        assert_eq!(3, serializer.len());
+        serializer.into_inner().push(0x00);
+        assert_eq!(&[0xFF, 0x01, 0xFE, 0x00][..], buffer);
+    }
+
+    #[test]
+    fn serialize_interned() {
+        let serialization = to_bytes_interned(&("ab", vec!["c", "ab", "ab"]));
+        assert!(serialization.is_ok());
+        let serialization = serialization.unwrap();
+        assert_eq!(&[4, b'a', b'b', 3, 2, b'c', 1, 1][..], serialization);
+        let mut buffer = Vec::new();
+        let mut serializer = Serializer::new_interned(&mut buffer);
+        assert!(serde::Serialize::serialize("ab", &mut serializer).is_ok());
+        assert!(serde::Serialize::serialize("ab", &mut serializer).is_ok());
+        assert_eq!(&[4, b'a', b'b', 1][..], buffer);
+    }
+
+    #[test]
+    fn serialize_indexed() {
+        #[derive(serde::Serialize)]
+        struct Point {
+            x: u16,
+            name: &'static str,
+        }
+        let serialization = to_bytes_indexed(&vec![
+            Point {
+                x: 300,
+                name: "a",
+            },
+            Point {
+                x: 1,
+                name: "",
+            },
+        ]);
+        assert!(serialization.is_ok());
+        let serialization = serialization.unwrap();
+        assert_eq!(
+            &[
+                2, 0, 0, 0, 13, 0, 0, 0, 24, 2, 0, 0, 0, 2, 0, 0, 0, 4, 0x82,
+                0x2C, 1, b'a', 2, 0, 0, 0, 1, 0, 0, 0, 2, 1, 0
+            ][..],
+            serialization
+        );
+        let serialization = to_bytes_indexed(&(Vec::<u8>::new(), 7_u8));
+        assert!(serialization.is_ok());
+        let serialization = serialization.unwrap();
+        assert_eq!(&[0, 7][..], serialization);
+    }
 }
@@ -0,0 +1,480 @@
+use std::{
+    any::Any,
+    cell::RefCell,
+    collections::HashMap,
+    fmt,
+    marker::PhantomData,
+    ops::Deref,
+    rc::Rc,
+    sync::{
+        atomic::{
+            AtomicU64,
+            Ordering,
+        },
+        Arc,
+    },
+};
+
+// This is the name of the newtype struct through which a shared value
+// passes to a serializer or deserializer.  The serializer and deserializer
+// of this crate recognize it and use the shared layout for the value inside.
+pub(crate) const NAME: &str = "$serialization::Shared";
+
+// This is what a `Shared` wrapper and the serializer or deserializer it is
+// used with pass to each other: a request for the value being decoded, or
+// a clone of the pointer to a value.
+enum Slot {
+    Empty,
+    Wanted,
+    Value(Rc<dyn Any>),
+}
+
+// These are the shared values met so far by one serializer or deserializer.
+// The index of an entry is the order in which its value was first met, and
+// a deserializer leaves the value out until it has been decoded.
+#[derive(Default)]
+struct Entries {
+    addresses: HashMap<usize, usize>,
+    values: Vec<(usize, Option<Rc<dyn Any>>)>,
+}
+
+thread_local! {
+    static SLOT: RefCell<Slot> = const { RefCell::new(Slot::Empty) };
+
+    // The tables of shared values are kept here, rather than in the
+    // serializers and deserializers, so that those can still be sent
+    // between threads when the values cannot.
+    static TABLES: RefCell<HashMap<u64, Entries>> =
+        RefCell::new(HashMap::new());
+}
+
+static NEXT_TABLE: AtomicU64 = AtomicU64::new(0);
+
+// Hand a clone of a pointer to the serializer or deserializer which is
+// about to be called, or to the `Shared` wrapper it is about to call.
+pub(crate) fn offer(value: Rc<dyn Any>) {
+    SLOT.with(|slot| *slot.borrow_mut() = Slot::Value(value));
+}
+
+// Ask the `Shared` wrapper which is about to be decoded for a clone of the
+// pointer it makes.
+pub(crate) fn request() {
+    SLOT.with(|slot| *slot.borrow_mut() = Slot::Wanted);
+}
+
+// Take the pointer which was handed over, if any, leaving nothing behind.
+pub(crate) fn take() -> Option<Rc<dyn Any>> {
+    match SLOT.with(|slot| slot.replace(Slot::Empty)) {
+        Slot::Value(value) => Some(value),
+        Slot::Empty | Slot::Wanted => None,
+    }
+}
+
+fn take_request() -> bool {
+    SLOT.with(|slot| {
+        let wanted = matches!(*slot.borrow(), Slot::Wanted);
+        if wanted {
+            *slot.borrow_mut() = Slot::Empty;
+        }
+        wanted
+    })
+}
+
+// This is a handle to the table of shared values of a serializer or
+// deserializer, which is removed when the handle is dropped.
+pub(crate) struct Table {
+    id: u64,
+}
+
+impl Table {
+    pub(crate) fn new() -> Self {
+        Self {
+            id: NEXT_TABLE.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    // Return the index of the entry for the value at the given address.
+    pub(crate) fn find(
+        &self,
+        address: usize,
+    ) -> Option<usize> {
+        self.with(|entries| entries.addresses.get(&address).copied())
+    }
+
+    // Return the value of the entry with the given index, if it has one.
+    pub(crate) fn get(
+        &self,
+        index: usize,
+    ) -> Option<Rc<dyn Any>> {
+        self.with(|entries| {
+            entries.values.get(index).and_then(|(_, value)| value.clone())
+        })
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.with(|entries| entries.values.len())
+    }
+
+    // Add an entry for the value at the given address, returning its index.
+    // Holding the value keeps the address from being reused by another.
+    pub(crate) fn push(
+        &self,
+        address: usize,
+        value: Option<Rc<dyn Any>>,
+    ) -> usize {
+        self.with(|entries| {
+            let index = entries.values.len();
+            if value.is_some() {
+                entries.addresses.insert(address, index);
+            }
+            entries.values.push((address, value));
+            index
+        })
+    }
+
+    pub(crate) fn set(
+        &self,
+        index: usize,
+        value: Rc<dyn Any>,
+    ) {
+        self.with(|entries| {
+            if let Some(entry) = entries.values.get_mut(index) {
+                entry.1 = Some(value);
+            }
+        });
+    }
+
+    // Remove the entries past the given number of them.  The values are
+    // dropped after the table is released, since dropping one may run code
+    // which uses another table.
+    pub(crate) fn truncate(
+        &self,
+        len: usize,
+    ) {
+        let removed = self.with(|entries| {
+            let removed = entries
+                .values
+                .drain(len.min(entries.values.len())..)
+                .collect::<Vec<_>>();
+            for (address, _) in &removed {
+                entries.addresses.remove(address);
+            }
+            removed
+        });
+        drop(removed);
+    }
+
+    fn with<F, R>(
+        &self,
+        f: F,
+    ) -> R
+    where
+        F: FnOnce(&mut Entries) -> R,
+    {
+        TABLES.with(|tables| f(tables.borrow_mut().entry(self.id).or_default()))
+    }
+}
+
+impl Drop for Table {
+    fn drop(&mut self) {
+        let entries = TABLES
+            .try_with(|tables| tables.borrow_mut().remove(&self.id))
+            .ok()
+            .flatten();
+        drop(entries);
+    }
+}
+
+/// This wraps an `Rc` or `Arc` pointer so that a value shared by several
+/// pointers is only encoded once, and decoded into a single value shared
+/// by the same number of pointers.
+///
+/// The first time a value is encoded by a serializer, it is preceded by a
+/// zero.  Every later time, it is encoded only as one more than the number
+/// of shared values which the serializer had encoded before it.  A value
+/// containing a pointer to itself can be encoded this way, but cannot be
+/// decoded.  Values first encoded within an [`Appendable`] struct are only
+/// shared within that struct.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate serialization;
+/// use serialization::Shared;
+/// use std::rc::Rc;
+///
+/// let name = Rc::new(String::from("shared"));
+/// let names = vec![Shared(Rc::clone(&name)), Shared(name)];
+/// let encoding = serialization::to_bytes(&names).unwrap();
+/// assert_eq!(&[2, 0, 6, b's', b'h', b'a', b'r', b'e', b'd', 1][..], encoding);
+/// let decoded: Vec<Shared<Rc<String>>> =
+///     serialization::from_bytes(&encoding).unwrap();
+/// assert!(Rc::ptr_eq(&decoded[0], &decoded[1]));
+/// ```
+///
+/// [`Appendable`]: struct.Appendable.html
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Shared<P>(pub P);
+
+impl<P> Shared<P> {
+    /// Return the wrapped pointer.
+    pub fn into_inner(self) -> P {
+        self.0
+    }
+}
+
+impl<P> Deref for Shared<P> {
+    type Target = P;
+
+    fn deref(&self) -> &P {
+        &self.0
+    }
+}
+
+impl<P> From<P> for Shared<P> {
+    fn from(pointer: P) -> Self {
+        Self(pointer)
+    }
+}
+
+struct Visitor<P> {
+    pointer: PhantomData<P>,
+}
+
+macro_rules! shared_pointer {
+    ($($pointer:ident),*) => {$(
+        impl<T> serde::Serialize for Shared<$pointer<T>>
+        where
+            T: serde::Serialize + 'static,
+        {
+            fn serialize<S>(
+                &self,
+                serializer: S,
+            ) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                offer(Rc::new($pointer::clone(&self.0)));
+                let result = serializer.serialize_newtype_struct(NAME, &*self.0);
+                take();
+                result
+            }
+        }
+
+        impl<'de, T> serde::de::Visitor<'de> for Visitor<$pointer<T>>
+        where
+            T: serde::Deserialize<'de> + 'static,
+        {
+            type Value = Shared<$pointer<T>>;
+
+            fn expecting(
+                &self,
+                f: &mut fmt::Formatter,
+            ) -> fmt::Result {
+                write!(f, "a shared value")
+            }
+
+            fn visit_newtype_struct<D>(
+                self,
+                deserializer: D,
+            ) -> Result<Self::Value, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                let wanted = take_request();
+                let pointer = $pointer::new(T::deserialize(deserializer)?);
+                if wanted {
+                    offer(Rc::new($pointer::clone(&pointer)));
+                }
+                Ok(Shared(pointer))
+            }
+
+            fn visit_unit<E>(self) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                take()
+                    .and_then(|value| {
+                        value.downcast_ref::<$pointer<T>>().map($pointer::clone)
+                    })
+                    .map(Shared)
+                    .ok_or_else(|| {
+                        E::invalid_type(
+                            serde::de::Unexpected::Other(
+                                "shared value of another type",
+                            ),
+                            &self,
+                        )
+                    })
+            }
+        }
+
+        impl<'de, T> serde::Deserialize<'de> for Shared<$pointer<T>>
+        where
+            T: serde::Deserialize<'de> + 'static,
+        {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                deserializer.deserialize_newtype_struct(NAME, Visitor::<
+                    $pointer<T>,
+                > {
+                    pointer: PhantomData,
+                })
+            }
+        }
+    )*};
+}
+
+shared_pointer!(Rc, Arc);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        from_bytes,
+        to_bytes,
+        Appendable,
+        Error,
+    };
+    use std::cell::RefCell;
+
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Node {
+        label: u8,
+        children: Vec<Shared<Rc<Node>>>,
+    }
+
+    fn leaf(label: u8) -> Rc<Node> {
+        Rc::new(Node {
+            label,
+            children: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn serialize_layout() {
+        let value = Rc::new(7_u16);
+        let serialization = to_bytes(&(
+            Shared(Rc::clone(&value)),
+            Shared(Rc::new(7_u16)),
+            Shared(value),
+        ));
+        assert!(serialization.is_ok());
+        let serialization = serialization.unwrap();
+        assert_eq!(&[0, 7, 0, 7, 1][..], serialization);
+    }
+
+    #[test]
+    fn diamond_keeps_identity() {
+        let bottom = leaf(3);
+        let left = Rc::new(Node {
+            label: 1,
+            children: vec![Shared(Rc::clone(&bottom))],
+        });
+        let right = Rc::new(Node {
+            label: 2,
+            children: vec![Shared(Rc::clone(&bottom))],
+        });
+        let top = Node {
+            label: 0,
+            children: vec![Shared(left), Shared(right), Shared(bottom)],
+        };
+        let encoding = to_bytes(&top).unwrap();
+        let deserialization = from_bytes::<Node>(&encoding);
+        assert!(deserialization.is_ok());
+        let deserialization = deserialization.unwrap();
+        assert_eq!(top, deserialization);
+        let children = &deserialization.children;
+        assert!(Rc::ptr_eq(&children[0].children[0], &children[2]));
+        assert!(Rc::ptr_eq(&children[1].children[0], &children[2]));
+        assert_eq!(3, Rc::strong_count(&children[2]));
+    }
+
+    #[test]
+    fn arc_keeps_identity() {
+        let value = Arc::new(String::from("a"));
+        let values = vec![Shared(Arc::clone(&value)), Shared(value)];
+        let encoding = to_bytes(&values).unwrap();
+        let deserialization: Vec<Shared<Arc<String>>> =
+            from_bytes(&encoding).unwrap();
+        assert!(Arc::ptr_eq(&deserialization[0], &deserialization[1]));
+    }
+
+    #[test]
+    fn serializer_holds_values() {
+        let mut buffer = Vec::new();
+        let mut serializer = crate::Serializer::new(&mut buffer);
+        for _ in 0..2 {
+            let value = Shared(Rc::new(5_u8));
+            assert!(
+                serde::Serialize::serialize(&value, &mut serializer).is_ok()
+            );
+        }
+        assert_eq!(&[0, 5, 0, 5][..], buffer);
+    }
+
+    #[test]
+    fn old_reader_skips_shared_values() {
+        #[derive(serde::Serialize)]
+        struct Version2 {
+            label: u8,
+            extra: Shared<Rc<u8>>,
+        }
+        #[derive(serde::Deserialize)]
+        struct Version1 {
+            label: u8,
+        }
+        let value = Rc::new(9_u8);
+        let encoding = to_bytes(&(
+            Appendable(Version2 {
+                label: 1,
+                extra: Shared(Rc::clone(&value)),
+            }),
+            Shared(value),
+        ))
+        .unwrap();
+        let deserialization =
+            from_bytes::<(Appendable<Version1>, Shared<Rc<u8>>)>(&encoding);
+        assert!(deserialization.is_ok());
+        let (message, value) = deserialization.unwrap();
+        assert_eq!(1, message.label);
+        assert_eq!(9, **value);
+    }
+
+    #[test]
+    fn deserialize_unknown_reference() {
+        assert!(matches!(
+            from_bytes::<Shared<Rc<u8>>>(&[1][..]),
+            Err(Error::SharedUnknown(0))
+        ));
+    }
+
+    #[test]
+    fn deserialize_other_type() {
+        assert!(matches!(
+            from_bytes::<(Shared<Rc<u8>>, Shared<Rc<i8>>)>(&[0, 5, 1][..]),
+            Err(Error::Message(_))
+        ));
+    }
+
+    #[test]
+    fn deserialize_cycle() {
+        #[derive(serde::Serialize, serde::Deserialize)]
+        struct Cycle {
+            next: RefCell<Option<Shared<Rc<Cycle>>>>,
+        }
+        let cycle = Rc::new(Cycle {
+            next: RefCell::new(None),
+        });
+        *cycle.next.borrow_mut() = Some(Shared(Rc::clone(&cycle)));
+        let encoding = to_bytes(&Shared(Rc::clone(&cycle))).unwrap();
+        cycle.next.borrow_mut().take();
+        assert_eq!(&[0, 1, 1][..], encoding);
+        assert!(matches!(
+            from_bytes::<Shared<Rc<Cycle>>>(&encoding),
+            Err(Error::SharedUnknown(0))
+        ));
+    }
+}
@@ -0,0 +1,397 @@
+use super::{
+    borrowed_slice::Bytes,
+    from_bytes,
+    to_bytes,
+};
+use serde::ser::{
+    Error as _,
+    SerializeTuple as _,
+};
+use std::{
+    convert::TryFrom,
+    fmt,
+    marker::PhantomData,
+};
+
+/// This is the encoding of one field of a struct deriving `Encode`: its tag
+/// followed by the encoding of its value as a block of bytes, so that a
+/// reader which does not know the tag can skip over it.
+pub struct TaggedField<'a, T: ?Sized>(pub u32, pub &'a T);
+
+impl<T> serde::Serialize for TaggedField<'_, T>
+where
+    T: ?Sized + serde::Serialize,
+{
+    fn serialize<S>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let value = to_bytes(self.1).map_err(S::Error::custom)?;
+        let mut tuple = serializer.serialize_tuple(2)?;
+        tuple.serialize_element(&self.0)?;
+        tuple.serialize_element(&Bytes(&value))?;
+        tuple.end()
+    }
+}
+
+/// This is one field of a struct deriving `Decode`, whose value has not yet
+/// been decoded because its type depends on the tag.
+pub struct RawField<'de> {
+    /// This is the tag identifying the field.
+    pub tag: u32,
+    bytes: &'de [u8],
+}
+
+impl RawField<'_> {
+    /// Decode the value of the field.
+    ///
+    /// # Errors
+    ///
+    /// Any error decoding the value is converted to the error type of the
+    /// deserializer which produced the field.
+    pub fn decode<T, E>(&self) -> Result<T, E>
+    where
+        T: serde::de::DeserializeOwned,
+        E: serde::de::Error,
+    {
+        from_bytes(self.bytes).map_err(E::custom)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for RawField<'de> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let (tag, bytes) = <(u32, &'de [u8])>::deserialize(deserializer)?;
+        Ok(Self {
+            tag,
+            bytes,
+        })
+    }
+}
+
+/// This decodes the contents of a tuple or struct variant of an enum
+/// deriving `Decode` as a tuple of the types of its fields.
+pub struct PositionalVisitor<T> {
+    value: PhantomData<T>,
+}
+
+impl<T> PositionalVisitor<T> {
+    /// Return a new visitor.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            value: PhantomData,
+        }
+    }
+}
+
+impl<T> Default for PositionalVisitor<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'de, T> serde::de::Visitor<'de> for PositionalVisitor<T>
+where
+    T: serde::Deserialize<'de>,
+{
+    type Value = T;
+
+    fn expecting(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        f.write_str("variant fields")
+    }
+
+    fn visit_seq<A>(
+        self,
+        seq: A,
+    ) -> Result<T, A::Error>
+    where
+        A: serde::de::SeqAccess<'de>,
+    {
+        T::deserialize(serde::de::value::SeqAccessDeserializer::new(seq))
+    }
+}
+
+/// This decodes the wire id of a variant of an enum deriving `Decode`,
+/// given the names of the variants indexed by id, where an empty name marks
+/// an id which no variant has.  Formats which identify
+/// variants by name rather than by index are also supported.
+pub struct VariantId(pub &'static [&'static str]);
+
+impl<'de> serde::de::DeserializeSeed<'de> for VariantId {
+    type Value = u32;
+
+    fn deserialize<D>(
+        self,
+        deserializer: D,
+    ) -> Result<u32, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_identifier(self)
+    }
+}
+
+impl serde::de::Visitor<'_> for VariantId {
+    type Value = u32;
+
+    fn expecting(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        f.write_str("variant id or name")
+    }
+
+    fn visit_u64<E>(
+        self,
+        v: u64,
+    ) -> Result<u32, E>
+    where
+        E: serde::de::Error,
+    {
+        usize::try_from(v)
+            .ok()
+            .filter(|&id| self.0.get(id).is_some_and(|name| !name.is_empty()))
+            .and_then(|id| u32::try_from(id).ok())
+            .ok_or_else(|| {
+                E::invalid_value(serde::de::Unexpected::Unsigned(v), &self)
+            })
+    }
+
+    fn visit_str<E>(
+        self,
+        v: &str,
+    ) -> Result<u32, E>
+    where
+        E: serde::de::Error,
+    {
+        let names = self.0;
+        names
+            .iter()
+            .position(|name| !name.is_empty() && *name == v)
+            .and_then(|id| u32::try_from(id).ok())
+            .ok_or_else(|| E::unknown_variant(v, names))
+    }
+}
+
+#[cfg(all(test, feature = "derive"))]
+mod tests {
+    use crate::{
+        from_bytes,
+        to_bytes,
+        Decode,
+        Encode,
+        Error,
+    };
+
+    #[derive(Encode, Decode, Debug, Default, PartialEq)]
+    struct Version1 {
+        #[ser(tag = 1)]
+        id: u32,
+        #[ser(tag = 2)]
+        name: String,
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    struct Version2 {
+        #[ser(tag = 2)]
+        name: String,
+        #[ser(tag = 3)]
+        tags: Vec<String>,
+        #[ser(tag = 1)]
+        id: u32,
+    }
+
+    #[derive(Encode, Decode, Debug, Default, PartialEq)]
+    struct Wrapper<T> {
+        #[ser(tag = 7)]
+        inner: T,
+        #[ser(tag = 8)]
+        seq: Option<u8>,
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct NoDefault(u8);
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    struct Optional<T> {
+        #[ser(tag = 1)]
+        value: Option<T>,
+        #[ser(tag = 2)]
+        count: u8,
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    enum Shape<T> {
+        #[ser(id = 4)]
+        Square {
+            side: T,
+            filled: bool,
+        },
+        #[ser(id = 1)]
+        Circle(T),
+        #[ser(id = 0)]
+        Empty,
+        #[ser(id = 9)]
+        Line(T, T),
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    enum ShapeVersion1 {
+        #[ser(id = 0)]
+        Empty,
+        #[ser(id = 1)]
+        Circle(u8),
+    }
+
+    #[test]
+    fn serialize_layout() {
+        let serialization = to_bytes(&Version1 {
+            id: 300,
+            name: String::from("a"),
+        });
+        assert!(serialization.is_ok());
+        let serialization = serialization.unwrap();
+        assert_eq!(&[2, 1, 2, 0x82, 0x2C, 2, 2, 1, 0x61][..], serialization);
+    }
+
+    #[test]
+    fn old_reader_skips_unknown_tags() {
+        let new = Version2 {
+            name: String::from("widget"),
+            tags: vec![String::from("blue")],
+            id: 42,
+        };
+        let old: Version1 = from_bytes(&to_bytes(&new).unwrap()).unwrap();
+        assert_eq!(
+            Version1 {
+                id: 42,
+                name: String::from("widget"),
+            },
+            old
+        );
+    }
+
+    #[test]
+    fn new_reader_defaults_missing_tags() {
+        let old = Version1 {
+            id: 7,
+            name: String::from("gadget"),
+        };
+        let new: Version2 = from_bytes(&to_bytes(&old).unwrap()).unwrap();
+        assert_eq!(
+            Version2 {
+                name: String::from("gadget"),
+                tags: vec![],
+                id: 7,
+            },
+            new
+        );
+    }
+
+    #[test]
+    fn generic_struct() {
+        let value = Wrapper {
+            inner: Version1 {
+                id: 1,
+                name: String::from("x"),
+            },
+            seq: Some(9),
+        };
+        let round_trip: Wrapper<Version1> =
+            from_bytes(&to_bytes(&value).unwrap()).unwrap();
+        assert_eq!(value, round_trip);
+        let missing: Wrapper<u16> = from_bytes(&[0][..]).unwrap();
+        assert_eq!(Wrapper::default(), missing);
+    }
+
+    #[test]
+    fn generic_field_with_default() {
+        let value = Optional {
+            value: Some(NoDefault(3)),
+            count: 1,
+        };
+        let round_trip: Optional<NoDefault> =
+            from_bytes(&to_bytes(&value).unwrap()).unwrap();
+        assert_eq!(value, round_trip);
+        let missing: Optional<NoDefault> = from_bytes(&[0][..]).unwrap();
+        assert_eq!(
+            Optional {
+                value: None,
+                count: 0,
+            },
+            missing
+        );
+    }
+
+    #[test]
+    fn enum_variants_use_pinned_ids() {
+        for (value, expected) in [
+            (
+                Shape::Square {
+                    side: 3_u8,
+                    filled: true,
+                },
+                &[4, 3, 1][..],
+            ),
+            (Shape::Circle(2), &[1, 2][..]),
+            (Shape::Empty, &[0][..]),
+            (Shape::Line(5, 6), &[9, 5, 6][..]),
+        ] {
+            let serialization = to_bytes(&value);
+            assert!(serialization.is_ok());
+            let serialization = serialization.unwrap();
+            assert_eq!(expected, serialization);
+            assert_eq!(value, from_bytes(&serialization).unwrap());
+        }
+    }
+
+    #[test]
+    fn enum_unknown_variant() {
+        let line = to_bytes(&Shape::Line(1_u8, 2)).unwrap();
+        assert!(matches!(
+            from_bytes::<ShapeVersion1>(&line),
+            Err(Error::UnknownVariant(9))
+        ));
+        assert!(matches!(
+            from_bytes::<Shape<u8>>(&[2][..]),
+            Err(Error::UnknownVariant(2))
+        ));
+        let circle = to_bytes(&Shape::Circle(7_u8)).unwrap();
+        assert_eq!(
+            ShapeVersion1::Circle(7),
+            from_bytes::<ShapeVersion1>(&circle).unwrap()
+        );
+    }
+
+    #[test]
+    fn enum_schema_skips_unused_ids() {
+        let schema = crate::schema_of::<Shape<u8>>();
+        assert!(schema.is_ok());
+        let schema = schema.unwrap();
+        let crate::Schema::Enum {
+            variants,
+            ..
+        } = schema
+        else {
+            panic!("expected enum schema");
+        };
+        let mut ids = variants
+            .iter()
+            .map(|variant| (variant.name.as_str(), variant.index))
+            .collect::<Vec<_>>();
+        ids.sort_unstable_by_key(|&(_, index)| index);
+        assert_eq!(
+            vec![("Empty", 0), ("Circle", 1), ("Square", 4), ("Line", 9)],
+            ids
+        );
+    }
+}
@@ -0,0 +1,161 @@
+//! This module provides functions for use with serde's `with` field
+//! attribute in order to encode a vector of unsigned integers using exactly
+//! the same layout as the C++ `SerializedUnsignedIntegerVector` class: the
+//! number of elements, followed by each element as a variable-length unsigned
+//! integer.
+//!
+//! Elements of any unsigned integer type which converts losslessly to `u64`
+//! may be used.  Unlike a plain `Vec<u8>`, which encodes each element as a
+//! single raw byte, a vector encoded through this module always uses the
+//! variable-length encoding, so that it can be read by the C++ class.
+//!
+//! The [`delta`], [`rle`], and [`delta_rle`] submodules provide optional
+//! packings of the same data which are more compact for sorted or
+//! repetitive sequences.  These packings are not understood by the C++
+//! implementation.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # extern crate serialization;
+//! # extern crate serde;
+//! #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+//! struct Samples {
+//!     #[serde(with = "serialization::uint_vec")]
+//!     raw: Vec<u8>,
+//!     #[serde(with = "serialization::uint_vec::delta")]
+//!     timestamps: Vec<u64>,
+//! }
+//!
+//! let samples = Samples {
+//!     raw: vec![1, 200],
+//!     timestamps: vec![1_000_000, 1_000_001, 1_000_003],
+//! };
+//! let encoding = serialization::to_bytes(&samples).unwrap();
+//! assert_eq!(&[2, 1, 0x81, 0x48, 3, 0xBD, 0x84, 0x40, 1, 2][..], encoding);
+//! assert_eq!(samples, serialization::from_bytes(&encoding).unwrap());
+//! ```
+//!
+//! [`delta`]: delta/index.html
+//! [`rle`]: rle/index.html
+//! [`delta_rle`]: delta_rle/index.html
+
+crate::int_seq::packings! {
+    u64,
+    "SerializedUnsignedIntegerVector",
+    /// This module encodes each element after the first as its difference from
+    /// the element before it, which keeps the encoding of a vector sorted in
+    /// ascending order compact even when the elements themselves are large.
+    /// Differences are computed with wrapping arithmetic, so a decreasing
+    /// element is still encoded correctly, but not compactly.
+    mod delta;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        from_bytes,
+        to_bytes,
+        Error,
+        Result,
+    };
+
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    struct Plain(#[serde(with = "super")] Vec<u32>);
+
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    struct Delta(#[serde(with = "super::delta")] Vec<u64>);
+
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    struct Rle(#[serde(with = "super::rle")] Vec<u8>);
+
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    struct DeltaRle(#[serde(with = "super::delta_rle")] Vec<u64>);
+
+    #[test]
+    fn plain_matches_cpp_layout() {
+        let value = Plain(vec![42, 255, 40000]);
+        let serialization = to_bytes(&value);
+        assert!(serialization.is_ok());
+        let serialization = serialization.unwrap();
+        assert_eq!(&[3, 42, 0x81, 0x7F, 0x82, 0xB8, 0x40][..], serialization);
+        let deserialization: Result<Plain> = from_bytes(&serialization);
+        assert!(deserialization.is_ok());
+        assert_eq!(value, deserialization.unwrap());
+    }
+
+    #[test]
+    fn element_out_of_range() {
+        let deserialization: Result<Rle> = from_bytes(&[1, 0x82, 0x00, 1][..]);
+        assert!(matches!(deserialization, Err(Error::Message(_))));
+    }
+
+    #[test]
+    fn delta_round_trip() {
+        for (value, expected) in &[
+            (vec![], &[0][..]),
+            (
+                vec![100, 101, 99],
+                &[
+                    3, 0x64, 0x01, 0x81, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+                    0xFF, 0xFF, 0x7E,
+                ][..],
+            ),
+            (
+                vec![u64::MAX, 0],
+                &[
+                    2, 0x81, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+                    0x7F, 0x01,
+                ][..],
+            ),
+        ] {
+            let value = Delta(value.clone());
+            let serialization = to_bytes(&value);
+            assert!(serialization.is_ok());
+            let serialization = serialization.unwrap();
+            assert_eq!(*expected, serialization);
+            let deserialization: Result<Delta> = from_bytes(&serialization);
+            assert!(deserialization.is_ok());
+            assert_eq!(value, deserialization.unwrap());
+        }
+    }
+
+    #[test]
+    fn rle_round_trip() {
+        let value = Rle(vec![7, 7, 7, 200, 7]);
+        let serialization = to_bytes(&value);
+        assert!(serialization.is_ok());
+        let serialization = serialization.unwrap();
+        assert_eq!(&[3, 7, 3, 0x81, 0x48, 1, 7, 1][..], serialization);
+        let deserialization: Result<Rle> = from_bytes(&serialization);
+        assert!(deserialization.is_ok());
+        assert_eq!(value, deserialization.unwrap());
+    }
+
+    #[test]
+    fn rle_empty_run() {
+        let deserialization: Result<Rle> = from_bytes(&[1, 7, 0][..]);
+        assert!(matches!(deserialization, Err(Error::Message(_))));
+    }
+
+    #[test]
+    fn rle_run_too_long() {
+        let deserialization: Result<Rle> = from_bytes(
+            &[1, 7, 0x81, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x7F]
+                [..],
+        );
+        assert!(matches!(deserialization, Err(Error::Message(_))));
+    }
+
+    #[test]
+    fn delta_rle_round_trip() {
+        let value = DeltaRle((1000..1100).collect());
+        let serialization = to_bytes(&value);
+        assert!(serialization.is_ok());
+        let serialization = serialization.unwrap();
+        assert_eq!(&[2, 0x87, 0x68, 1, 1, 0x63][..], serialization);
+        let deserialization: Result<DeltaRle> = from_bytes(&serialization);
+        assert!(deserialization.is_ok());
+        assert_eq!(value, deserialization.unwrap());
+    }
+}
@@ -0,0 +1,164 @@
+/*
+ * This program exercises the functions declared in serialization.h.  It is
+ * compiled and run by tests/capi.rs, and exits with a nonzero status,
+ * after naming the failed check, if any check fails.
+ */
+
+#include <serialization.h>
+
+#include <stdio.h>
+#include <string.h>
+
+#define CHECK(condition) \
+    do { \
+        if (!(condition)) { \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, \
+                #condition); \
+            return 1; \
+        } \
+    } while (0)
+
+#define TEXT(literal) literal, sizeof(literal) - 1
+
+static const uint8_t INTEGER_ENCODING[] = {3, 1, 'n', 0x41};
+
+static int check_scalars(void) {
+    SerializationValue *value = serialization_value_new_integer(-1);
+    uint8_t *data;
+    size_t len;
+    CHECK(serialization_encode(value, &data, &len)
+          == SERIALIZATION_STATUS_OK);
+    CHECK(len == sizeof(INTEGER_ENCODING));
+    CHECK(memcmp(data, INTEGER_ENCODING, len) == 0);
+    serialization_bytes_free(data, len);
+    serialization_value_free(value);
+
+    SerializationValue *decoded;
+    CHECK(serialization_decode(INTEGER_ENCODING, sizeof(INTEGER_ENCODING),
+              &decoded)
+          == SERIALIZATION_STATUS_OK);
+    CHECK(serialization_value_kind(decoded) == SERIALIZATION_KIND_INTEGER);
+    int32_t integer = 0;
+    CHECK(serialization_value_integer(decoded, &integer)
+          == SERIALIZATION_STATUS_OK);
+    CHECK(integer == -1);
+    bool boolean;
+    CHECK(serialization_value_boolean(decoded, &boolean)
+          == SERIALIZATION_STATUS_WRONG_KIND);
+    serialization_value_free(decoded);
+
+    CHECK(serialization_decode(INTEGER_ENCODING, 2, &decoded)
+          == SERIALIZATION_STATUS_DECODE_FAILED);
+    CHECK(serialization_value_new_string(TEXT("\xFF")) == NULL);
+    return 0;
+}
+
+static int check_containers(void) {
+    SerializationValue *collection = serialization_value_new_collection();
+    SerializationValue *vector = serialization_value_new_vector();
+    CHECK(serialization_vector_push(vector,
+              serialization_value_new_string(TEXT("h\xC3\xA9llo")))
+          == SERIALIZATION_STATUS_OK);
+    CHECK(serialization_vector_push(vector,
+              serialization_value_new_unsigned_integer(UINT64_MAX))
+          == SERIALIZATION_STATUS_OK);
+    CHECK(serialization_collection_set(collection, TEXT("items"), vector)
+          == SERIALIZATION_STATUS_OK);
+    CHECK(serialization_collection_set(collection, TEXT("address"),
+              serialization_value_new_ip_address(0xC0A80102))
+          == SERIALIZATION_STATUS_OK);
+    const int32_t samples[] = {-1, 0, 70000};
+    CHECK(serialization_collection_set(collection, TEXT("samples"),
+              serialization_value_new_integer_vector(samples, 3))
+          == SERIALIZATION_STATUS_OK);
+    CHECK(serialization_vector_push(collection,
+              serialization_value_new_empty())
+          == SERIALIZATION_STATUS_WRONG_KIND);
+
+    uint8_t *data;
+    size_t len;
+    CHECK(serialization_encode(collection, &data, &len)
+          == SERIALIZATION_STATUS_OK);
+    serialization_value_free(collection);
+    SerializationValue *decoded;
+    CHECK(serialization_decode(data, len, &decoded)
+          == SERIALIZATION_STATUS_OK);
+    serialization_bytes_free(data, len);
+
+    size_t count;
+    CHECK(serialization_value_len(decoded, &count) == SERIALIZATION_STATUS_OK);
+    CHECK(count == 3);
+    const char *name;
+    size_t name_len;
+    const SerializationValue *element;
+    CHECK(serialization_collection_entry(decoded, 0, &name, &name_len,
+              &element)
+          == SERIALIZATION_STATUS_OK);
+    CHECK(name_len == 7 && memcmp(name, "address", 7) == 0);
+    uint32_t address;
+    CHECK(serialization_value_ip_address(element, &address)
+          == SERIALIZATION_STATUS_OK);
+    CHECK(address == 0xC0A80102);
+    CHECK(serialization_collection_entry(decoded, 3, &name, &name_len,
+              &element)
+          == SERIALIZATION_STATUS_OUT_OF_RANGE);
+    CHECK(serialization_collection_entry(decoded, 0, &name, &name_len, NULL)
+          == SERIALIZATION_STATUS_NULL_ARGUMENT);
+
+    const char *names[3];
+    size_t found = 0;
+    SerializationStatus status = serialization_collection_first(decoded,
+        &name, &name_len, &element);
+    while (status == SERIALIZATION_STATUS_OK && found < 3) {
+        names[found++] = name;
+        status = serialization_collection_next(decoded, name, name_len,
+            &name, &name_len, &element);
+    }
+    CHECK(status == SERIALIZATION_STATUS_OUT_OF_RANGE && found == 3);
+    CHECK(memcmp(names[0], "address", 7) == 0);
+    CHECK(memcmp(names[1], "items", 5) == 0);
+    CHECK(memcmp(names[2], "samples", 7) == 0);
+    CHECK(serialization_collection_next(decoded, TEXT("missing"), &name,
+              &name_len, &element)
+          == SERIALIZATION_STATUS_NOT_FOUND);
+
+    const SerializationValue *items;
+    CHECK(serialization_collection_get(decoded, TEXT("items"), &items)
+          == SERIALIZATION_STATUS_OK);
+    CHECK(serialization_value_kind(items) == SERIALIZATION_KIND_VECTOR);
+    CHECK(serialization_vector_get(items, 0, &element)
+          == SERIALIZATION_STATUS_OK);
+    const char *text;
+    size_t text_len;
+    CHECK(serialization_value_string(element, &text, &text_len)
+          == SERIALIZATION_STATUS_OK);
+    CHECK(text_len == 6 && memcmp(text, "h\xC3\xA9llo", 6) == 0);
+    CHECK(serialization_vector_get(items, 1, &element)
+          == SERIALIZATION_STATUS_OK);
+    uint64_t unsigned_integer;
+    CHECK(serialization_value_unsigned_integer(element, &unsigned_integer)
+          == SERIALIZATION_STATUS_OK);
+    CHECK(unsigned_integer == UINT64_MAX);
+    CHECK(serialization_vector_get(items, 2, &element)
+          == SERIALIZATION_STATUS_OUT_OF_RANGE);
+
+    const SerializationValue *samples_value;
+    CHECK(serialization_collection_get(decoded, TEXT("samples"),
+              &samples_value)
+          == SERIALIZATION_STATUS_OK);
+    int32_t sample;
+    CHECK(serialization_integer_vector_get(samples_value, 2, &sample)
+          == SERIALIZATION_STATUS_OK);
+    CHECK(sample == 70000);
+    CHECK(serialization_collection_get(decoded, TEXT("missing"), &element)
+          == SERIALIZATION_STATUS_NOT_FOUND);
+    serialization_value_free(decoded);
+    return 0;
+}
+
+int main(void) {
+    if (check_scalars() != 0 || check_containers() != 0) {
+        return 1;
+    }
+    return 0;
+}
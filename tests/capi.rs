@@ -0,0 +1,59 @@
+// This compiles tests/capi.c with the system C compiler, against the
+// header in `include` and a static library built from this crate, and runs
+// it.
+#![cfg(feature = "capi")]
+
+use std::{
+    fs,
+    path::{
+        Path,
+        PathBuf,
+    },
+    process::Command,
+};
+
+// Build this crate as a static library with the `capi` feature, in a
+// target directory of its own so as not to contend with the build running
+// this test, and return the path of the library.
+fn static_library() -> PathBuf {
+    let target_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("capi-target");
+    let status = Command::new(env!("CARGO"))
+        .args(["rustc", "--lib", "--features", "capi", "--manifest-path"])
+        .arg(Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(&target_dir)
+        .args(["--crate-type", "staticlib"])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    target_dir.join("debug").join("libserialization.a")
+}
+
+#[test]
+fn header_up_to_date() {
+    let generated = Path::new(env!("OUT_DIR")).join("serialization.h");
+    assert!(
+        include_str!("../include/serialization.h")
+            == fs::read_to_string(&generated).unwrap(),
+        "include/serialization.h is out of date; copy {} over it",
+        generated.display()
+    );
+}
+
+#[test]
+fn c_program() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let program = Path::new(env!("CARGO_TARGET_TMPDIR")).join("capi");
+    let status = Command::new("cc")
+        .args(["-std=c99", "-Wall", "-Wextra", "-Werror", "-I"])
+        .arg(manifest_dir.join("include"))
+        .arg(manifest_dir.join("tests").join("capi.c"))
+        .arg(static_library())
+        .args(["-lpthread", "-ldl", "-lm", "-o"])
+        .arg(&program)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let status = Command::new(&program).status().unwrap();
+    assert!(status.success());
+}